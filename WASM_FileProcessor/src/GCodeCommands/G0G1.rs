@@ -1,5 +1,6 @@
 use crate::gcode_line::{GCodeLine, MoveData, Vector3, Color4};
 use crate::processor_properties::ProcessorProperties;
+use crate::error::ProcessingError;
 use crate::utils::parse_parameter;
 
 /// Ultra-fast G0/G1 move parser optimized for the most common G-code commands
@@ -9,10 +10,10 @@ pub fn parse_g0_g1_move(
     line: &str, 
     file_position: u32, 
     line_number: u32
-) -> Result<GCodeLine, String> {
+) -> Result<GCodeLine, ProcessingError> {
     
     let bytes = line.as_bytes();
-    let mut move_data = MoveData::new(file_position, line_number, line.to_string());
+    let mut move_data = MoveData::new(file_position, line_number, line);
     
     // Copy current position as start position
     move_data.start = props.current_position.clone();
@@ -130,6 +131,7 @@ pub fn parse_g0_g1_move(
     if is_g1 {
         // Use slicer feature color instead of tool color for proper rendering
         move_data.color = props.current_feature_color.clone();
+        move_data.feature = props.current_feature.clone();
         move_data.extruding = move_data.extruding || props.cnc_mode;
     }
     
@@ -145,22 +147,30 @@ pub fn parse_g0_g1_move(
     ];
     
     // Handle extrusion
+    let mut extruded = 0.0;
     if let Some(e_value) = e {
         if e_value > 0.0 {
             move_data.extruding = true;
         }
+        extruded = if props.absolute_extrusion { e_value - props.current_e } else { e_value };
+        props.current_e = if props.absolute_extrusion { e_value } else { props.current_e + e_value };
     }
     
+    // Line width from the filament volume pushed over the move
+    move_data.layer_height = props.layer_height;
+    move_data.width = props.current_tool.extrusion_width(extruded, move_data.length(), move_data.layer_height);
+    
     // Set move type based on extrusion
     if !move_data.extruding {
         move_data.tool = 255; // Travel moves use tool 255
     }
     
-    // Handle feed rate
+    // F is modal for G0 and G1 alike; the feed rate range only covers extrusions
     if let Some(f_value) = f {
-        if move_data.extruding {
-            props.update_feed_rate(f_value);
-        }
+        props.current_feed_rate = f_value;
+    }
+    if move_data.extruding {
+        props.update_feed_rate(props.current_feed_rate);
     }
     
     move_data.feed_rate = props.current_feed_rate;
@@ -301,4 +311,48 @@ mod tests {
             assert_eq!(move_data.feed_rate, 1500.0);
         }
     }
+
+    #[test]
+    fn test_extrusion_width_from_filament_diameter() {
+        let width = |filament_diameter: f64| {
+            let mut props = ProcessorProperties::new();
+            props.current_tool.filament_diameter = filament_diameter;
+            props.layer_height = 0.2;
+            parse_g0_g1_move(&mut props, "G1 X10 F1200", 0, 1).unwrap();
+            match parse_g0_g1_move(&mut props, "G1 X20 E0.4", 13, 2) {
+                Ok(GCodeLine::Move(move_data)) => move_data.width,
+                _ => panic!("expected a move"),
+            }
+        };
+
+        // 0.4 mm of filament over 10 mm at 0.2 mm layers
+        let thin = width(1.75);
+        let thick = width(2.85);
+        assert!((thin - 0.4 * std::f64::consts::PI * 0.875f64.powi(2) / 2.0).abs() < 1e-9);
+        assert!((thin - 0.481).abs() < 1e-3);
+        assert!((thick / thin - (2.85f64 / 1.75).powi(2)).abs() < 1e-9);
+
+        // Travel keeps the nozzle size
+        let mut props = ProcessorProperties::new();
+        match parse_g0_g1_move(&mut props, "G0 X10", 0, 1) {
+            Ok(GCodeLine::Move(move_data)) => assert_eq!(move_data.width, props.current_tool.diameter),
+            _ => panic!("expected a move"),
+        }
+    }
+
+    #[test]
+    fn test_feed_rate_is_modal_for_travels() {
+        let mut props = ProcessorProperties::new();
+
+        let feed_rate = |result: Result<GCodeLine, ProcessingError>| match result {
+            Ok(GCodeLine::Move(move_data)) => move_data.feed_rate,
+            _ => panic!("expected a move"),
+        };
+        assert_eq!(feed_rate(parse_g0_g1_move(&mut props, "G0 X50 F9000", 0, 1)), 9000.0);
+        assert_eq!(feed_rate(parse_g0_g1_move(&mut props, "G1 F1200", 13, 2)), 1200.0);
+        assert_eq!(feed_rate(parse_g0_g1_move(&mut props, "G1 X60 E2", 22, 3)), 1200.0);
+
+        // Only the extrusion counts towards the feed rate range
+        assert_eq!((props.min_feed_rate, props.max_feed_rate), (1200.0, 1200.0));
+    }
 }
\ No newline at end of file
@@ -1,5 +1,6 @@
 use crate::gcode_line::{GCodeLine, CommandData};
 use crate::processor_properties::ProcessorProperties;
+use crate::error::ProcessingError;
 
 /// Parse G10 (Firmware Retraction) command
 /// G10: Enable firmware retraction (retract filament)
@@ -8,13 +9,13 @@ pub fn parse_g10_retract(
     line: &str,
     file_position: u32,
     line_number: u32,
-) -> Result<GCodeLine, String> {
+) -> Result<GCodeLine, ProcessingError> {
     
     // Enable firmware retraction
     properties.firmware_retraction = true;
     
     // Create command data
-    let cmd_data = CommandData::new(file_position, line_number, line.to_string(), "G10".to_string());
+    let cmd_data = CommandData::new(file_position, line_number, line, "G10");
     Ok(GCodeLine::Command(cmd_data))
 }
 
@@ -25,13 +26,13 @@ pub fn parse_g11_unretract(
     line: &str,
     file_position: u32,
     line_number: u32,
-) -> Result<GCodeLine, String> {
+) -> Result<GCodeLine, ProcessingError> {
     
     // Disable firmware retraction
     properties.firmware_retraction = false;
     
     // Create command data
-    let cmd_data = CommandData::new(file_position, line_number, line.to_string(), "G11".to_string());
+    let cmd_data = CommandData::new(file_position, line_number, line, "G11");
     Ok(GCodeLine::Command(cmd_data))
 }
 
@@ -1,5 +1,6 @@
 use crate::gcode_line::{GCodeLine, CommandData};
 use crate::processor_properties::{ProcessorProperties, Units};
+use crate::error::ProcessingError;
 
 /// Parse G20 (Inches Units) command
 /// G20: Set units to inches
@@ -8,13 +9,13 @@ pub fn parse_g20_inches(
     line: &str,
     file_position: u32,
     line_number: u32,
-) -> Result<GCodeLine, String> {
+) -> Result<GCodeLine, ProcessingError> {
     
     // Set units to inches
     properties.units = Units::Inches;
     
     // Create command data
-    let cmd_data = CommandData::new(file_position, line_number, line.to_string(), "G20".to_string());
+    let cmd_data = CommandData::new(file_position, line_number, line, "G20");
     Ok(GCodeLine::Command(cmd_data))
 }
 
@@ -25,13 +26,13 @@ pub fn parse_g21_millimeters(
     line: &str,
     file_position: u32,
     line_number: u32,
-) -> Result<GCodeLine, String> {
+) -> Result<GCodeLine, ProcessingError> {
     
     // Set units to millimeters
     properties.units = Units::Millimeters;
     
     // Create command data
-    let cmd_data = CommandData::new(file_position, line_number, line.to_string(), "G21".to_string());
+    let cmd_data = CommandData::new(file_position, line_number, line, "G21");
     Ok(GCodeLine::Command(cmd_data))
 }
 
@@ -1,5 +1,6 @@
 use crate::gcode_line::{GCodeLine, CommandData};
 use crate::processor_properties::ProcessorProperties;
+use crate::error::ProcessingError;
 use crate::utils::{parse_number_fast, skip_whitespace};
 
 /// Parse G28 (Auto Home) command
@@ -10,7 +11,7 @@ pub fn parse_g28_home(
     line: &str,
     file_position: u32,
     line_number: u32,
-) -> Result<GCodeLine, String> {
+) -> Result<GCodeLine, ProcessingError> {
     
     let line_bytes = line.as_bytes();
     let mut pos = 0;
@@ -44,19 +45,19 @@ pub fn parse_g28_home(
         match param_char {
             'X' | 'x' => {
                 home_x = true;
-                parameters.push(("X".to_string(), 0.0));
+                parameters.push(('X', 0.0));
             }
             'Y' | 'y' => {
                 home_y = true;
-                parameters.push(("Y".to_string(), 0.0));
+                parameters.push(('Y', 0.0));
             }
             'Z' | 'z' => {
                 home_z = true;
-                parameters.push(("Z".to_string(), 0.0));
+                parameters.push(('Z', 0.0));
             }
             'E' | 'e' => {
                 home_e = true;
-                parameters.push(("E".to_string(), 0.0));
+                parameters.push(('E', 0.0));
             }
             ';' => break, // Comment start
             _ => {
@@ -74,10 +75,10 @@ pub fn parse_g28_home(
         home_y = true;  
         home_z = true;
         home_e = true;
-        parameters.push(("X".to_string(), 0.0));
-        parameters.push(("Y".to_string(), 0.0));
-        parameters.push(("Z".to_string(), 0.0));
-        parameters.push(("E".to_string(), 0.0));
+        parameters.push(('X', 0.0));
+        parameters.push(('Y', 0.0));
+        parameters.push(('Z', 0.0));
+        parameters.push(('E', 0.0));
     }
     
     // Update processor state - homing resets positions to 0
@@ -95,7 +96,7 @@ pub fn parse_g28_home(
     }
     
     // Create command data
-    let mut cmd_data = CommandData::new(file_position, line_number, line.to_string(), "G28".to_string());
+    let mut cmd_data = CommandData::new(file_position, line_number, line, "G28");
     cmd_data.parameters = parameters;
     
     Ok(GCodeLine::Command(cmd_data))
@@ -107,10 +108,10 @@ pub fn parse_g29_bed_leveling(
     line: &str,
     file_position: u32,
     line_number: u32,
-) -> Result<GCodeLine, String> {
+) -> Result<GCodeLine, ProcessingError> {
     
     // G29 just triggers bed leveling - no state changes needed
-    let cmd_data = CommandData::new(file_position, line_number, line.to_string(), "G29".to_string());
+    let cmd_data = CommandData::new(file_position, line_number, line, "G29");
     Ok(GCodeLine::Command(cmd_data))
 }
 
@@ -1,5 +1,6 @@
 use crate::gcode_line::{GCodeLine, ArcMove, Vector3};
 use crate::processor_properties::ProcessorProperties;
+use crate::error::ProcessingError;
 use crate::utils::{parse_number_fast, skip_whitespace, tessellate_arc, ArcPlane};
 
 /// Parse G2 (clockwise arc) and G3 (counter-clockwise arc) commands
@@ -12,7 +13,7 @@ pub fn parse_arc_move(
     is_clockwise: bool,
     file_position: u32,
     line_number: u32,
-) -> Result<GCodeLine, String> {
+) -> Result<GCodeLine, ProcessingError> {
     
     let mut x = properties.current_position.x;
     let mut y = properties.current_position.y;
@@ -46,63 +47,63 @@ pub fn parse_arc_move(
         
         match param_char {
             'X' | 'x' => {
-                let parse_result = parse_number_fast(&line_bytes, pos).ok_or("Failed to parse number")?;
+                let parse_result = parse_number_fast(&line_bytes, pos).ok_or_else(|| ProcessingError::malformed_number(line, file_position, line_number))?;
                 let value = parse_result.value;
                 let new_pos = pos + parse_result.consumed_bytes;
                 x = if properties.absolute_positioning { value } else { properties.current_position.x + value };
                 pos = new_pos;
             }
             'Y' | 'y' => {
-                let parse_result = parse_number_fast(&line_bytes, pos).ok_or("Failed to parse number")?;
+                let parse_result = parse_number_fast(&line_bytes, pos).ok_or_else(|| ProcessingError::malformed_number(line, file_position, line_number))?;
                 let value = parse_result.value;
                 let new_pos = pos + parse_result.consumed_bytes;
                 y = if properties.absolute_positioning { value } else { properties.current_position.y + value };
                 pos = new_pos;
             }
             'Z' | 'z' => {
-                let parse_result = parse_number_fast(&line_bytes, pos).ok_or("Failed to parse number")?;
+                let parse_result = parse_number_fast(&line_bytes, pos).ok_or_else(|| ProcessingError::malformed_number(line, file_position, line_number))?;
                 let value = parse_result.value;
                 let new_pos = pos + parse_result.consumed_bytes;
                 z = if properties.absolute_positioning { value } else { properties.current_position.z + value };
                 pos = new_pos;
             }
             'I' | 'i' => {
-                let parse_result = parse_number_fast(&line_bytes, pos).ok_or("Failed to parse number")?;
+                let parse_result = parse_number_fast(&line_bytes, pos).ok_or_else(|| ProcessingError::malformed_number(line, file_position, line_number))?;
                 let value = parse_result.value;
                 let new_pos = pos + parse_result.consumed_bytes;
                 i = value;
                 pos = new_pos;
             }
             'J' | 'j' => {
-                let parse_result = parse_number_fast(&line_bytes, pos).ok_or("Failed to parse number")?;
+                let parse_result = parse_number_fast(&line_bytes, pos).ok_or_else(|| ProcessingError::malformed_number(line, file_position, line_number))?;
                 let value = parse_result.value;
                 let new_pos = pos + parse_result.consumed_bytes;
                 j = value;
                 pos = new_pos;
             }
             'K' | 'k' => {
-                let parse_result = parse_number_fast(&line_bytes, pos).ok_or("Failed to parse number")?;
+                let parse_result = parse_number_fast(&line_bytes, pos).ok_or_else(|| ProcessingError::malformed_number(line, file_position, line_number))?;
                 let value = parse_result.value;
                 let new_pos = pos + parse_result.consumed_bytes;
                 k = value;
                 pos = new_pos;
             }
             'R' | 'r' => {
-                let parse_result = parse_number_fast(&line_bytes, pos).ok_or("Failed to parse number")?;
+                let parse_result = parse_number_fast(&line_bytes, pos).ok_or_else(|| ProcessingError::malformed_number(line, file_position, line_number))?;
                 let value = parse_result.value;
                 let new_pos = pos + parse_result.consumed_bytes;
                 radius = Some(value);
                 pos = new_pos;
             }
             'E' | 'e' => {
-                let parse_result = parse_number_fast(&line_bytes, pos).ok_or("Failed to parse number")?;
+                let parse_result = parse_number_fast(&line_bytes, pos).ok_or_else(|| ProcessingError::malformed_number(line, file_position, line_number))?;
                 let value = parse_result.value;
                 let new_pos = pos + parse_result.consumed_bytes;
                 e = if properties.absolute_extrusion { value } else { properties.current_e + value };
                 pos = new_pos;
             }
             'F' | 'f' => {
-                let parse_result = parse_number_fast(&line_bytes, pos).ok_or("Failed to parse number")?;
+                let parse_result = parse_number_fast(&line_bytes, pos).ok_or_else(|| ProcessingError::malformed_number(line, file_position, line_number))?;
                 let value = parse_result.value;
                 let new_pos = pos + parse_result.consumed_bytes;
                 feed_rate = value;
@@ -171,7 +172,7 @@ pub fn parse_arc_move(
     let arc_move = ArcMove {
         file_position,
         line_number,
-        original_line: line.to_string(),
+        line_length: line.len() as u32,
         tool: properties.current_tool.tool_number,
         start: start_pos,
         end: end_pos,
@@ -1,5 +1,6 @@
 use crate::gcode_line::{GCodeLine, CommandData};
 use crate::processor_properties::ProcessorProperties;
+use crate::error::ProcessingError;
 
 /// Parse G90 (Absolute Positioning) command
 /// G90: All coordinates are absolute
@@ -8,13 +9,13 @@ pub fn parse_g90_absolute(
     line: &str,
     file_position: u32,
     line_number: u32,
-) -> Result<GCodeLine, String> {
+) -> Result<GCodeLine, ProcessingError> {
     
     // Set absolute positioning mode
     properties.absolute_positioning = true;
     
     // Create command data
-    let cmd_data = CommandData::new(file_position, line_number, line.to_string(), "G90".to_string());
+    let cmd_data = CommandData::new(file_position, line_number, line, "G90");
     Ok(GCodeLine::Command(cmd_data))
 }
 
@@ -25,13 +26,13 @@ pub fn parse_g91_relative(
     line: &str,
     file_position: u32,
     line_number: u32,
-) -> Result<GCodeLine, String> {
+) -> Result<GCodeLine, ProcessingError> {
     
     // Set relative positioning mode
     properties.absolute_positioning = false;
     
     // Create command data
-    let cmd_data = CommandData::new(file_position, line_number, line.to_string(), "G91".to_string());
+    let cmd_data = CommandData::new(file_position, line_number, line, "G91");
     Ok(GCodeLine::Command(cmd_data))
 }
 
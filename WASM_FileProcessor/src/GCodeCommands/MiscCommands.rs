@@ -1,5 +1,6 @@
 use crate::gcode_line::{GCodeLine, CommandData, MCodeData};
 use crate::processor_properties::ProcessorProperties;
+use crate::error::{ErrorKind, ProcessingError};
 
 /// Parse workplace coordinate system commands (G54-G59.3)
 /// G54-G59: Select coordinate system 1-6
@@ -10,7 +11,7 @@ pub fn parse_workplace_coordinates(
     command: &str,
     file_position: u32,
     line_number: u32,
-) -> Result<GCodeLine, String> {
+) -> Result<GCodeLine, ProcessingError> {
     
     // Map G-code command to workspace index
     let workspace_idx = match command {
@@ -23,7 +24,9 @@ pub fn parse_workplace_coordinates(
         "G59.1" => 6,
         "G59.2" => 7,
         "G59.3" => 8,
-        _ => return Err(format!("Unknown workplace command: {}", command)),
+        _ => return Err(ProcessingError::new(ErrorKind::UnknownCommand, format!("Unknown workplace command: {}", command))
+            .at(file_position, line_number)
+            .with_context(line)),
     };
     
     // Update current workplace if valid index
@@ -35,8 +38,8 @@ pub fn parse_workplace_coordinates(
     let cmd_data = CommandData::new(
         file_position, 
         line_number, 
-        line.to_string(), 
-        command.to_string()
+        line, 
+        command
     );
     Ok(GCodeLine::Command(cmd_data))
 }
@@ -50,7 +53,7 @@ pub fn parse_m3_m4_spindle(
     command: &str,
     file_position: u32,
     line_number: u32,
-) -> Result<GCodeLine, String> {
+) -> Result<GCodeLine, ProcessingError> {
     
     // For CNC mode, this affects spindle state
     if properties.cnc_mode {
@@ -62,11 +65,13 @@ pub fn parse_m3_m4_spindle(
     let mcode_num = match command {
         "M3" => 3,
         "M4" => 4,
-        _ => return Err(format!("Invalid spindle command: {}", command)),
+        _ => return Err(ProcessingError::new(ErrorKind::UnknownCommand, format!("Invalid spindle command: {}", command))
+            .at(file_position, line_number)
+            .with_context(line)),
     };
     
     // Create M-code data
-    let mcode_data = MCodeData::new(file_position, line_number, line.to_string(), mcode_num);
+    let mcode_data = MCodeData::new(file_position, line_number, line, mcode_num);
     Ok(GCodeLine::MCode(mcode_data))
 }
 
@@ -76,7 +81,7 @@ pub fn parse_m5_spindle_stop(
     line: &str,
     file_position: u32,
     line_number: u32,
-) -> Result<GCodeLine, String> {
+) -> Result<GCodeLine, ProcessingError> {
     
     // For CNC mode, stop spindle
     if properties.cnc_mode {
@@ -85,7 +90,7 @@ pub fn parse_m5_spindle_stop(
     }
     
     // Create M-code data
-    let mcode_data = MCodeData::new(file_position, line_number, line.to_string(), 5);
+    let mcode_data = MCodeData::new(file_position, line_number, line, 5);
     Ok(GCodeLine::MCode(mcode_data))
 }
 
@@ -96,13 +101,13 @@ pub fn parse_m567_mixing(
     line: &str,
     file_position: u32,
     line_number: u32,
-) -> Result<GCodeLine, String> {
+) -> Result<GCodeLine, ProcessingError> {
     
     // Enable mixing mode
     properties.has_mixing = true;
     
     // Create M-code data
-    let mut mcode_data = MCodeData::new(file_position, line_number, line.to_string(), 567);
+    let mut mcode_data = MCodeData::new(file_position, line_number, line, 567);
     
     // TODO: Parse mixing ratios from parameters if needed
     // This would require parsing E0, E1, E2, etc. parameters
@@ -117,13 +122,13 @@ pub fn parse_m600_filament_change(
     line: &str,
     file_position: u32,
     line_number: u32,
-) -> Result<GCodeLine, String> {
+) -> Result<GCodeLine, ProcessingError> {
     
     // M600 typically pauses the print for filament change
     // No specific state changes needed in processor
     
     // Create M-code data
-    let mcode_data = MCodeData::new(file_position, line_number, line.to_string(), 600);
+    let mcode_data = MCodeData::new(file_position, line_number, line, 600);
     Ok(GCodeLine::MCode(mcode_data))
 }
 
@@ -133,10 +138,10 @@ pub fn parse_blank_line(
     line: &str,
     file_position: u32,
     line_number: u32,
-) -> Result<GCodeLine, String> {
+) -> Result<GCodeLine, ProcessingError> {
     
     // Empty lines are treated as comments
-    Ok(GCodeLine::new_comment(file_position, line_number, line.to_string()))
+    Ok(GCodeLine::new_comment(file_position, line_number, line))
 }
 
 #[cfg(test)]
@@ -151,6 +156,9 @@ mod tests {
         let result = parse_workplace_coordinates(&mut props, "G54", "G54", 100, 1);
         assert!(result.is_ok());
         assert_eq!(props.current_workplace_idx, 0); // Should switch to G54 (index 0)
+        
+        let error = parse_workplace_coordinates(&mut props, "G60", "G60", 120, 2).unwrap_err();
+        assert_eq!((error.kind(), error.line_number()), (ErrorKind::UnknownCommand, Some(2)));
     }
     
     #[test]
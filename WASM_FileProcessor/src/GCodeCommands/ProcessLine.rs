@@ -1,5 +1,6 @@
-use crate::gcode_line::{GCodeLine, CommentData, CommandData, MCodeData};
+use crate::gcode_line::{GCodeLine, CommandName, CommentData, CommandData, MCodeData};
 use crate::processor_properties::ProcessorProperties;
+use crate::error::ProcessingError;
 use crate::utils::{is_comment_line, detect_gcode_command, parse_parameter};
 use crate::GCodeCommands::G0G1::{parse_g0_g1_move, is_g0_g1_command};
 use crate::GCodeCommands::G2G3::parse_arc_move;
@@ -17,7 +18,7 @@ pub fn process_line(
     line: &str,
     file_position: u32,
     line_number: u32,
-) -> Result<GCodeLine, String> {
+) -> Result<GCodeLine, ProcessingError> {
     
     let trimmed_line = line.trim();
     
@@ -26,7 +27,7 @@ pub fn process_line(
         return Ok(GCodeLine::Comment(CommentData::new(
             file_position,
             line_number,
-            line.to_string(),
+            line,
         )));
     }
     
@@ -37,7 +38,8 @@ pub fn process_line(
     
     // Detect other G-code commands
     if let Some(command) = detect_gcode_command(trimmed_line) {
-        let command_upper = command.to_uppercase();
+        // Uppercased on the stack; no allocation per line
+        let command_upper = CommandName::uppercase(command);
         
         match command_upper.as_str() {
             // Positioning modes
@@ -66,10 +68,10 @@ pub fn process_line(
             
             // Workplace coordinates
             "G54" | "G55" | "G56" | "G57" | "G58" | "G59" => {
-                return parse_workplace_coordinates(props, line, &command_upper, file_position, line_number);
+                return parse_workplace_coordinates(props, line, command_upper.as_str(), file_position, line_number);
             }
             "G59.1" | "G59.2" | "G59.3" => {
-                return parse_workplace_coordinates(props, line, &command_upper, file_position, line_number);
+                return parse_workplace_coordinates(props, line, command_upper.as_str(), file_position, line_number);
             }
             
             // G2/G3 Arc moves
@@ -90,9 +92,20 @@ pub fn process_line(
                 return parse_g29_bed_leveling(props, line, file_position, line_number);
             }
             
+            // G92 Set position; only the extruder axis is tracked
+            "G92" => {
+                let command = create_command(file_position, line_number, line, command_upper.as_str());
+                if let GCodeLine::Command(data) = &command {
+                    if let Some(&(_, e)) = data.parameters.iter().find(|(letter, _)| *letter == 'E') {
+                        props.current_e = e;
+                    }
+                }
+                return Ok(command);
+            }
+            
             // M-codes - route to appropriate parsers
             _ if command_upper.starts_with('M') => {
-                let mcode_num = command_upper[1..].parse::<u32>().unwrap_or(0);
+                let mcode_num = command_upper.as_str()[1..].parse::<u32>().unwrap_or(0);
                 match mcode_num {
                     // Temperature and tool-related M-codes
                     104 | 109 | 140 | 190 | 106 | 107 => {
@@ -130,7 +143,7 @@ pub fn process_line(
             
             // Other G-codes
             _ => {
-                return Ok(create_command(file_position, line_number, line, command_upper));
+                return Ok(create_command(file_position, line_number, line, command_upper.as_str()));
             }
         }
     }
@@ -139,7 +152,7 @@ pub fn process_line(
     Ok(GCodeLine::Comment(CommentData::new(
         file_position,
         line_number,
-        line.to_string(),
+        line,
     )))
 }
 
@@ -150,7 +163,7 @@ fn parse_mcode(
     file_position: u32,
     line_number: u32,
     command: &str,
-) -> Result<GCodeLine, String> {
+) -> Result<GCodeLine, ProcessingError> {
     
     // Extract M-code number
     let mcode_num = if let Some(num_str) = command.strip_prefix('M') {
@@ -159,7 +172,7 @@ fn parse_mcode(
         0
     };
     
-    let mut mcode_data = MCodeData::new(file_position, line_number, line.to_string(), mcode_num);
+    let mut mcode_data = MCodeData::new(file_position, line_number, line, mcode_num);
     
     // Parse parameters
     let bytes = line.as_bytes();
@@ -167,7 +180,7 @@ fn parse_mcode(
     
     while i < bytes.len() {
         if let Some((letter, value, consumed)) = parse_parameter(bytes, i) {
-            mcode_data.parameters.push((letter.to_string(), value));
+            mcode_data.parameters.push((letter, value));
             i += consumed;
             
             // Handle specific M-code behaviors
@@ -201,7 +214,7 @@ fn parse_tool_change(
     file_position: u32,
     line_number: u32,
     command: &str,
-) -> Result<GCodeLine, String> {
+) -> Result<GCodeLine, ProcessingError> {
     
     // Extract tool number
     let tool_num = if let Some(num_str) = command.strip_prefix('T') {
@@ -213,7 +226,7 @@ fn parse_tool_change(
     // Update current tool
     props.set_current_tool(tool_num);
     
-    Ok(create_command(file_position, line_number, line, command.to_string()))
+    Ok(create_command(file_position, line_number, line, command))
 }
 
 /// Create a generic command object
@@ -221,9 +234,9 @@ fn create_command(
     file_position: u32,
     line_number: u32,
     line: &str,
-    command_type: String,
+    command_type: &str,
 ) -> GCodeLine {
-    let mut cmd_data = CommandData::new(file_position, line_number, line.to_string(), command_type);
+    let mut cmd_data = CommandData::new(file_position, line_number, line, command_type);
     
     // Parse any parameters
     let bytes = line.as_bytes();
@@ -231,7 +244,7 @@ fn create_command(
     
     while i < bytes.len() {
         if let Some((letter, value, consumed)) = parse_parameter(bytes, i) {
-            cmd_data.parameters.push((letter.to_string(), value));
+            cmd_data.parameters.push((letter, value));
             i += consumed;
         } else {
             i += 1;
@@ -250,7 +263,7 @@ pub fn detect_line_type(line: &str) -> LineType {
     } else if is_g0_g1_command(trimmed) {
         LineType::Move
     } else if let Some(command) = detect_gcode_command(trimmed) {
-        let upper = command.to_uppercase();
+        let upper = CommandName::uppercase(command);
         if upper.starts_with('M') {
             LineType::MCode
         } else if upper.starts_with('G') {
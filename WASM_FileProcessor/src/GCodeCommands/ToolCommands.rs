@@ -1,5 +1,6 @@
-use crate::gcode_line::{GCodeLine, ToolCommand};
+use crate::gcode_line::{CommandName, GCodeLine, ToolCommand};
 use crate::processor_properties::ProcessorProperties;
+use crate::error::ProcessingError;
 use crate::utils::{parse_number_fast, skip_whitespace};
 
 /// Parse tool change commands (T0, T1, etc.) and related M-codes
@@ -8,7 +9,7 @@ pub fn parse_tool_command(
     line: &str,
     file_position: u32,
     line_number: u32,
-) -> Result<GCodeLine, String> {
+) -> Result<GCodeLine, ProcessingError> {
     
     let line_bytes = line.as_bytes();
     let mut pos = 0;
@@ -44,7 +45,7 @@ pub fn parse_tool_command(
         match param_char {
             'T' | 't' => {
                 // Tool parameter in M-code (e.g., M104 T1 S200)
-                let parse_result = parse_number_fast(&line_bytes, pos).ok_or("Failed to parse number")?;
+                let parse_result = parse_number_fast(&line_bytes, pos).ok_or_else(|| ProcessingError::malformed_number(line, file_position, line_number))?;
                 let value = parse_result.value;
                 let new_pos = pos + parse_result.consumed_bytes;
                 tool_number = Some(value as u32);
@@ -52,7 +53,7 @@ pub fn parse_tool_command(
             }
             'S' | 's' => {
                 // Temperature parameter
-                let parse_result = parse_number_fast(&line_bytes, pos).ok_or("Failed to parse number")?;
+                let parse_result = parse_number_fast(&line_bytes, pos).ok_or_else(|| ProcessingError::malformed_number(line, file_position, line_number))?;
                 let value = parse_result.value;
                 let new_pos = pos + parse_result.consumed_bytes;
                 temperature = Some(value);
@@ -60,7 +61,7 @@ pub fn parse_tool_command(
             }
             'P' | 'p' => {
                 // Some tool commands use P for tool number
-                let parse_result = parse_number_fast(&line_bytes, pos).ok_or("Failed to parse number")?;
+                let parse_result = parse_number_fast(&line_bytes, pos).ok_or_else(|| ProcessingError::malformed_number(line, file_position, line_number))?;
                 let value = parse_result.value;
                 let new_pos = pos + parse_result.consumed_bytes;
                 if tool_number.is_none() {
@@ -106,6 +107,12 @@ pub fn parse_tool_command(
         }
     }
     
+    if command == "M106" {
+        properties.fan_speed = temperature.unwrap_or(255.0);
+    } else if command == "M107" {
+        properties.fan_speed = 0.0;
+    }
+    
     if let Some(temp) = temperature {
         if command == "M104" || command == "M109" {
             properties.target_hotend_temp = temp;
@@ -115,13 +122,13 @@ pub fn parse_tool_command(
     }
     
     let tool_cmd = ToolCommand {
-        command_type: command_type.to_string(),
+        command_type: CommandName::new(command_type),
         tool_number,
         temperature,
         wait_for_temperature,
         file_position,
         line_number,
-        original_line: line.to_string(),
+        line_length: line.len() as u32,
     };
     
     Ok(GCodeLine::Tool(tool_cmd))
@@ -133,7 +140,7 @@ pub fn parse_m_command(
     line: &str,
     file_position: u32,
     line_number: u32,
-) -> Result<GCodeLine, String> {
+) -> Result<GCodeLine, ProcessingError> {
     
     let line_bytes = line.as_bytes();
     let mut pos = 0;
@@ -144,7 +151,7 @@ pub fn parse_m_command(
     }
     
     let command = &line[..pos];
-    let mut parameters = Vec::new();
+    let mut s_value: Option<f64> = None;
     
     // Parse parameters
     while pos < line_bytes.len() {
@@ -165,7 +172,15 @@ pub fn parse_m_command(
         if let Some(parse_result) = parse_number_fast(line.as_bytes(), pos) {
             let value = parse_result.value;
             let new_pos = pos + parse_result.consumed_bytes;
-            parameters.push((param_char, value));
+            match (command, param_char) {
+                (_, 'S') => s_value = Some(value),
+                // Set steps per unit - store for reference
+                ("M92", 'X' | 'x') => properties.steps_per_mm_x = value,
+                ("M92", 'Y' | 'y') => properties.steps_per_mm_y = value,
+                ("M92", 'Z' | 'z') => properties.steps_per_mm_z = value,
+                ("M92", 'E' | 'e') => properties.steps_per_mm_e = value,
+                _ => {}
+            }
             pos = new_pos;
         } else {
             // Skip invalid parameter
@@ -193,31 +208,19 @@ pub fn parse_m_command(
             // Relative extrusion mode
             properties.absolute_extrusion = false;
         }
-        "M92" => {
-            // Set steps per unit - store for reference
-            for (param, value) in &parameters {
-                match param {
-                    'X' | 'x' => properties.steps_per_mm_x = *value,
-                    'Y' | 'y' => properties.steps_per_mm_y = *value,
-                    'Z' | 'z' => properties.steps_per_mm_z = *value,
-                    'E' | 'e' => properties.steps_per_mm_e = *value,
-                    _ => {}
-                }
-            }
-        }
         _ => {
             // Generic M-code handling
         }
     }
     
     let tool_cmd = ToolCommand {
-        command_type: format!("M_COMMAND_{}", &command[1..]), // M84 -> M_COMMAND_84
+        command_type: CommandName::concat("M_COMMAND_", &command[1..]), // M84 -> M_COMMAND_84
         tool_number: None,
-        temperature: parameters.iter().find(|(c, _)| *c == 'S').map(|(_, v)| *v),
+        temperature: s_value,
         wait_for_temperature: false,
         file_position,
         line_number,
-        original_line: line.to_string(),
+        line_length: line.len() as u32,
     };
     
     Ok(GCodeLine::Tool(tool_cmd))
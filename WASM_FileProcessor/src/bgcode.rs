@@ -0,0 +1,414 @@
+// PrusaSlicer binary G-code (.bgcode) reader
+// Layout: file header ("GCDE", version, checksum type) followed by blocks.
+// Each block is: header (type, compression, sizes), block parameters, payload and an optional CRC32.
+
+use crate::meatpack::decode_meatpack;
+use crate::thumbnails::Thumbnail;
+use serde::{Deserialize, Serialize};
+
+pub const BGCODE_MAGIC: &[u8; 4] = b"GCDE";
+
+// Block types
+const BLOCK_FILE_METADATA: u16 = 0;
+const BLOCK_GCODE: u16 = 1;
+const BLOCK_SLICER_METADATA: u16 = 2;
+const BLOCK_PRINTER_METADATA: u16 = 3;
+const BLOCK_PRINT_METADATA: u16 = 4;
+const BLOCK_THUMBNAIL: u16 = 5;
+
+// Compression types
+const COMPRESSION_NONE: u16 = 0;
+const COMPRESSION_DEFLATE: u16 = 1;
+const COMPRESSION_HEATSHRINK_11_4: u16 = 2;
+const COMPRESSION_HEATSHRINK_12_4: u16 = 3;
+
+// G-code block encodings
+const GCODE_ENCODING_NONE: u16 = 0;
+const GCODE_ENCODING_MEATPACK: u16 = 1;
+const GCODE_ENCODING_MEATPACK_COMMENTS: u16 = 2;
+
+const CHECKSUM_NONE: u16 = 0;
+const CHECKSUM_CRC32: u16 = 1;
+
+/// Key/value metadata blocks, in file order
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct BgcodeMetadata {
+    pub version: u32, // Format version from the file header
+    pub file: Vec<(String, String)>,
+    pub printer: Vec<(String, String)>,
+    pub print: Vec<(String, String)>,
+    pub slicer: Vec<(String, String)>,
+}
+
+impl BgcodeMetadata {
+    /// Value of a file metadata key such as "Producer"
+    pub fn file_value(&self, key: &str) -> Option<&str> {
+        self.file.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+}
+
+/// Decoded contents of a binary G-code file
+#[derive(Clone, Debug, Default)]
+pub struct BgcodeFile {
+    pub metadata: BgcodeMetadata,
+    pub thumbnails: Vec<Thumbnail>,
+    pub gcode: String,
+}
+
+/// True if the data starts with the binary G-code magic
+pub fn is_bgcode(data: &[u8]) -> bool {
+    data.len() >= 4 && &data[..4] == BGCODE_MAGIC
+}
+
+impl BgcodeFile {
+    /// Validate and decode a complete .bgcode file
+    pub fn decode(data: &[u8]) -> Result<BgcodeFile, String> {
+        if !is_bgcode(data) {
+            return Err("Not a binary G-code file (missing GCDE header)".to_string());
+        }
+
+        let mut reader = ByteReader::new(data);
+        reader.skip(4)?;
+        let version = reader.read_u32()?;
+        let checksum_type = reader.read_u16()?;
+        if checksum_type != CHECKSUM_NONE && checksum_type != CHECKSUM_CRC32 {
+            return Err(format!("Unsupported bgcode checksum type {}", checksum_type));
+        }
+
+        let mut file = BgcodeFile::default();
+        file.metadata.version = version;
+        let mut gcode = Vec::new();
+
+        while !reader.is_empty() {
+            let block_start = reader.position();
+            let block_type = reader.read_u16()?;
+            let compression = reader.read_u16()?;
+            let uncompressed_size = reader.read_u32()? as usize;
+            let stored_size = if compression == COMPRESSION_NONE {
+                uncompressed_size
+            } else {
+                reader.read_u32()? as usize
+            };
+
+            let params_size = match block_type {
+                BLOCK_THUMBNAIL => 6,
+                BLOCK_FILE_METADATA | BLOCK_GCODE | BLOCK_SLICER_METADATA
+                | BLOCK_PRINTER_METADATA | BLOCK_PRINT_METADATA => 2,
+                _ => return Err(format!("Unknown bgcode block type {} at byte {}", block_type, block_start)),
+            };
+            let params = reader.take(params_size)?;
+            let payload = reader.take(stored_size)?;
+
+            if checksum_type == CHECKSUM_CRC32 {
+                let block_end = reader.position();
+                let expected = reader.read_u32()?;
+                if crc32(&data[block_start..block_end]) != expected {
+                    return Err(format!("bgcode block CRC mismatch at byte {}", block_start));
+                }
+            }
+
+            let content = decompress(payload, compression, uncompressed_size)
+                .map_err(|e| format!("{} (block at byte {})", e, block_start))?;
+
+            match block_type {
+                BLOCK_THUMBNAIL => {
+                    let format = match u16::from_le_bytes([params[0], params[1]]) {
+                        0 => "PNG",
+                        1 => "JPG",
+                        2 => "QOI",
+                        other => return Err(format!("Unknown bgcode thumbnail format {}", other)),
+                    };
+                    let width = u16::from_le_bytes([params[2], params[3]]) as u32;
+                    let height = u16::from_le_bytes([params[4], params[5]]) as u32;
+                    file.thumbnails.push(Thumbnail::new(format, width, height, content));
+                }
+                BLOCK_GCODE => {
+                    match u16::from_le_bytes([params[0], params[1]]) {
+                        GCODE_ENCODING_NONE => gcode.extend_from_slice(&content),
+                        GCODE_ENCODING_MEATPACK | GCODE_ENCODING_MEATPACK_COMMENTS => {
+                            gcode.extend_from_slice(decode_meatpack(&content).as_bytes());
+                        }
+                        other => return Err(format!("Unknown bgcode G-code encoding {}", other)),
+                    }
+                }
+                _ => {
+                    let pairs = parse_ini(&content);
+                    match block_type {
+                        BLOCK_FILE_METADATA => file.metadata.file = pairs,
+                        BLOCK_PRINTER_METADATA => file.metadata.printer = pairs,
+                        BLOCK_PRINT_METADATA => file.metadata.print = pairs,
+                        _ => file.metadata.slicer = pairs,
+                    }
+                }
+            }
+        }
+
+        file.gcode = String::from_utf8(gcode).map_err(|_| "bgcode G-code is not valid UTF-8".to_string())?;
+        Ok(file)
+    }
+
+    /// Rebuild ASCII G-code: producer header, print metadata comments, body and the
+    /// slicer config block, so slicer detection and metadata parsing work unchanged
+    pub fn to_gcode_text(&self) -> String {
+        let mut text = String::with_capacity(self.gcode.len() + 64 * 1024);
+
+        if let Some(producer) = self.metadata.file_value("Producer") {
+            text.push_str("; generated by ");
+            text.push_str(producer);
+            text.push('\n');
+        }
+        for (key, value) in self.metadata.printer.iter().chain(self.metadata.print.iter()) {
+            text.push_str(&format!("; {} = {}\n", key, value));
+        }
+        text.push('\n');
+
+        text.push_str(&self.gcode);
+        if !self.gcode.ends_with('\n') {
+            text.push('\n');
+        }
+
+        if !self.metadata.slicer.is_empty() {
+            text.push_str("\n; prusaslicer_config = begin\n");
+            for (key, value) in &self.metadata.slicer {
+                text.push_str(&format!("; {} = {}\n", key, value));
+            }
+            text.push_str("; prusaslicer_config = end\n");
+        }
+
+        text
+    }
+}
+
+// Metadata blocks are INI-style "key=value" lines
+fn parse_ini(content: &[u8]) -> Vec<(String, String)> {
+    String::from_utf8_lossy(content)
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+fn decompress(payload: &[u8], compression: u16, uncompressed_size: usize) -> Result<Vec<u8>, String> {
+    let data = match compression {
+        COMPRESSION_NONE => payload.to_vec(),
+        COMPRESSION_DEFLATE => miniz_oxide::inflate::decompress_to_vec_zlib(payload)
+            .map_err(|e| format!("Deflate error: {:?}", e.status))?,
+        COMPRESSION_HEATSHRINK_11_4 => heatshrink_decompress(payload, 11, 4, uncompressed_size),
+        COMPRESSION_HEATSHRINK_12_4 => heatshrink_decompress(payload, 12, 4, uncompressed_size),
+        other => return Err(format!("Unknown bgcode compression {}", other)),
+    };
+
+    if data.len() != uncompressed_size {
+        return Err(format!("Decompressed size {} does not match expected {}", data.len(), uncompressed_size));
+    }
+    Ok(data)
+}
+
+/// Heatshrink (LZSS) decoder
+/// Stream of MSB-first bits: tag 1 = 8-bit literal, tag 0 = back-reference
+/// with `window_bits` index and `lookahead_bits` count, both stored minus one
+pub fn heatshrink_decompress(input: &[u8], window_bits: u8, lookahead_bits: u8, expected_size: usize) -> Vec<u8> {
+    let mut output = Vec::with_capacity(expected_size);
+    let mut bits = BitReader::new(input);
+
+    while output.len() < expected_size {
+        let Some(tag) = bits.read(1) else { break };
+
+        if tag == 1 {
+            let Some(byte) = bits.read(8) else { break };
+            output.push(byte as u8);
+            continue;
+        }
+
+        let Some(index) = bits.read(window_bits) else { break };
+        let Some(count) = bits.read(lookahead_bits) else { break };
+        let offset = index as usize + 1;
+
+        for _ in 0..=count {
+            // The window starts zero-filled
+            let byte = if offset > output.len() { 0 } else { output[output.len() - offset] };
+            output.push(byte);
+        }
+    }
+
+    output.truncate(expected_size);
+    output
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    bit_position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, bit_position: 0 }
+    }
+
+    fn read(&mut self, count: u8) -> Option<u32> {
+        if self.bit_position + count as usize > self.data.len() * 8 {
+            return None;
+        }
+        let mut value = 0u32;
+        for _ in 0..count {
+            let byte = self.data[self.bit_position / 8];
+            let bit = (byte >> (7 - self.bit_position % 8)) & 1;
+            value = (value << 1) | bit as u32;
+            self.bit_position += 1;
+        }
+        Some(value)
+    }
+}
+
+// Little-endian cursor with bounds-checked reads
+struct ByteReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn position(&self) -> usize {
+        self.position
+    }
+
+    fn is_empty(&self) -> bool {
+        self.position >= self.data.len()
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        if self.data.len() - self.position < count {
+            return Err(format!("Truncated bgcode file at byte {}", self.position));
+        }
+        let slice = &self.data[self.position..self.position + count];
+        self.position += count;
+        Ok(slice)
+    }
+
+    fn skip(&mut self, count: usize) -> Result<(), String> {
+        self.take(count).map(|_| ())
+    }
+
+    fn read_u16(&mut self) -> Result<u16, String> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, String> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Standard CRC-32 (IEEE 802.3)
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc = CRC32_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Append one block with a CRC32 trailer
+    fn push_block(file: &mut Vec<u8>, block_type: u16, compression: u16, uncompressed: usize, params: &[u8], payload: &[u8]) {
+        let start = file.len();
+        file.extend_from_slice(&block_type.to_le_bytes());
+        file.extend_from_slice(&compression.to_le_bytes());
+        file.extend_from_slice(&(uncompressed as u32).to_le_bytes());
+        if compression != COMPRESSION_NONE {
+            file.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        }
+        file.extend_from_slice(params);
+        file.extend_from_slice(payload);
+        let crc = crc32(&file[start..]);
+        file.extend_from_slice(&crc.to_le_bytes());
+    }
+
+    fn sample_file() -> Vec<u8> {
+        let mut file = Vec::new();
+        file.extend_from_slice(BGCODE_MAGIC);
+        file.extend_from_slice(&1u32.to_le_bytes());
+        file.extend_from_slice(&CHECKSUM_CRC32.to_le_bytes());
+
+        let file_meta = b"Producer=PrusaSlicer 2.7.0\n";
+        push_block(&mut file, BLOCK_FILE_METADATA, COMPRESSION_NONE, file_meta.len(), &[0, 0], file_meta);
+
+        let print_meta = b"estimated printing time (normal mode)=1h 0m 0s\n";
+        let deflated = miniz_oxide::deflate::compress_to_vec_zlib(print_meta, 6);
+        push_block(&mut file, BLOCK_PRINT_METADATA, COMPRESSION_DEFLATE, print_meta.len(), &[0, 0], &deflated);
+
+        let png = [0x89, b'P', b'N', b'G'];
+        push_block(&mut file, BLOCK_THUMBNAIL, COMPRESSION_NONE, png.len(), &[0, 0, 16, 0, 12, 0], &png);
+
+        // MeatPack "G1 X10\n"
+        let packed = [0xFF, 0xFF, 0xFB, 0x1D, 0xEB, 0x01, 0x0C];
+        push_block(&mut file, BLOCK_GCODE, COMPRESSION_NONE, packed.len(), &[1, 0], &packed);
+
+        let slicer_meta = b"layer_height = 0.2\n";
+        push_block(&mut file, BLOCK_SLICER_METADATA, COMPRESSION_NONE, slicer_meta.len(), &[0, 0], slicer_meta);
+        file
+    }
+
+    #[test]
+    fn test_decode_bgcode() {
+        let file = BgcodeFile::decode(&sample_file()).unwrap();
+        assert_eq!(file.metadata.version, 1);
+        assert_eq!(file.metadata.file_value("Producer"), Some("PrusaSlicer 2.7.0"));
+        assert_eq!(file.metadata.print[0].1, "1h 0m 0s");
+        assert_eq!(file.thumbnails.len(), 1);
+        assert_eq!((file.thumbnails[0].width(), file.thumbnails[0].height()), (16, 12));
+        assert_eq!(file.gcode, "G1 X10\n");
+
+        let text = file.to_gcode_text();
+        assert!(text.starts_with("; generated by PrusaSlicer 2.7.0\n"));
+        assert!(text.contains("; estimated printing time (normal mode) = 1h 0m 0s\n"));
+        assert!(text.contains("; layer_height = 0.2\n; prusaslicer_config = end"));
+    }
+
+    #[test]
+    fn test_crc_mismatch_is_rejected() {
+        let mut data = sample_file();
+        let last = data.len() - 1;
+        data[last] ^= 0xFF;
+        assert!(BgcodeFile::decode(&data).unwrap_err().contains("CRC"));
+        assert!(BgcodeFile::decode(b"; plain text").is_err());
+    }
+
+    #[test]
+    fn test_heatshrink_literals_and_backrefs() {
+        // "abab": literal 'a', literal 'b', back-reference offset 2 (index 1) length 2 (count 1)
+        // Bits: 1 01100001 | 1 01100010 | 0 00000000001 0001 (window 11, lookahead 4)
+        let bits = "1011000011011000100000000000010001";
+        let mut bytes = vec![0u8; bits.len().div_ceil(8)];
+        for (i, bit) in bits.bytes().enumerate() {
+            if bit == b'1' {
+                bytes[i / 8] |= 0x80 >> (i % 8);
+            }
+        }
+        assert_eq!(heatshrink_decompress(&bytes, 11, 4, 4), b"abab");
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+}
@@ -0,0 +1,169 @@
+use crate::gcode_line::Color4;
+use crate::slicers::FeatureType;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Every feature type that can carry a color, in a stable order
+pub const ALL_FEATURES: [FeatureType; 14] = [
+    FeatureType::Perimeter,
+    FeatureType::ExternalPerimeter,
+    FeatureType::InternalPerimeter,
+    FeatureType::Infill,
+    FeatureType::SolidInfill,
+    FeatureType::TopSolidInfill,
+    FeatureType::Support,
+    FeatureType::SupportInterface,
+    FeatureType::BridgeInfill,
+    FeatureType::GapFill,
+    FeatureType::Skirt,
+    FeatureType::Brim,
+    FeatureType::WipeTower,
+    FeatureType::Unknown,
+];
+
+/// Names of the palettes returned by `ColorTheme::builtin`
+pub const BUILTIN_THEMES: [&str; 4] = ["okabe_ito", "tol_bright", "high_contrast", "monochrome"];
+
+/// Per-feature color overrides applied on top of the detected slicer's colors
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ColorTheme {
+    pub name: String,
+    /// Feature colors; features not listed keep the slicer color
+    #[serde(default)]
+    pub feature_colors: HashMap<FeatureType, Color4>,
+    /// Alpha overrides, applied after the color lookup
+    #[serde(default)]
+    pub alpha: HashMap<FeatureType, f64>,
+}
+
+impl ColorTheme {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            feature_colors: HashMap::new(),
+            alpha: HashMap::new(),
+        }
+    }
+
+    /// Look up one of the built-in palettes by name
+    pub fn builtin(name: &str) -> Option<ColorTheme> {
+        // Colors as 0-255 RGB triples, in ALL_FEATURES order
+        let palette: [(u8, u8, u8); 14] = match name {
+            // Okabe & Ito, "Color Universal Design" - safe for all common color vision deficiencies
+            "okabe_ito" => [
+                (230, 159, 0),   // Perimeter - orange
+                (213, 94, 0),    // External perimeter - vermillion
+                (240, 228, 66),  // Internal perimeter - yellow
+                (0, 114, 178),   // Infill - blue
+                (86, 180, 233),  // Solid infill - sky blue
+                (204, 121, 167), // Top solid infill - reddish purple
+                (0, 158, 115),   // Support - bluish green
+                (0, 158, 115),   // Support interface
+                (86, 180, 233),  // Bridge infill
+                (240, 228, 66),  // Gap fill
+                (128, 128, 128), // Skirt
+                (128, 128, 128), // Brim
+                (0, 0, 0),       // Wipe tower - black
+                (128, 128, 128), // Unknown
+            ],
+            // Paul Tol's "bright" qualitative scheme - distinct under deuteranopia/protanopia
+            "tol_bright" => [
+                (204, 187, 68),  // Perimeter - yellow
+                (238, 102, 119), // External perimeter - red
+                (204, 187, 68),  // Internal perimeter
+                (68, 119, 170),  // Infill - blue
+                (102, 204, 238), // Solid infill - cyan
+                (170, 51, 119),  // Top solid infill - purple
+                (34, 136, 51),   // Support - green
+                (34, 136, 51),   // Support interface
+                (102, 204, 238), // Bridge infill
+                (204, 187, 68),  // Gap fill
+                (187, 187, 187), // Skirt - grey
+                (187, 187, 187), // Brim
+                (187, 187, 187), // Wipe tower
+                (187, 187, 187), // Unknown
+            ],
+            "high_contrast" => [
+                (255, 255, 0),   // Perimeter
+                (255, 255, 255), // External perimeter
+                (255, 255, 0),   // Internal perimeter
+                (0, 128, 255),   // Infill
+                (0, 255, 255),   // Solid infill
+                (255, 0, 255),   // Top solid infill
+                (255, 128, 0),   // Support
+                (255, 128, 0),   // Support interface
+                (0, 255, 0),     // Bridge infill
+                (255, 0, 0),     // Gap fill
+                (160, 160, 160), // Skirt
+                (160, 160, 160), // Brim
+                (160, 160, 160), // Wipe tower
+                (160, 160, 160), // Unknown
+            ],
+            "monochrome" => [
+                (200, 200, 200), // Perimeter
+                (255, 255, 255), // External perimeter
+                (200, 200, 200), // Internal perimeter
+                (110, 110, 110), // Infill
+                (150, 150, 150), // Solid infill
+                (230, 230, 230), // Top solid infill
+                (80, 80, 80),    // Support
+                (80, 80, 80),    // Support interface
+                (170, 170, 170), // Bridge infill
+                (190, 190, 190), // Gap fill
+                (128, 128, 128), // Skirt
+                (128, 128, 128), // Brim
+                (128, 128, 128), // Wipe tower
+                (128, 128, 128), // Unknown
+            ],
+            _ => return None,
+        };
+
+        let mut theme = ColorTheme::new(name);
+        for (feature, (r, g, b)) in ALL_FEATURES.iter().zip(palette.iter()) {
+            theme.feature_colors.insert(
+                feature.clone(),
+                Color4::new(*r as f64 / 255.0, *g as f64 / 255.0, *b as f64 / 255.0, 1.0),
+            );
+        }
+        Some(theme)
+    }
+
+    /// Resolve the color for a feature, falling back to the slicer color
+    pub fn resolve(&self, feature: &FeatureType, slicer_color: &Color4) -> Color4 {
+        let mut color = self.feature_colors.get(feature).cloned().unwrap_or_else(|| slicer_color.clone());
+        if let Some(alpha) = self.alpha.get(feature) {
+            color.a = alpha.clamp(0.0, 1.0);
+        }
+        color
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_themes_cover_all_features() {
+        for name in BUILTIN_THEMES {
+            let theme = ColorTheme::builtin(name).expect(name);
+            assert_eq!(theme.feature_colors.len(), ALL_FEATURES.len());
+        }
+        assert!(ColorTheme::builtin("no_such_theme").is_none());
+    }
+
+    #[test]
+    fn test_resolve_falls_back_to_slicer_color() {
+        let mut theme = ColorTheme::new("custom");
+        theme.feature_colors.insert(FeatureType::Infill, Color4::new(0.0, 0.0, 1.0, 1.0));
+        theme.alpha.insert(FeatureType::Support, 0.25);
+
+        let slicer_color = Color4::new(1.0, 0.5, 0.2, 1.0);
+
+        let infill = theme.resolve(&FeatureType::Infill, &slicer_color);
+        assert_eq!(infill.b, 1.0);
+
+        let support = theme.resolve(&FeatureType::Support, &slicer_color);
+        assert_eq!(support.r, 1.0);
+        assert_eq!(support.a, 0.25);
+    }
+}
@@ -0,0 +1,210 @@
+// Compact segment layout for GPU-side orientation
+// Each segment is 8 floats: start xyz, end xyz, width, height. The vertex shader builds the
+// box basis from the endpoints, matching the columns of `calculate_render_matrix`:
+//   X (length) = segment direction
+//   Y (height) = cross(side, direction)
+//   Z (width)  = side = normalize(cross(direction, up)), or +Z for vertical/zero-length segments
+
+use crate::segment_store::Segment;
+
+pub const COMPACT_FLOATS_PER_SEGMENT: usize = 8;
+
+// Below this length a segment has no direction and uses the identity basis
+const MIN_SEGMENT_LENGTH: f32 = 1e-6;
+
+/// Compact record for one segment; padding lengthens the box as in the matrix layout
+pub fn compact_segment(segment: &Segment, nozzle_size: f32, padding: f32) -> [f32; COMPACT_FLOATS_PER_SEGMENT] {
+    let [sx, sy, sz] = segment.start;
+    let [ex, ey, ez] = segment.end;
+    let length = segment.length();
+
+    // The matrix layout scales the length by `length + padding * 0.1` around the midpoint,
+    // so each end moves out by half of that
+    let (px, py, pz) = if length > MIN_SEGMENT_LENGTH {
+        let extend = padding * 0.05 / length;
+        ((ex - sx) * extend, (ey - sy) * extend, (ez - sz) * extend)
+    } else {
+        (0.0, 0.0, 0.0)
+    };
+
+    [
+        sx - px, sy - py, sz - pz,
+        ex + px, ey + py, ez + pz,
+        nozzle_size,
+        segment.layer_height,
+    ]
+}
+
+// Reference for the shader's basis [direction, height axis, width axis]
+#[cfg(test)]
+fn segment_basis(start: [f32; 3], end: [f32; 3]) -> [[f32; 3]; 3] {
+    let v = [end[0] - start[0], end[1] - start[1], end[2] - start[2]];
+    let length = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    if length < MIN_SEGMENT_LENGTH {
+        return [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+    }
+
+    let dir = [v[0] / length, v[1] / length, v[2] / length];
+    // cross(dir, up) with up = +Y (the vertical axis)
+    let horizontal = (dir[0] * dir[0] + dir[2] * dir[2]).sqrt();
+    let side = if horizontal < MIN_SEGMENT_LENGTH {
+        [0.0, 0.0, 1.0]
+    } else {
+        [-dir[2] / horizontal, 0.0, dir[0] / horizontal]
+    };
+    let height = cross(side, dir);
+
+    [dir, height, side]
+}
+
+#[cfg(test)]
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+// Instanced attributes: a_start (offset 0), a_end (offset 12), a_size (offset 24), stride 32 bytes.
+// a_position is the unit box vertex in [-0.5, 0.5]^3.
+pub const COMPACT_VERTEX_SHADER: &str = r#"#version 300 es
+precision highp float;
+
+in vec3 a_position;
+in vec3 a_normal;
+in vec3 a_start;
+in vec3 a_end;
+in vec2 a_size; // x = width, y = height
+
+uniform mat4 u_viewProjection;
+
+out vec3 v_normal;
+
+void main() {
+    vec3 v = a_end - a_start;
+    float len = length(v);
+    vec3 dir = len > 1e-6 ? v / len : vec3(1.0, 0.0, 0.0);
+    vec3 horizontal = vec3(-dir.z, 0.0, dir.x);
+    float h = length(horizontal);
+    vec3 side = (len > 1e-6 && h > 1e-6) ? horizontal / h : vec3(0.0, 0.0, 1.0);
+    vec3 up = len > 1e-6 ? cross(side, dir) : vec3(0.0, 1.0, 0.0);
+
+    vec3 center = (a_start + a_end) * 0.5;
+    vec3 world = center
+        + dir * (a_position.x * len)
+        + up * (a_position.y * a_size.y)
+        + side * (a_position.z * a_size.x);
+
+    v_normal = mat3(dir, up, side) * a_normal;
+    gl_Position = u_viewProjection * vec4(world, 1.0);
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(start: [f32; 3], end: [f32; 3]) -> Segment {
+        Segment {
+            start,
+            end,
+            feed_rate: 1500.0,
+            layer_height: 0.2,
+            width: 0.4,
+            color: 0,
+            flags: 0,
+            tool: 0,
+            feature: 0,
+            layer: 0,
+            line_number: 1,
+            file_position: 0,
+            file_end_position: 10,
+        }
+    }
+
+    fn assert_close(a: [f32; 3], b: [f32; 3]) {
+        for i in 0..3 {
+            assert!((a[i] - b[i]).abs() < 1e-5, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_compact_record_and_padding() {
+        let record = compact_segment(&segment([0.0, 0.2, 0.0], [10.0, 0.2, 0.0]), 0.4, 2.0);
+        // Padding 2.0 adds 0.2 to the length, 0.1 per end
+        assert_close([record[0], record[1], record[2]], [-0.1, 0.2, 0.0]);
+        assert_close([record[3], record[4], record[5]], [10.1, 0.2, 0.0]);
+        assert_eq!(record[6], 0.4);
+        assert_eq!(record[7], 0.2);
+        // Half the floats of the 16-float matrix layout
+        assert_eq!(COMPACT_FLOATS_PER_SEGMENT * 2, 16);
+    }
+
+    // Unit box corner placed by the compact shader path
+    fn compact_corner(record: &[f32; COMPACT_FLOATS_PER_SEGMENT], corner: [f32; 3]) -> [f32; 3] {
+        let start = [record[0], record[1], record[2]];
+        let end = [record[3], record[4], record[5]];
+        let [dir, up, side] = segment_basis(start, end);
+        let v = [end[0] - start[0], end[1] - start[1], end[2] - start[2]];
+        let length = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+        let scale = [corner[0] * length, corner[1] * record[7], corner[2] * record[6]];
+        let mut world = [0.0; 3];
+        for i in 0..3 {
+            world[i] = (start[i] + end[i]) * 0.5 + dir[i] * scale[0] + up[i] * scale[1] + side[i] * scale[2];
+        }
+        world
+    }
+
+    // Unit box corner placed by the column-major render matrix
+    fn matrix_corner(matrix: &[f32; 16], corner: [f32; 3]) -> [f32; 3] {
+        let mut world = [0.0; 3];
+        for (i, value) in world.iter_mut().enumerate() {
+            *value = matrix[i] * corner[0] + matrix[4 + i] * corner[1] + matrix[8 + i] * corner[2] + matrix[12 + i];
+        }
+        world
+    }
+
+    #[test]
+    fn test_corners_match_matrix_layout() {
+        let segments = [
+            segment([0.0, 0.2, 0.0], [10.0, 0.2, 0.0]),   // Along X
+            segment([5.0, 0.2, 0.0], [5.0, 0.2, 8.0]),    // Along Z
+            segment([10.0, 0.4, 3.0], [2.0, 0.4, -4.0]),  // Diagonal, backwards
+            segment([1.0, 0.2, 1.0], [4.0, 0.6, 5.0]),    // Rising travel
+            segment([3.0, 0.2, 3.0], [3.0, 0.8, 3.0]),    // Vertical Z hop
+        ];
+
+        for padding in [0.0, 2.0] {
+            for seg in &segments {
+                let (matrix, _) = crate::GCodeProcessor::calculate_render_matrix(seg, 0.4, padding);
+                let record = compact_segment(seg, 0.4, padding);
+                for corner in 0..8 {
+                    let corner = [
+                        if corner & 1 == 0 { -0.5 } else { 0.5 },
+                        if corner & 2 == 0 { -0.5 } else { 0.5 },
+                        if corner & 4 == 0 { -0.5 } else { 0.5 },
+                    ];
+                    let expected = matrix_corner(&matrix, corner);
+                    let actual = compact_corner(&record, corner);
+                    for i in 0..3 {
+                        assert!(
+                            (expected[i] - actual[i]).abs() < 1e-4,
+                            "{:?} -> {:?} corner {:?}: matrix {:?}, compact {:?}",
+                            seg.start, seg.end, corner, expected, actual
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_zero_length_basis() {
+        // Zero-length segments fall back to the identity basis, as the matrix path does
+        let [dir, height, side] = segment_basis([1.0, 1.0, 1.0], [1.0, 1.0, 1.0]);
+        assert_close(dir, [1.0, 0.0, 0.0]);
+        assert_close(height, [0.0, 1.0, 0.0]);
+        assert_close(side, [0.0, 0.0, 1.0]);
+    }
+}
@@ -0,0 +1,469 @@
+// G-code lint pass
+// Lines are re-parsed with their own processor state (independent of rendering) and checked for
+// parse failures, malformed words and semantic problems. Findings carry the line and byte range.
+
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::GCodeCommands::ProcessLine::process_line;
+use crate::processor_properties::ProcessorProperties;
+use crate::machine_profile::MachineProfile;
+use crate::error::ErrorKind;
+use crate::utils::lines_with_length;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DiagnosticCode {
+    ParseError,
+    MalformedNumber,
+    UnknownCommand,
+    MoveBeforeHoming,
+    ColdExtrusion,
+    OutOfBuildVolume,
+    NegativeFeedRate,
+    ToolOutOfRange,
+    TooManyDiagnostics,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: DiagnosticCode,
+    pub line_number: u32,
+    pub start: u32, // Byte range in the file (end exclusive): the offending word, or the whole line
+    pub end: u32,
+    pub message: String,
+    pub occurrences: u32, // Lines with the same unknown or extended command; reported once, at the first
+}
+
+/// Axis-aligned printable box in G-code axes (Z up)
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BuildVolume {
+    pub min: [f64; 3],
+    pub max: [f64; 3],
+}
+
+impl BuildVolume {
+    pub fn contains(&self, point: [f64; 3]) -> bool {
+        const EPSILON: f64 = 1e-6;
+        (0..3).all(|axis| point[axis] >= self.min[axis] - EPSILON && point[axis] <= self.max[axis] + EPSILON)
+    }
+}
+
+fn default_min_extrusion_temperature() -> f64 {
+    170.0
+}
+
+fn default_max_diagnostics() -> usize {
+    1000
+}
+
+/// Lint configuration (plain JS object); checks without a setting are skipped
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LintSettings {
+    #[serde(default)]
+    pub build_volume: Option<BuildVolume>,
+    #[serde(default = "default_min_extrusion_temperature")]
+    pub min_extrusion_temperature: f64,
+    #[serde(default)]
+    pub tool_count: Option<u32>,
+    #[serde(default = "default_max_diagnostics")]
+    pub max_diagnostics: usize,
+}
+
+impl Default for LintSettings {
+    fn default() -> Self {
+        LintSettings {
+            build_volume: None,
+            min_extrusion_temperature: default_min_extrusion_temperature(),
+            tool_count: None,
+            max_diagnostics: default_max_diagnostics(),
+        }
+    }
+}
+
+// Commands taking free text instead of words (messages, file names)
+const TEXT_COMMANDS: &[&str] = &["M23", "M28", "M30", "M32", "M117", "M118", "M928", "M1002"];
+
+// Integer part of G/M codes understood by common firmwares (Marlin, RepRapFirmware, Prusa, Klipper, Bambu)
+const KNOWN_G_CODES: &[u32] = &[
+    0, 1, 2, 3, 4, 5, 6, 10, 11, 12, 17, 18, 19, 20, 21, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 38, 42,
+    53, 54, 55, 56, 57, 58, 59, 60, 61, 64, 76, 80, 90, 91, 92, 425,
+];
+const KNOWN_M_CODES: &[u32] = &[
+    0, 1, 2, 3, 4, 5, 7, 8, 9, 16, 17, 18, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 42,
+    73, 75, 76, 77, 80, 81, 82, 83, 84, 85, 92, 100, 104, 105, 106, 107, 108, 109, 110, 111, 112, 113,
+    114, 115, 117, 118, 119, 120, 121, 122, 123, 125, 126, 127, 140, 141, 143, 150, 155, 163, 164, 165,
+    190, 191, 192, 193, 200, 201, 202, 203, 204, 205, 206, 207, 208, 209, 211, 217, 218, 220, 221, 226,
+    250, 260, 261, 280, 281, 290, 300, 301, 302, 303, 304, 305, 350, 351, 355, 360, 380, 381, 400, 401,
+    402, 403, 404, 405, 406, 407, 410, 412, 413, 420, 421, 422, 425, 428, 486, 500, 501, 502, 503, 504,
+    510, 511, 512, 524, 540, 550, 551, 552, 553, 554, 555, 556, 557, 558, 559, 560, 561, 562, 563, 564,
+    566, 567, 568, 569, 570, 571, 572, 573, 574, 575, 577, 578, 579, 580, 581, 582, 584, 585, 586, 587,
+    589, 590, 591, 592, 593, 594, 595, 596, 597, 598, 599, 600, 601, 602, 603, 605, 620, 621, 622, 623,
+    624, 625, 665, 666, 669, 701, 702, 750, 751, 752, 851, 860, 861, 862, 863, 864, 865, 866, 867, 868,
+    869, 871, 876, 900, 906, 907, 908, 909, 910, 911, 912, 913, 914, 915, 916, 917, 928, 929, 950, 951,
+    960, 970, 971, 972, 973, 974, 975, 976, 981, 982, 983, 991, 997, 998, 999, 1002, 1003, 1006,
+];
+
+// One address word (`X10.5`) with its byte range in the file
+struct Word<'a> {
+    letter: char,
+    value: &'a str,
+    start: u32,
+    end: u32,
+}
+
+// Split the code part of a line into words; stops at comments and checksums
+fn words(code: &str, line_start: u32) -> Vec<Word<'_>> {
+    let bytes = code.as_bytes();
+    let mut words = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let byte = bytes[pos];
+        if byte.is_ascii_whitespace() {
+            pos += 1;
+        } else if byte == b'*' || byte == b';' {
+            break;
+        } else if byte.is_ascii_alphabetic() {
+            let value_start = pos + 1;
+            let mut end = value_start;
+            while end < bytes.len() && !bytes[end].is_ascii_whitespace() && !bytes[end].is_ascii_alphabetic()
+                && bytes[end] != b';' && bytes[end] != b'*' {
+                end += 1;
+            }
+            words.push(Word {
+                letter: byte.to_ascii_uppercase() as char,
+                value: &code[value_start..end],
+                start: line_start + pos as u32,
+                end: line_start + end as u32,
+            });
+            pos = end;
+        } else {
+            // Stray characters are skipped up to the next separator
+            while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+        }
+    }
+    words
+}
+
+fn is_known_command(letter: char, value: &str) -> bool {
+    let number = value.split('.').next().and_then(|n| n.parse::<u32>().ok());
+    match (letter, number) {
+        ('G', Some(n)) => KNOWN_G_CODES.binary_search(&n).is_ok(),
+        ('M', Some(n)) => KNOWN_M_CODES.binary_search(&n).is_ok(),
+        ('T', Some(_)) => true,
+        _ => false,
+    }
+}
+
+struct Linter<'s> {
+    settings: &'s LintSettings,
+    profile: Option<&'s MachineProfile>,
+    properties: ProcessorProperties,
+    diagnostics: Vec<Diagnostic>,
+    unknown_commands: HashMap<String, usize>, // Command name -> index of its diagnostic
+    errors: usize,
+    dropped: u32, // Findings past `max_diagnostics`
+    first_dropped: (u32, u32), // Line number and byte offset of the first dropped finding
+    homed: bool,
+    reported_unhomed: bool,
+    reported_cold: bool,
+}
+
+impl Linter<'_> {
+    // Past the cap only errors are kept (up to `max_diagnostics` of them), so noise can't hide them
+    fn report(&mut self, severity: Severity, code: DiagnosticCode, line_number: u32, range: (u32, u32), message: String) {
+        let max = self.settings.max_diagnostics;
+        if self.diagnostics.len() >= max && !(severity == Severity::Error && self.errors < max) {
+            if self.dropped == 0 {
+                self.first_dropped = (line_number, range.0);
+            }
+            self.dropped += 1;
+            return;
+        }
+        if severity == Severity::Error {
+            self.errors += 1;
+        }
+        self.diagnostics.push(Diagnostic { severity, code, line_number, start: range.0, end: range.1, message, occurrences: 1 });
+    }
+
+    // Unknown and extended commands are reported once each, counting later occurrences
+    fn report_command(&mut self, severity: Severity, name: &str, line_number: u32, range: (u32, u32), message: String) {
+        if let Some(&index) = self.unknown_commands.get(name) {
+            self.diagnostics[index].occurrences += 1;
+            return;
+        }
+        let count = self.diagnostics.len();
+        self.report(severity, DiagnosticCode::UnknownCommand, line_number, range, message);
+        if self.diagnostics.len() > count {
+            self.unknown_commands.insert(name.to_string(), count);
+        }
+    }
+
+    fn finish(mut self) -> Vec<Diagnostic> {
+        for diagnostic in &mut self.diagnostics {
+            if diagnostic.code == DiagnosticCode::UnknownCommand && diagnostic.occurrences > 1 {
+                diagnostic.message = format!("{} ({} lines)", diagnostic.message, diagnostic.occurrences);
+            }
+        }
+        if self.dropped > 0 {
+            let message = format!("{} more findings after {} diagnostics (errors are still reported)",
+                                  self.dropped, self.settings.max_diagnostics);
+            self.diagnostics.push(Diagnostic {
+                severity: Severity::Info,
+                code: DiagnosticCode::TooManyDiagnostics,
+                line_number: self.first_dropped.0,
+                start: self.first_dropped.1,
+                end: self.first_dropped.1,
+                message,
+                occurrences: self.dropped,
+            });
+        }
+        self.diagnostics
+    }
+
+    fn check_line(&mut self, line: &str, file_position: u32, line_number: u32) {
+        let code = line.split(';').next().unwrap_or("");
+        let first = code.split_whitespace().next().unwrap_or("");
+        let line_range = (file_position, file_position + line.len() as u32);
+        if first.is_empty() {
+            return;
+        }
+
+        // Standard words are a letter followed by a number; anything else is a macro or extended command
+        let first_bytes = first.as_bytes();
+        if !(first_bytes[0].is_ascii_alphabetic() && first_bytes.get(1).is_some_and(|b| b.is_ascii_digit() || *b == b'-')) {
+            self.report_command(Severity::Info, first, line_number, line_range,
+                                format!("Extended command '{}' is not checked", first));
+            return;
+        }
+
+        let words = words(code, file_position);
+        // Skip line numbers (N123)
+        let Some(command_index) = words.iter().position(|word| word.letter != 'N') else {
+            return;
+        };
+        let command = &words[command_index];
+        let name = format!("{}{}", command.letter, command.value);
+        if !is_known_command(command.letter, command.value) {
+            self.report_command(Severity::Warning, &name, line_number, (command.start, command.end),
+                                format!("Unknown command '{}'", name));
+        }
+        if TEXT_COMMANDS.contains(&name.as_str()) {
+            return;
+        }
+
+        for word in &words {
+            if word.value.is_empty() {
+                continue; // Axis flags such as `G28 X Y`
+            }
+            let Ok(value) = word.value.parse::<f64>() else {
+                self.report(Severity::Error, DiagnosticCode::MalformedNumber, line_number, (word.start, word.end),
+                            format!("Malformed number '{}' for {}", word.value, word.letter));
+                continue;
+            };
+            if word.letter == 'F' && value < 0.0 {
+                self.report(Severity::Error, DiagnosticCode::NegativeFeedRate, line_number, (word.start, word.end),
+                            format!("Negative feed rate F{}", word.value));
+            }
+            if word.letter == 'T' {
+                if let Some(count) = self.settings.tool_count {
+                    if value >= count as f64 {
+                        self.report(Severity::Error, DiagnosticCode::ToolOutOfRange, line_number, (word.start, word.end),
+                                    format!("Tool T{} is out of range (machine has {} tools)", word.value, count));
+                    }
+                }
+            }
+        }
+
+        if name == "G28" {
+            self.homed = true;
+        }
+        let hotend_target = self.properties.target_hotend_temp;
+        let previous_e = self.properties.current_e;
+        let parsed = match process_line(&mut self.properties, line, file_position, line_number) {
+            Ok(parsed) => parsed,
+            Err(error) => {
+                let code = match error.kind() {
+                    ErrorKind::MalformedNumber => DiagnosticCode::MalformedNumber,
+                    ErrorKind::UnknownCommand => DiagnosticCode::UnknownCommand,
+                    _ => DiagnosticCode::ParseError,
+                };
+                self.report(Severity::Error, code, line_number, line_range, error.message());
+                return;
+            }
+        };
+        if self.properties.target_hotend_temp != hotend_target {
+            self.reported_cold = false;
+        }
+
+        let is_arc = parsed.as_arc().is_some();
+        if parsed.as_move().is_none() && !is_arc {
+            return;
+        }
+        if !self.homed && !self.reported_unhomed {
+            self.reported_unhomed = true;
+            self.report(Severity::Warning, DiagnosticCode::MoveBeforeHoming, line_number, line_range,
+                        "Move before any G28 homing (unless a start macro homes the machine)".to_string());
+        }
+
+        let extruded = self.properties.current_e - previous_e;
+        if extruded > 0.0 && self.properties.target_hotend_temp < self.settings.min_extrusion_temperature && !self.reported_cold {
+            self.reported_cold = true;
+            self.report(Severity::Error, DiagnosticCode::ColdExtrusion, line_number, line_range,
+                        format!("Extrusion with hotend target {}°C, below the {}°C minimum",
+                                self.properties.target_hotend_temp, self.settings.min_extrusion_temperature));
+        }
+
+        // Linear moves only: stored move positions have Y and Z swapped, arcs keep G-code axes
+        if let Some(movement) = parsed.as_move() {
+            let end = [movement.end.x, movement.end.z, movement.end.y];
+            let inside = match (self.settings.build_volume, self.profile) {
+                (Some(volume), _) => volume.contains(end),
+                (None, Some(profile)) => profile.contains(end),
+                (None, None) => true,
+            };
+            if !inside {
+                let severity = if extruded > 0.0 { Severity::Error } else { Severity::Warning };
+                self.report(severity, DiagnosticCode::OutOfBuildVolume, line_number, line_range,
+                            format!("Move to X{:.3} Y{:.3} Z{:.3} leaves the build volume", end[0], end[1], end[2]));
+            }
+        }
+    }
+}
+
+/// Lint a whole file; at most `max_diagnostics` findings, then only errors (again at most
+/// `max_diagnostics`), plus a notice counting what was dropped. Without a `build_volume` setting, moves are checked against `profile` (circular beds included).
+pub fn lint(source: &str, settings: &LintSettings, profile: Option<&MachineProfile>) -> Vec<Diagnostic> {
+    let mut linter = Linter {
+        settings,
+        profile,
+        properties: ProcessorProperties::new(),
+        diagnostics: Vec::new(),
+        unknown_commands: HashMap::new(),
+        errors: 0,
+        dropped: 0,
+        first_dropped: (0, 0),
+        homed: false,
+        reported_unhomed: false,
+        reported_cold: false,
+    };
+
+    let mut file_position = 0u32;
+    for (index, (line, length)) in lines_with_length(source).enumerate() {
+        linter.check_line(line, file_position, index as u32 + 1);
+        file_position += length;
+    }
+    linter.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::machine_profile::{BedOrigin, BedShape};
+
+    fn codes(source: &str, settings: &LintSettings) -> Vec<(DiagnosticCode, u32)> {
+        lint(source, settings, None).iter().map(|d| (d.code, d.line_number)).collect()
+    }
+
+    #[test]
+    fn test_reports_semantic_problems() {
+        let source = "G1 X5 Y5\nG28\nG1 X10 E1 F1200\nM104 S210\nG1 X20 E2\nG1 X300 Y10 E3\nG0 X10 F-100\nT2\n";
+        let settings = LintSettings {
+            build_volume: Some(BuildVolume { min: [0.0; 3], max: [250.0, 210.0, 200.0] }),
+            tool_count: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(codes(source, &settings), vec![
+            (DiagnosticCode::MoveBeforeHoming, 1),
+            (DiagnosticCode::ColdExtrusion, 3),
+            (DiagnosticCode::OutOfBuildVolume, 6),
+            (DiagnosticCode::NegativeFeedRate, 7),
+            (DiagnosticCode::ToolOutOfRange, 8),
+        ]);
+
+        let diagnostics = lint(source, &settings, None);
+        assert_eq!(diagnostics[2].severity, Severity::Error);
+        // Word-level findings point at the word itself
+        let feed = &diagnostics[3];
+        assert_eq!(&source[feed.start as usize..feed.end as usize], "F-100");
+
+        // Ranges stay on the word when lines end in CRLF
+        let crlf = source.replace('\n', "\r\n");
+        let feed = &lint(&crlf, &settings, None)[3];
+        assert_eq!(&crlf[feed.start as usize..feed.end as usize], "F-100");
+    }
+
+    #[test]
+    fn test_reports_syntax_problems() {
+        let source = "G28\nM104 S215\nG1 X1.2.3 Y4\nM9999\nPRINT_START BED=60\nM117 Printing 1.2.3\nN10 G1 X1 Y-*57\n";
+        let diagnostics = lint(source, &LintSettings::default(), None);
+        let found: Vec<(DiagnosticCode, u32)> = diagnostics.iter().map(|d| (d.code, d.line_number)).collect();
+        assert_eq!(found, vec![
+            (DiagnosticCode::MalformedNumber, 3),
+            (DiagnosticCode::UnknownCommand, 4),
+            (DiagnosticCode::UnknownCommand, 5),
+            (DiagnosticCode::MalformedNumber, 7),
+        ]);
+        assert_eq!(diagnostics[2].severity, Severity::Info);
+        assert_eq!(&source[diagnostics[0].start as usize..diagnostics[0].end as usize], "X1.2.3");
+
+        // Past the cap, warnings are dropped but later errors are still found
+        let source = format!("G28\n{}G0 X10 F-100\n", "G0 X300\n".repeat(10));
+        let settings = LintSettings {
+            build_volume: Some(BuildVolume { min: [0.0; 3], max: [250.0, 210.0, 200.0] }),
+            max_diagnostics: 3,
+            ..Default::default()
+        };
+        let capped = lint(&source, &settings, None);
+        assert_eq!(capped.len(), 5);
+        assert_eq!((capped[3].code, capped[3].line_number), (DiagnosticCode::NegativeFeedRate, 12));
+        assert_eq!((capped[4].code, capped[4].line_number), (DiagnosticCode::TooManyDiagnostics, 5));
+        assert_eq!(capped[4].occurrences, 7);
+    }
+
+    #[test]
+    fn test_repeated_commands_reported_once() {
+        let source = "G28\nM104 S215\nEXCLUDE_OBJECT_START NAME=a\nM9999\nSET_PRINT_STATS_INFO CURRENT_LAYER=1\n\
+                      EXCLUDE_OBJECT_START NAME=b\nM9999 S1\nEXCLUDE_OBJECT_START NAME=c\n\
+                      M991 S0 P1\nM1002 gcode_claim_action : 2\nM620 S0A\nM621 S0A\nM622 J1\nM623\n";
+        let diagnostics = lint(source, &LintSettings { max_diagnostics: 3, ..Default::default() }, None);
+        let found: Vec<(DiagnosticCode, u32, u32)> = diagnostics.iter().map(|d| (d.code, d.line_number, d.occurrences)).collect();
+        assert_eq!(found, vec![
+            (DiagnosticCode::UnknownCommand, 3, 3),
+            (DiagnosticCode::UnknownCommand, 4, 2),
+            (DiagnosticCode::UnknownCommand, 5, 1),
+        ]);
+        assert_eq!(diagnostics[0].message, "Extended command 'EXCLUDE_OBJECT_START' is not checked (3 lines)");
+    }
+
+    #[test]
+    fn test_circular_bed_profile() {
+        let profile = MachineProfile {
+            bed: BedShape::Circular { diameter: 200.0 },
+            origin: BedOrigin::Center,
+            origin_offset: [0.0, 0.0],
+            max_z: 300.0,
+            excluded_zones: Vec::new(),
+        };
+        // X80 Y80 is inside the bounding square but outside the circle
+        let source = "G28\nM104 S215\nG1 X70 Y70 E1 F1200\nG1 X80 Y80 E2\n";
+        let found: Vec<(DiagnosticCode, u32)> = lint(source, &LintSettings::default(), Some(&profile))
+            .iter().map(|d| (d.code, d.line_number)).collect();
+        assert_eq!(found, vec![(DiagnosticCode::OutOfBuildVolume, 4)]);
+
+        // An explicit build volume takes precedence over the profile
+        let settings = LintSettings {
+            build_volume: Some(BuildVolume { min: [-100.0, -100.0, 0.0], max: [100.0, 100.0, 300.0] }),
+            ..Default::default()
+        };
+        assert!(lint(source, &settings, Some(&profile)).is_empty());
+    }
+}
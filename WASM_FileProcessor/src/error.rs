@@ -0,0 +1,164 @@
+// Structured processing errors
+// Every failure carries a kind the UI can switch on, the line and byte where it happened when
+// known, and context: the offending line or value. Location is 1-based for lines, 0-based for bytes.
+
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+pub const MAX_FILE_SIZE: usize = 500_000_000;
+const MAX_CONTEXT_LENGTH: usize = 120;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ErrorKind {
+    EmptyFile = 0,
+    NotGCode = 1,
+    FileTooLarge = 2,
+    InvalidUtf8 = 3,
+    InvalidInput = 4,      // Damaged or unsupported container (gzip, zip/3MF, bgcode), missing plate
+    MalformedNumber = 5,
+    UnknownCommand = 6,
+    ArcRadiusTooSmall = 7, // G2/G3 R too small for the chord and radius fixing disabled
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProcessingError {
+    kind: ErrorKind,
+    message: String,
+    line_number: Option<u32>,
+    file_position: Option<u32>,
+    context: String,
+}
+
+impl ProcessingError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        ProcessingError {
+            kind,
+            message: message.into(),
+            line_number: None,
+            file_position: None,
+            context: String::new(),
+        }
+    }
+
+    /// Container errors from the gzip, zip and bgcode readers
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::InvalidInput, message)
+    }
+
+    /// Number that failed to parse on a G-code line
+    pub fn malformed_number(line: &str, file_position: u32, line_number: u32) -> Self {
+        Self::new(ErrorKind::MalformedNumber, "Failed to parse number")
+            .at(file_position, line_number)
+            .with_context(line)
+    }
+
+    pub fn at(mut self, file_position: u32, line_number: u32) -> Self {
+        self.file_position = Some(file_position);
+        self.line_number = Some(line_number);
+        self
+    }
+
+    /// Context is trimmed and cut to a readable length on a character boundary
+    pub fn with_context(mut self, context: &str) -> Self {
+        let context = context.trim();
+        let end = context.char_indices().nth(MAX_CONTEXT_LENGTH).map_or(context.len(), |(index, _)| index);
+        self.context = context[..end].to_string();
+        self
+    }
+}
+
+#[wasm_bindgen]
+impl ProcessingError {
+    #[wasm_bindgen(getter)]
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn message(&self) -> String {
+        self.message.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn line_number(&self) -> Option<u32> {
+        self.line_number
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn file_position(&self) -> Option<u32> {
+        self.file_position
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn context(&self) -> String {
+        self.context.clone()
+    }
+
+    /// Message with location and context, as logged
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_display_string(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for ProcessingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(line_number) = self.line_number {
+            write!(f, " at line {}", line_number)?;
+        } else if let Some(file_position) = self.file_position {
+            write!(f, " at byte {}", file_position)?;
+        }
+        if !self.context.is_empty() {
+            write!(f, " ({})", self.context)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ProcessingError {}
+
+/// Decode UTF-8, reporting the first invalid byte and its line
+pub fn decode_utf8(bytes: Vec<u8>) -> Result<String, ProcessingError> {
+    String::from_utf8(bytes).map_err(|e| {
+        let bytes = e.as_bytes();
+        let position = e.utf8_error().valid_up_to();
+        let line_number = bytes[..position].iter().filter(|&&b| b == b'\n').count() as u32 + 1;
+        let invalid = &bytes[position..bytes.len().min(position + 4)];
+        let context = invalid.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ");
+        ProcessingError::new(ErrorKind::InvalidUtf8, "G-code is not valid UTF-8")
+            .at(position as u32, line_number)
+            .with_context(&context)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_with_location_and_context() {
+        let error = ProcessingError::malformed_number("  G1 X1.2.3 Y4  ", 120, 7);
+        assert_eq!(error.kind(), ErrorKind::MalformedNumber);
+        assert_eq!((error.file_position(), error.line_number()), (Some(120), Some(7)));
+        assert_eq!(error.to_string(), "Failed to parse number at line 7 (G1 X1.2.3 Y4)");
+
+        let empty = ProcessingError::new(ErrorKind::EmptyFile, "File is empty");
+        assert_eq!(empty.to_string(), "File is empty");
+
+        let long = ProcessingError::new(ErrorKind::NotGCode, "Not G-code").with_context(&"é".repeat(200));
+        assert_eq!(long.context().chars().count(), MAX_CONTEXT_LENGTH);
+    }
+
+    #[test]
+    fn test_invalid_utf8_location() {
+        let error = decode_utf8(b"G28\nG1 X1\nG1 \xFF\xFEY2\n".to_vec()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidUtf8);
+        assert_eq!((error.file_position(), error.line_number()), (Some(13), Some(3)));
+        assert_eq!(error.context(), "FF FE 59 32");
+        assert_eq!(decode_utf8(b"G28\n".to_vec()).unwrap(), "G28\n");
+    }
+}
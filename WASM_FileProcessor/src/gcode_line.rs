@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use crate::slicers::FeatureType;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Vector3 {
@@ -59,7 +60,89 @@ pub trait GCodeLineBase {
     fn line_type(&self) -> LineType;
     fn file_position(&self) -> u32;
     fn line_number(&self) -> u32;
-    fn original_line(&self) -> &str;
+    fn line_length(&self) -> u32;
+    
+    /// Line text, sliced from the source the line was parsed from (no copy is kept)
+    fn original_line<'s>(&self, source: &'s str) -> &'s str {
+        let start = self.file_position() as usize;
+        source.get(start..start + self.line_length() as usize).unwrap_or("")
+    }
+}
+
+// Maximum stored length of a command name; longer names are truncated
+const COMMAND_NAME_CAPACITY: usize = 23;
+
+/// Command name ("G92", "TOOL_CHANGE", "M_COMMAND_84"...) stored inline to avoid a heap allocation per line
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct CommandName {
+    bytes: [u8; COMMAND_NAME_CAPACITY],
+    len: u8,
+}
+
+impl CommandName {
+    pub fn new(name: &str) -> Self {
+        Self::concat(name, "")
+    }
+    
+    /// ASCII-uppercased copy of a command word
+    pub fn uppercase(name: &str) -> Self {
+        let mut command = Self::new(name);
+        command.bytes[..command.len as usize].make_ascii_uppercase();
+        command
+    }
+    
+    pub fn concat(prefix: &str, suffix: &str) -> Self {
+        let mut command = CommandName { bytes: [0; COMMAND_NAME_CAPACITY], len: 0 };
+        for c in prefix.chars().chain(suffix.chars()) {
+            let start = command.len as usize;
+            if start + c.len_utf8() > COMMAND_NAME_CAPACITY {
+                break;
+            }
+            c.encode_utf8(&mut command.bytes[start..]);
+            command.len += c.len_utf8() as u8;
+        }
+        command
+    }
+    
+    pub fn as_str(&self) -> &str {
+        // Only whole chars are ever copied in, so the bytes are valid UTF-8
+        std::str::from_utf8(&self.bytes[..self.len as usize]).unwrap_or("")
+    }
+    
+    pub fn starts_with(&self, c: char) -> bool {
+        self.as_str().starts_with(c)
+    }
+}
+
+impl std::fmt::Debug for CommandName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+impl std::fmt::Display for CommandName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl PartialEq<&str> for CommandName {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl Serialize for CommandName {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for CommandName {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(CommandName::new(&name))
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -118,14 +201,14 @@ impl GCodeLineBase for GCodeLine {
         }
     }
     
-    fn original_line(&self) -> &str {
+    fn line_length(&self) -> u32 {
         match self {
-            GCodeLine::Move(m) => &m.original_line,
-            GCodeLine::Arc(a) => &a.original_line,
-            GCodeLine::Comment(c) => &c.original_line,
-            GCodeLine::Command(cmd) => &cmd.original_line,
-            GCodeLine::MCode(m) => &m.original_line,
-            GCodeLine::Tool(t) => &t.original_line,
+            GCodeLine::Move(m) => m.line_length,
+            GCodeLine::Arc(a) => a.line_length,
+            GCodeLine::Comment(c) => c.line_length,
+            GCodeLine::Command(cmd) => cmd.line_length,
+            GCodeLine::MCode(m) => m.line_length,
+            GCodeLine::Tool(t) => t.line_length,
         }
     }
 }
@@ -135,7 +218,7 @@ impl GCodeLineBase for GCodeLine {
 pub struct MoveData {
     pub file_position: u32,
     pub line_number: u32,
-    pub original_line: String,
+    pub line_length: u32, // Line text is file_position..file_position + line_length of the source
     pub tool: u8,
     pub start: Vector3,
     pub end: Vector3,
@@ -143,17 +226,19 @@ pub struct MoveData {
     pub color: Color4,
     pub feed_rate: f64,
     pub layer_height: f64,
+    pub width: f64, // Extrusion width from E and the tool's filament diameter; nozzle size for travel
     pub is_perimeter: bool,
     pub is_support: bool,
+    pub feature: FeatureType,
     pub color_id: [u8; 3], // RGB color ID for picking
 }
 
 impl MoveData {
-    pub fn new(file_position: u32, line_number: u32, original_line: String) -> Self {
+    pub fn new(file_position: u32, line_number: u32, line: &str) -> Self {
         Self {
             file_position,
             line_number,
-            original_line,
+            line_length: line.len() as u32,
             tool: 0,
             start: Vector3::zero(),
             end: Vector3::zero(),
@@ -161,8 +246,10 @@ impl MoveData {
             color: Color4::white(),
             feed_rate: 1500.0,
             layer_height: 0.2,
+            width: 0.4,
             is_perimeter: false,
             is_support: false,
+            feature: FeatureType::Unknown,
             color_id: [0, 0, 0],
         }
     }
@@ -186,7 +273,7 @@ impl MoveData {
 pub struct ArcMove {
     pub file_position: u32,
     pub line_number: u32,
-    pub original_line: String,
+    pub line_length: u32, // Line text is file_position..file_position + line_length of the source
     pub tool: u8,
     pub start: Vector3,
     pub end: Vector3,
@@ -200,11 +287,11 @@ pub struct ArcMove {
 }
 
 impl ArcMove {
-    pub fn new(file_position: u32, line_number: u32, original_line: String) -> Self {
+    pub fn new(file_position: u32, line_number: u32, line: &str) -> Self {
         Self {
             file_position,
             line_number,
-            original_line,
+            line_length: line.len() as u32,
             tool: 0,
             start: Vector3::zero(),
             end: Vector3::zero(),
@@ -224,25 +311,31 @@ impl ArcMove {
 pub struct CommentData {
     pub file_position: u32,
     pub line_number: u32,
-    pub original_line: String,
-    pub comment_text: String,
+    pub line_length: u32, // Line text is file_position..file_position + line_length of the source
+    pub comment_start: u32, // Comment text range within the line (after ';', trimmed)
+    pub comment_length: u32,
 }
 
 impl CommentData {
-    pub fn new(file_position: u32, line_number: u32, original_line: String) -> Self {
-        let comment_text = if original_line.starts_with(';') {
-            original_line[1..].trim().to_string()
-        } else {
-            original_line.trim().to_string()
-        };
+    pub fn new(file_position: u32, line_number: u32, line: &str) -> Self {
+        let trimmed = line.trim();
+        let comment = trimmed.strip_prefix(';').map(str::trim).unwrap_or(trimmed);
+        let comment_start = comment.as_ptr() as usize - line.as_ptr() as usize;
         
         Self {
             file_position,
             line_number,
-            original_line,
-            comment_text,
+            line_length: line.len() as u32,
+            comment_start: comment_start as u32,
+            comment_length: comment.len() as u32,
         }
     }
+    
+    /// Comment text without the leading ';', sliced from the parsed source
+    pub fn comment_text<'s>(&self, source: &'s str) -> &'s str {
+        let start = (self.file_position + self.comment_start) as usize;
+        source.get(start..start + self.comment_length as usize).unwrap_or("")
+    }
 }
 
 // Generic command data (G-codes other than moves)
@@ -250,18 +343,18 @@ impl CommentData {
 pub struct CommandData {
     pub file_position: u32,
     pub line_number: u32,
-    pub original_line: String,
-    pub command_type: String,
-    pub parameters: Vec<(String, f64)>, // Parameter name and value pairs
+    pub line_length: u32, // Line text is file_position..file_position + line_length of the source
+    pub command_type: CommandName,
+    pub parameters: Vec<(char, f64)>, // Parameter letter and value pairs
 }
 
 impl CommandData {
-    pub fn new(file_position: u32, line_number: u32, original_line: String, command_type: String) -> Self {
+    pub fn new(file_position: u32, line_number: u32, line: &str, command_type: &str) -> Self {
         Self {
             file_position,
             line_number,
-            original_line,
-            command_type,
+            line_length: line.len() as u32,
+            command_type: CommandName::new(command_type),
             parameters: Vec::new(),
         }
     }
@@ -272,17 +365,17 @@ impl CommandData {
 pub struct MCodeData {
     pub file_position: u32,
     pub line_number: u32,
-    pub original_line: String,
+    pub line_length: u32, // Line text is file_position..file_position + line_length of the source
     pub mcode_number: u32,
-    pub parameters: Vec<(String, f64)>, // Parameter name and value pairs
+    pub parameters: Vec<(char, f64)>, // Parameter letter and value pairs
 }
 
 impl MCodeData {
-    pub fn new(file_position: u32, line_number: u32, original_line: String, mcode_number: u32) -> Self {
+    pub fn new(file_position: u32, line_number: u32, line: &str, mcode_number: u32) -> Self {
         Self {
             file_position,
             line_number,
-            original_line,
+            line_length: line.len() as u32,
             mcode_number,
             parameters: Vec::new(),
         }
@@ -291,24 +384,24 @@ impl MCodeData {
 
 // Utility functions for creating G-code lines
 impl GCodeLine {
-    pub fn new_move(file_position: u32, line_number: u32, original_line: String) -> Self {
-        GCodeLine::Move(MoveData::new(file_position, line_number, original_line))
+    pub fn new_move(file_position: u32, line_number: u32, line: &str) -> Self {
+        GCodeLine::Move(MoveData::new(file_position, line_number, line))
     }
     
-    pub fn new_arc(file_position: u32, line_number: u32, original_line: String) -> Self {
-        GCodeLine::Arc(ArcMove::new(file_position, line_number, original_line))
+    pub fn new_arc(file_position: u32, line_number: u32, line: &str) -> Self {
+        GCodeLine::Arc(ArcMove::new(file_position, line_number, line))
     }
     
-    pub fn new_comment(file_position: u32, line_number: u32, original_line: String) -> Self {
-        GCodeLine::Comment(CommentData::new(file_position, line_number, original_line))
+    pub fn new_comment(file_position: u32, line_number: u32, line: &str) -> Self {
+        GCodeLine::Comment(CommentData::new(file_position, line_number, line))
     }
     
-    pub fn new_command(file_position: u32, line_number: u32, original_line: String, command_type: String) -> Self {
-        GCodeLine::Command(CommandData::new(file_position, line_number, original_line, command_type))
+    pub fn new_command(file_position: u32, line_number: u32, line: &str, command_type: &str) -> Self {
+        GCodeLine::Command(CommandData::new(file_position, line_number, line, command_type))
     }
     
-    pub fn new_mcode(file_position: u32, line_number: u32, original_line: String, mcode_number: u32) -> Self {
-        GCodeLine::MCode(MCodeData::new(file_position, line_number, original_line, mcode_number))
+    pub fn new_mcode(file_position: u32, line_number: u32, line: &str, mcode_number: u32) -> Self {
+        GCodeLine::MCode(MCodeData::new(file_position, line_number, line, mcode_number))
     }
 }
 
@@ -356,8 +449,8 @@ impl GCodeLine {
 pub struct ToolCommand {
     pub file_position: u32,
     pub line_number: u32,
-    pub original_line: String,
-    pub command_type: String, // "TOOL_CHANGE", "SET_HOTEND_TEMP", etc.
+    pub line_length: u32, // Line text is file_position..file_position + line_length of the source
+    pub command_type: CommandName, // "TOOL_CHANGE", "SET_HOTEND_TEMP", etc.
     pub tool_number: Option<u32>,
     pub temperature: Option<f64>,
     pub wait_for_temperature: bool,
@@ -367,14 +460,14 @@ impl ToolCommand {
     pub fn new(
         file_position: u32,
         line_number: u32,
-        original_line: String,
-        command_type: String,
+        line: &str,
+        command_type: &str,
     ) -> Self {
         Self {
             file_position,
             line_number,
-            original_line,
-            command_type,
+            line_length: line.len() as u32,
+            command_type: CommandName::new(command_type),
             tool_number: None,
             temperature: None,
             wait_for_temperature: false,
@@ -0,0 +1,426 @@
+// Input format sniffing in front of the text pipeline
+// Unwraps gzip (.gcode.gz), zip/3MF (.gcode.3mf), binary G-code and MeatPack streams to plain G-code text.
+
+use crate::bgcode::{crc32, is_bgcode, BgcodeFile, BgcodeMetadata};
+use crate::error::{decode_utf8, ProcessingError};
+use crate::meatpack::{decode_meatpack, is_meatpack};
+use crate::thumbnails::Thumbnail;
+use serde::{Deserialize, Serialize};
+
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+const ZIP_LOCAL_HEADER: u32 = 0x0403_4B50;
+const ZIP_CENTRAL_HEADER: u32 = 0x0201_4B50;
+const ZIP_END_OF_DIRECTORY: u32 = 0x0605_4B50;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InputFormat {
+    Text,
+    Gzip,
+    Zip3mf,
+    Bgcode,
+    MeatPack,
+}
+
+/// Detect the container format from the leading bytes
+pub fn sniff_format(data: &[u8]) -> InputFormat {
+    if data.starts_with(&GZIP_MAGIC) {
+        InputFormat::Gzip
+    } else if data.len() >= 4 && read_u32(data, 0) == Some(ZIP_LOCAL_HEADER) {
+        InputFormat::Zip3mf
+    } else if is_bgcode(data) {
+        InputFormat::Bgcode
+    } else if is_meatpack(data) {
+        InputFormat::MeatPack
+    } else {
+        InputFormat::Text
+    }
+}
+
+/// What the container held besides the G-code text
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ArchiveInfo {
+    pub format: Option<InputFormat>,
+    pub plates: Vec<u32>,             // Plates with G-code (3MF)
+    pub selected_plate: Option<u32>,
+    pub plate_json: Option<String>,   // Metadata/plate_N.json as written by the slicer
+    pub slice_info: Option<String>,   // Metadata/slice_info.config (XML)
+    pub entries: Vec<String>,         // All archive entry names
+    pub bgcode: Option<BgcodeMetadata>,
+}
+
+/// G-code text unpacked from an input file
+#[derive(Clone, Debug, Default)]
+pub struct UnpackedInput {
+    pub text: String,
+    pub thumbnails: Vec<Thumbnail>, // Images stored outside the text (3MF, bgcode)
+    pub info: ArchiveInfo,
+}
+
+/// Unpack any supported input; `plate` selects a 3MF plate (first plate when None)
+pub fn unpack_input(data: &[u8], plate: Option<u32>) -> Result<UnpackedInput, ProcessingError> {
+    let format = sniff_format(data);
+
+    let mut unpacked = match format {
+        InputFormat::Text => UnpackedInput {
+            text: decode_utf8(data.to_vec())?,
+            ..Default::default()
+        },
+        InputFormat::Gzip => UnpackedInput {
+            text: decode_utf8(gunzip(data).map_err(ProcessingError::invalid_input)?)?,
+            ..Default::default()
+        },
+        InputFormat::Zip3mf => unpack_3mf(data, plate)?,
+        InputFormat::Bgcode => {
+            let file = BgcodeFile::decode(data).map_err(ProcessingError::invalid_input)?;
+            UnpackedInput {
+                text: file.to_gcode_text(),
+                info: ArchiveInfo { bgcode: Some(file.metadata), ..Default::default() },
+                thumbnails: file.thumbnails,
+            }
+        }
+        InputFormat::MeatPack => UnpackedInput {
+            text: decode_meatpack(data),
+            ..Default::default()
+        },
+    };
+
+    unpacked.info.format = Some(format);
+    Ok(unpacked)
+}
+
+/// Inflate a gzip member and verify its CRC32 and length trailer
+pub fn gunzip(data: &[u8]) -> Result<Vec<u8>, String> {
+    const FHCRC: u8 = 0x02;
+    const FEXTRA: u8 = 0x04;
+    const FNAME: u8 = 0x08;
+    const FCOMMENT: u8 = 0x10;
+
+    if data.len() < 18 || !data.starts_with(&GZIP_MAGIC) {
+        return Err("Not a gzip file".to_string());
+    }
+    if data[2] != 8 {
+        return Err(format!("Unsupported gzip compression method {}", data[2]));
+    }
+
+    let flags = data[3];
+    let mut offset = 10;
+    if flags & FEXTRA != 0 {
+        offset += 2 + read_u16(data, offset).ok_or("Truncated gzip header")? as usize;
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            // Zero-terminated strings
+            let end = data[offset.min(data.len())..].iter().position(|&b| b == 0).ok_or("Truncated gzip header")?;
+            offset += end + 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        offset += 2;
+    }
+    if offset > data.len() - 8 {
+        return Err("Truncated gzip header".to_string());
+    }
+
+    let inflated = miniz_oxide::inflate::decompress_to_vec(&data[offset..data.len() - 8])
+        .map_err(|e| format!("gzip inflate error: {:?}", e.status))?;
+
+    let expected_crc = read_u32(data, data.len() - 8).unwrap_or(0);
+    let expected_size = read_u32(data, data.len() - 4).unwrap_or(0);
+    if inflated.len() as u32 != expected_size || crc32(&inflated) != expected_crc {
+        return Err("gzip CRC or length mismatch".to_string());
+    }
+    Ok(inflated)
+}
+
+struct ZipEntry {
+    name: String,
+    method: u16,
+    compressed_size: usize,
+    uncompressed_size: usize,
+    local_header_offset: usize,
+}
+
+/// Minimal zip reader (stored and deflate entries, no ZIP64)
+pub struct ZipArchive<'a> {
+    data: &'a [u8],
+    entries: Vec<ZipEntry>,
+}
+
+impl<'a> ZipArchive<'a> {
+    pub fn open(data: &'a [u8]) -> Result<Self, String> {
+        // End of central directory record sits in the last 22 + comment bytes
+        let search_start = data.len().saturating_sub(22 + u16::MAX as usize);
+        let eocd = (search_start..data.len().saturating_sub(21))
+            .rev()
+            .find(|&i| read_u32(data, i) == Some(ZIP_END_OF_DIRECTORY))
+            .ok_or("Zip end of central directory not found")?;
+
+        let entry_count = read_u16(data, eocd + 10).ok_or("Truncated zip directory")? as usize;
+        let mut offset = read_u32(data, eocd + 16).ok_or("Truncated zip directory")? as usize;
+        if offset == u32::MAX as usize {
+            return Err("ZIP64 archives are not supported".to_string());
+        }
+
+        let mut entries = Vec::with_capacity(entry_count);
+        for _ in 0..entry_count {
+            if read_u32(data, offset) != Some(ZIP_CENTRAL_HEADER) {
+                return Err(format!("Corrupt zip central directory at byte {}", offset));
+            }
+            let field = |at: usize| read_u32(data, offset + at).ok_or("Truncated zip directory");
+            let short = |at: usize| read_u16(data, offset + at).ok_or("Truncated zip directory");
+
+            let method = short(10)?;
+            let compressed_size = field(20)? as usize;
+            let uncompressed_size = field(24)? as usize;
+            let name_len = short(28)? as usize;
+            let extra_len = short(30)? as usize;
+            let comment_len = short(32)? as usize;
+            let local_header_offset = field(42)? as usize;
+
+            if compressed_size == u32::MAX as usize || local_header_offset == u32::MAX as usize {
+                return Err("ZIP64 archives are not supported".to_string());
+            }
+
+            let name_bytes = data.get(offset + 46..offset + 46 + name_len).ok_or("Truncated zip directory")?;
+            entries.push(ZipEntry {
+                name: String::from_utf8_lossy(name_bytes).into_owned(),
+                method,
+                compressed_size,
+                uncompressed_size,
+                local_header_offset,
+            });
+            offset += 46 + name_len + extra_len + comment_len;
+        }
+
+        Ok(Self { data, entries })
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.entries.iter().map(|e| e.name.clone()).collect()
+    }
+
+    /// Read and decompress one entry
+    pub fn read(&self, name: &str) -> Result<Vec<u8>, String> {
+        let entry = self.entries.iter().find(|e| e.name == name)
+            .ok_or_else(|| format!("Zip entry '{}' not found", name))?;
+
+        let header = entry.local_header_offset;
+        if read_u32(self.data, header) != Some(ZIP_LOCAL_HEADER) {
+            return Err(format!("Corrupt zip local header for '{}'", name));
+        }
+        // Local name/extra lengths can differ from the central directory
+        let name_len = read_u16(self.data, header + 26).unwrap_or(0) as usize;
+        let extra_len = read_u16(self.data, header + 28).unwrap_or(0) as usize;
+        let start = header + 30 + name_len + extra_len;
+        let stored = self.data.get(start..start + entry.compressed_size)
+            .ok_or_else(|| format!("Truncated zip entry '{}'", name))?;
+
+        let bytes = match entry.method {
+            0 => stored.to_vec(),
+            8 => miniz_oxide::inflate::decompress_to_vec(stored)
+                .map_err(|e| format!("Inflate error in '{}': {:?}", name, e.status))?,
+            other => return Err(format!("Unsupported zip compression method {} for '{}'", other, name)),
+        };
+
+        if bytes.len() != entry.uncompressed_size {
+            return Err(format!("Size mismatch in zip entry '{}'", name));
+        }
+        Ok(bytes)
+    }
+}
+
+// Bambu/Orca .gcode.3mf: Metadata/plate_N.gcode with plate_N.json and plate images
+fn unpack_3mf(data: &[u8], plate: Option<u32>) -> Result<UnpackedInput, ProcessingError> {
+    let archive = ZipArchive::open(data).map_err(ProcessingError::invalid_input)?;
+    let names = archive.names();
+
+    let mut plates: Vec<u32> = names.iter()
+        .filter_map(|name| name.strip_prefix("Metadata/plate_")?.strip_suffix(".gcode")?.parse::<u32>().ok())
+        .collect();
+    plates.sort_unstable();
+
+    // Plain zip holding a single .gcode file
+    if plates.is_empty() {
+        let gcode_name = names.iter().find(|n| n.to_lowercase().ends_with(".gcode"))
+            .ok_or_else(|| ProcessingError::invalid_input("Archive contains no G-code"))?;
+        return Ok(UnpackedInput {
+            text: decode_utf8(archive.read(gcode_name).map_err(ProcessingError::invalid_input)?)?,
+            info: ArchiveInfo { entries: names, ..Default::default() },
+            ..Default::default()
+        });
+    }
+
+    let selected = match plate {
+        Some(p) if plates.contains(&p) => p,
+        Some(p) => return Err(ProcessingError::invalid_input(format!("Plate {} not found (available: {:?})", p, plates))),
+        None => plates[0],
+    };
+
+    let plate_gcode = archive.read(&format!("Metadata/plate_{}.gcode", selected)).map_err(ProcessingError::invalid_input)?;
+    let text = decode_utf8(plate_gcode)?;
+    let read_text = |name: &str| {
+        archive.read(name).ok().map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+    };
+
+    let thumbnails = names.iter()
+        .filter(|name| is_plate_image(name, selected))
+        .filter_map(|name| archive.read(name).ok())
+        .map(|bytes| {
+            let (width, height) = png_size(&bytes).unwrap_or((0, 0));
+            Thumbnail::new("PNG", width, height, bytes)
+        })
+        .collect();
+
+    Ok(UnpackedInput {
+        text,
+        thumbnails,
+        info: ArchiveInfo {
+            plate_json: read_text(&format!("Metadata/plate_{}.json", selected)),
+            slice_info: read_text("Metadata/slice_info.config"),
+            plates,
+            selected_plate: Some(selected),
+            entries: names,
+            ..Default::default()
+        },
+    })
+}
+
+// "Metadata/plate_1.png", "Metadata/plate_1_small.png", "Metadata/top_1.png", ... but not pick buffers
+fn is_plate_image(name: &str, plate: u32) -> bool {
+    let Some(stem) = name.strip_prefix("Metadata/").and_then(|n| n.strip_suffix(".png")) else {
+        return false;
+    };
+    let plate = plate.to_string();
+    !stem.starts_with("pick") && stem.split('_').any(|part| part == plate)
+}
+
+// Width and height from the PNG IHDR chunk
+fn png_size(bytes: &[u8]) -> Option<(u32, u32)> {
+    if bytes.len() < 24 || &bytes[1..4] != b"PNG" {
+        return None;
+    }
+    let width = u32::from_be_bytes(bytes[16..20].try_into().ok()?);
+    let height = u32::from_be_bytes(bytes[20..24].try_into().ok()?);
+    Some((width, height))
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    fn gzip(content: &[u8]) -> Vec<u8> {
+        let mut out = vec![0x1F, 0x8B, 8, 0x08, 0, 0, 0, 0, 0, 3];
+        out.extend_from_slice(b"part.gcode\0"); // FNAME
+        out.extend_from_slice(&miniz_oxide::deflate::compress_to_vec(content, 6));
+        out.extend_from_slice(&crc32(content).to_le_bytes());
+        out.extend_from_slice(&(content.len() as u32).to_le_bytes());
+        out
+    }
+
+    // Build a zip with deflated entries
+    fn zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut central = Vec::new();
+        for (name, content) in files {
+            let compressed = miniz_oxide::deflate::compress_to_vec(content, 6);
+            let offset = out.len() as u32;
+            let mut header = Vec::new();
+            header.extend_from_slice(&[20, 0, 0, 0, 8, 0, 0, 0, 0, 0]);
+            header.extend_from_slice(&crc32(content).to_le_bytes());
+            header.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+            header.extend_from_slice(&(content.len() as u32).to_le_bytes());
+            header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            header.extend_from_slice(&[0, 0]);
+
+            out.extend_from_slice(&ZIP_LOCAL_HEADER.to_le_bytes());
+            out.extend_from_slice(&header);
+            out.extend_from_slice(name.as_bytes());
+            out.extend_from_slice(&compressed);
+
+            central.extend_from_slice(&ZIP_CENTRAL_HEADER.to_le_bytes());
+            central.extend_from_slice(&[20, 0]);
+            central.extend_from_slice(&header);
+            central.extend_from_slice(&[0; 10]); // Comment length, disk, attributes
+            central.extend_from_slice(&offset.to_le_bytes());
+            central.extend_from_slice(name.as_bytes());
+        }
+        let directory_offset = out.len() as u32;
+        out.extend_from_slice(&central);
+        out.extend_from_slice(&ZIP_END_OF_DIRECTORY.to_le_bytes());
+        out.extend_from_slice(&[0, 0, 0, 0]);
+        out.extend_from_slice(&(files.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(files.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(central.len() as u32).to_le_bytes());
+        out.extend_from_slice(&directory_offset.to_le_bytes());
+        out.extend_from_slice(&[0, 0]);
+        out
+    }
+
+    #[test]
+    fn test_gzip_input() {
+        let text = "G28\nG1 X10 Y10 E1\n".repeat(50);
+        let packed = gzip(text.as_bytes());
+        assert_eq!(sniff_format(&packed), InputFormat::Gzip);
+
+        let unpacked = unpack_input(&packed, None).unwrap();
+        assert_eq!(unpacked.text, text);
+
+        let mut corrupt = packed.clone();
+        let crc_at = corrupt.len() - 8;
+        corrupt[crc_at] ^= 0xFF;
+        assert!(gunzip(&corrupt).is_err());
+    }
+
+    #[test]
+    fn test_3mf_plate_selection() {
+        let mut png = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 13];
+        png.extend_from_slice(b"IHDR");
+        png.extend_from_slice(&512u32.to_be_bytes());
+        png.extend_from_slice(&256u32.to_be_bytes());
+
+        let archive = zip(&[
+            ("3D/3dmodel.model", b"<model/>"),
+            ("Metadata/plate_1.gcode", b"; plate one\nG1 X1\n"),
+            ("Metadata/plate_2.gcode", b"; plate two\nG1 X2\n"),
+            ("Metadata/plate_2.json", b"{\"bbox_all\":[0,0,10,10]}"),
+            ("Metadata/plate_2.png", &png),
+            ("Metadata/pick_2.png", &png),
+            ("Metadata/slice_info.config", b"<config/>"),
+        ]);
+        assert_eq!(sniff_format(&archive), InputFormat::Zip3mf);
+
+        let first = unpack_input(&archive, None).unwrap();
+        assert_eq!(first.info.plates, vec![1, 2]);
+        assert_eq!(first.info.selected_plate, Some(1));
+        assert!(first.text.starts_with("; plate one"));
+
+        let second = unpack_input(&archive, Some(2)).unwrap();
+        assert!(second.text.starts_with("; plate two"));
+        assert_eq!(second.info.plate_json.as_deref(), Some("{\"bbox_all\":[0,0,10,10]}"));
+        assert_eq!(second.info.slice_info.as_deref(), Some("<config/>"));
+        assert_eq!(second.thumbnails.len(), 1);
+        assert_eq!((second.thumbnails[0].width(), second.thumbnails[0].height()), (512, 256));
+
+        let missing = unpack_input(&archive, Some(7)).unwrap_err();
+        assert_eq!(missing.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_plain_text_passthrough() {
+        let unpacked = unpack_input(b"G28\n", None).unwrap();
+        assert_eq!(unpacked.info.format, Some(InputFormat::Text));
+        assert_eq!(unpacked.text, "G28\n");
+        assert_eq!(unpack_input(b"G28\n\xC3(", None).unwrap_err().kind(), ErrorKind::InvalidUtf8);
+    }
+}
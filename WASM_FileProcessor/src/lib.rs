@@ -1,7 +1,18 @@
 use wasm_bindgen::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 use crate::gcode_line::Color4;
+use crate::slicers::ProfileSlicer::SlicerProfile;
+use crate::slicers::FeatureType;
+use crate::color_theme::{ColorTheme, BUILTIN_THEMES};
+use crate::bgcode::BgcodeFile;
+use crate::input_format::{unpack_input, ArchiveInfo};
+use crate::segment_store::{Segment, SegmentStore};
+use crate::spatial_index::{SegmentHit, SpatialIndex};
+use crate::compact_buffers::{compact_segment, COMPACT_FLOATS_PER_SEGMENT};
+use crate::segment_filter::SegmentFilter;
+use crate::playback::Playback;
+use crate::diagnostics::LintSettings;
+use crate::machine_profile::MachineProfile;
 
 // Import our modules
 mod gcode_line;
@@ -10,6 +21,26 @@ mod processor;
 mod GCodeCommands;
 mod slicers;
 mod utils;
+mod color_theme;
+mod thumbnails;
+mod meatpack;
+mod bgcode;
+mod input_format;
+mod segment_store;
+mod compact_buffers;
+mod quantized_buffers;
+mod lod;
+mod tube_mesh;
+mod spatial_index;
+mod pick_id;
+mod segment_filter;
+mod progress_colors;
+mod playback;
+mod machine_state;
+mod resume;
+mod error;
+mod diagnostics;
+mod machine_profile;
 
 #[cfg(test)]
 mod tests;
@@ -18,12 +49,20 @@ mod tests;
 pub use gcode_line::*;
 pub use processor_properties::*;
 pub use processor::*;
+pub use thumbnails::Thumbnail;
+pub use quantized_buffers::QuantizedRenderBuffers;
+pub use progress_colors::ProgressColorUpdate;
+pub use tube_mesh::{TubeCrossSection, TubeJoint, TubeMesh};
+pub use error::{ErrorKind, ProcessingError};
 
 // Set up panic hook and allocator for WASM
 #[cfg(feature = "wee_alloc")]
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
+// Returned by the methods that need the processed text when none is retained
+const NO_SOURCE_ERROR: &str = "Processed file text not retained (call set_retain_source(true) before processing)";
+
 #[wasm_bindgen(start)]
 pub fn main() {
     console_error_panic_hook::set_once();
@@ -55,6 +94,7 @@ macro_rules! console_log {
 pub struct ProcessingResult {
     success: bool,
     error_message: String,
+    error: Option<ProcessingError>,
     line_count: usize,
     move_count: usize,
     processing_time_ms: f64,
@@ -72,6 +112,12 @@ impl ProcessingResult {
         self.error_message.clone()
     }
     
+    /// Structured error (kind, line, byte, context), or undefined on success
+    #[wasm_bindgen(getter)]
+    pub fn error(&self) -> Option<ProcessingError> {
+        self.error.clone()
+    }
+    
     #[wasm_bindgen]
     pub fn has_error(&self) -> bool {
         !self.error_message.is_empty()
@@ -82,6 +128,7 @@ impl ProcessingResult {
         ProcessingResult {
             success,
             error_message,
+            error: None,
             line_count,
             move_count,
             processing_time_ms,
@@ -104,17 +151,36 @@ impl ProcessingResult {
     }
 }
 
+impl ProcessingResult {
+    fn failed(error: ProcessingError, processing_time_ms: f64) -> ProcessingResult {
+        ProcessingResult {
+            success: false,
+            error_message: error.to_string(),
+            error: Some(error),
+            line_count: 0,
+            move_count: 0,
+            processing_time_ms,
+        }
+    }
+}
+
 // Render buffer data for fast mesh generation
 #[wasm_bindgen]
 pub struct RenderBuffers {
-    matrix_data: Vec<f32>,
+    matrix_data: Vec<f32>, // Empty in the compact layout
+    segment_data: Vec<f32>, // Compact layout only: start xyz, end xyz, width, height per segment
     color_data: Vec<f32>, 
-    pick_data: Vec<f32>,
+    pick_data: Vec<f32>, // Legacy 24-bit line number colors (alias above 16,777,215 lines)
+    pick_id_data: Vec<u32>, // 32-bit pick IDs keyed on the segment index, see `decode_pick_id`
+    pick_rgba_data: Vec<f32>, // `pick_id_data` encoded over all four RGBA channels
     file_position_data: Vec<f32>,
     file_end_position_data: Vec<f32>,
     tool_data: Vec<f32>,
     feed_rate_data: Vec<f32>,
     is_perimeter_data: Vec<f32>,
+    // LOD and filtered buffers only: inclusive range of original segment indices behind each segment
+    source_first_data: Vec<u32>,
+    source_last_data: Vec<u32>,
     segment_count: u32,
 }
 
@@ -130,6 +196,17 @@ impl RenderBuffers {
         self.matrix_data.clone()
     }
 
+    #[wasm_bindgen(getter)]
+    pub fn segment_data(&self) -> Vec<f32> {
+        self.segment_data.clone()
+    }
+
+    /// True if the buffers use the compact 8-float layout instead of matrices
+    #[wasm_bindgen(getter)]
+    pub fn is_compact(&self) -> bool {
+        !self.segment_data.is_empty()
+    }
+
     #[wasm_bindgen(getter)]
     pub fn color_data(&self) -> Vec<f32> {
         self.color_data.clone()
@@ -140,6 +217,16 @@ impl RenderBuffers {
         self.pick_data.clone()
     }
 
+    #[wasm_bindgen(getter)]
+    pub fn pick_id_data(&self) -> Vec<u32> {
+        self.pick_id_data.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn pick_rgba_data(&self) -> Vec<f32> {
+        self.pick_rgba_data.clone()
+    }
+
     #[wasm_bindgen(getter)]
     pub fn file_position_data(&self) -> Vec<f32> {
         self.file_position_data.clone()
@@ -164,6 +251,117 @@ impl RenderBuffers {
     pub fn is_perimeter_data(&self) -> Vec<f32> {
         self.is_perimeter_data.clone()
     }
+
+    /// First original segment index per segment (empty unless generated with a LOD level or filter)
+    #[wasm_bindgen(getter)]
+    pub fn source_first_data(&self) -> Vec<u32> {
+        self.source_first_data.clone()
+    }
+
+    /// Last original segment index per segment (empty unless generated with a LOD level or filter)
+    #[wasm_bindgen(getter)]
+    pub fn source_last_data(&self) -> Vec<u32> {
+        self.source_last_data.clone()
+    }
+
+    /// LOD or filtered segment covering an original segment index, or None if it was dropped
+    #[wasm_bindgen]
+    pub fn find_lod_segment(&self, source_index: u32) -> Option<u32> {
+        lod::lod_index_of(&self.source_first_data, &self.source_last_data, source_index).map(|index| index as u32)
+    }
+}
+
+// Zero-copy views over wasm memory
+//
+// Each `*_view` returns a Float32Array aliasing the buffer inside wasm memory, so
+// no data is copied across the boundary. Lifetime contract:
+// - The view is valid until this RenderBuffers is freed (`free()`) or mutated
+//   (`recolor_render_buffers`), and until the next call into the module that may
+//   allocate: wasm memory growth detaches every existing view.
+// - Upload the view (gl.bufferData / bufferSubData) or copy it (`view.slice()`)
+//   immediately after creating it; never keep a view across calls.
+// - Call `free()` once the data is on the GPU to release the wasm memory.
+#[wasm_bindgen]
+impl RenderBuffers {
+    /// View of `matrix_data` (16 floats per segment)
+    #[wasm_bindgen]
+    pub fn matrix_view(&self) -> js_sys::Float32Array {
+        // SAFETY: see the lifetime contract above
+        unsafe { js_sys::Float32Array::view(&self.matrix_data) }
+    }
+
+    /// View of `segment_data` (8 floats per segment, compact layout)
+    #[wasm_bindgen]
+    pub fn segment_view(&self) -> js_sys::Float32Array {
+        unsafe { js_sys::Float32Array::view(&self.segment_data) }
+    }
+
+    /// View of `color_data` (RGBA per segment)
+    #[wasm_bindgen]
+    pub fn color_view(&self) -> js_sys::Float32Array {
+        unsafe { js_sys::Float32Array::view(&self.color_data) }
+    }
+
+    /// View of `pick_data` (RGB pick color per segment)
+    #[wasm_bindgen]
+    pub fn pick_view(&self) -> js_sys::Float32Array {
+        unsafe { js_sys::Float32Array::view(&self.pick_data) }
+    }
+
+    /// View of `pick_id_data` (one u32 per segment, for integer pick targets)
+    #[wasm_bindgen]
+    pub fn pick_id_view(&self) -> js_sys::Uint32Array {
+        unsafe { js_sys::Uint32Array::view(&self.pick_id_data) }
+    }
+
+    /// View of `pick_rgba_data` (RGBA pick color per segment, for RGBA8 pick targets)
+    #[wasm_bindgen]
+    pub fn pick_rgba_view(&self) -> js_sys::Float32Array {
+        unsafe { js_sys::Float32Array::view(&self.pick_rgba_data) }
+    }
+
+    #[wasm_bindgen]
+    pub fn file_position_view(&self) -> js_sys::Float32Array {
+        unsafe { js_sys::Float32Array::view(&self.file_position_data) }
+    }
+
+    #[wasm_bindgen]
+    pub fn file_end_position_view(&self) -> js_sys::Float32Array {
+        unsafe { js_sys::Float32Array::view(&self.file_end_position_data) }
+    }
+
+    #[wasm_bindgen]
+    pub fn tool_view(&self) -> js_sys::Float32Array {
+        unsafe { js_sys::Float32Array::view(&self.tool_data) }
+    }
+
+    #[wasm_bindgen]
+    pub fn feed_rate_view(&self) -> js_sys::Float32Array {
+        unsafe { js_sys::Float32Array::view(&self.feed_rate_data) }
+    }
+
+    #[wasm_bindgen]
+    pub fn is_perimeter_view(&self) -> js_sys::Float32Array {
+        unsafe { js_sys::Float32Array::view(&self.is_perimeter_data) }
+    }
+
+    /// Release the per-segment vectors after upload while keeping `segment_count`
+    #[wasm_bindgen]
+    pub fn release_data(&mut self) {
+        self.matrix_data = Vec::new();
+        self.segment_data = Vec::new();
+        self.color_data = Vec::new();
+        self.pick_data = Vec::new();
+        self.pick_id_data = Vec::new();
+        self.pick_rgba_data = Vec::new();
+        self.file_position_data = Vec::new();
+        self.file_end_position_data = Vec::new();
+        self.tool_data = Vec::new();
+        self.feed_rate_data = Vec::new();
+        self.is_perimeter_data = Vec::new();
+        self.source_first_data = Vec::new();
+        self.source_last_data = Vec::new();
+    }
 }
 
 // Position data for nozzle animation and rendering (enhanced for matrix calculation)
@@ -183,6 +381,7 @@ pub struct PositionData {
     start_z: f64,
     length: f64,
     layer_height: f64,
+    width: f64,
     is_perimeter: bool,
     is_support: bool,
     
@@ -192,6 +391,7 @@ pub struct PositionData {
     file_position: u32,
     file_end_position: u32,
     tool: u32,
+    feature: FeatureType,
 }
 
 #[wasm_bindgen]
@@ -202,8 +402,9 @@ impl PositionData {
             x, y, z, feed_rate, extruding,
             // Default values for backward compatibility
             start_x: x, start_y: y, start_z: z,
-            length: 0.0, layer_height: 0.2, is_perimeter: true, is_support: false,
-            color: Color4::white(), line_number: 0, file_position: 0, file_end_position: 0, tool: 0
+            length: 0.0, layer_height: 0.2, width: 0.4, is_perimeter: true, is_support: false,
+            color: Color4::white(), line_number: 0, file_position: 0, file_end_position: 0, tool: 0,
+            feature: FeatureType::Unknown
         }
     }
     
@@ -219,26 +420,34 @@ impl PositionData {
             x: end_x, y: end_y, z: end_z, 
             feed_rate, extruding,
             start_x, start_y, start_z,
-            length, layer_height, is_perimeter, is_support: false,
-            color: Color4::white(), line_number: 0, file_position: 0, file_end_position: 0, tool: 0
+            length, layer_height, width: 0.4, is_perimeter, is_support: false,
+            color: Color4::white(), line_number: 0, file_position: 0, file_end_position: 0, tool: 0,
+            feature: FeatureType::Unknown
         }
     }
 
-    // Enhanced constructor with move color data (internal use only)
-    pub(crate) fn new_with_color(
-        start_x: f64, start_y: f64, start_z: f64,
-        end_x: f64, end_y: f64, end_z: f64,
-        feed_rate: f64, extruding: bool, layer_height: f64, is_perimeter: bool,
-        color: Color4, line_number: u32, file_position: u32, file_end_position: u32, tool: u32, is_support: bool
-    ) -> PositionData {
-        let length = ((end_x - start_x).powi(2) + (end_y - start_y).powi(2) + (end_z - start_z).powi(2)).sqrt();
+    // Expand a stored segment into the JS-facing record (internal use only)
+    pub(crate) fn from_segment(segment: &Segment) -> PositionData {
+        let [start_x, start_y, start_z] = segment.start.map(|v| v as f64);
+        let [x, y, z] = segment.end.map(|v| v as f64);
+        let [r, g, b, a] = segment.color_rgba().map(|v| v as f64);
         
-        PositionData { 
-            x: end_x, y: end_y, z: end_z, 
-            feed_rate, extruding,
+        PositionData {
+            x, y, z,
+            feed_rate: segment.feed_rate as f64,
+            extruding: segment.extruding(),
             start_x, start_y, start_z,
-            length, layer_height, is_perimeter, is_support,
-            color, line_number, file_position, file_end_position, tool
+            length: segment.length() as f64,
+            layer_height: segment.layer_height as f64,
+            width: segment.width as f64,
+            is_perimeter: segment.is_perimeter(),
+            is_support: segment.is_support(),
+            color: Color4::new(r, g, b, a),
+            line_number: segment.line_number,
+            file_position: segment.file_position,
+            file_end_position: segment.file_end_position,
+            tool: segment.tool as u32,
+            feature: segment.feature(),
         }
     }
     
@@ -274,9 +483,19 @@ impl PositionData {
     #[wasm_bindgen(getter)]
     pub fn layer_height(&self) -> f64 { self.layer_height }
     
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> f64 { self.width }
+    
     #[wasm_bindgen(getter)]
     pub fn is_perimeter(&self) -> bool { self.is_perimeter }
 
+    #[wasm_bindgen(getter)]
+    pub fn is_support(&self) -> bool { self.is_support }
+
+    /// Feature name as accepted by `SegmentFilter.features` ("Perimeter", "Infill", ... or "Unknown" for travel)
+    #[wasm_bindgen(getter)]
+    pub fn feature(&self) -> String { format!("{:?}", self.feature) }
+
     #[wasm_bindgen(getter)]
     pub fn line_number(&self) -> u32 { self.line_number }
     
@@ -290,12 +509,65 @@ impl PositionData {
     pub fn tool(&self) -> u32 { self.tool }
 }
 
+// Interpolated nozzle state during time-based playback. `state` only describes the current move
+// (feed rate, tool, feature, support/perimeter, extruding, width, layer height, line); E, temperatures,
+// fan and positioning modes come from `GCodeProcessor::get_playback_machine_state`
+#[wasm_bindgen]
+pub struct PlaybackFrame {
+    x: f64,
+    y: f64,
+    z: f64,
+    segment_index: u32,
+    segment_progress: f64, // 0..=1 through the current segment
+    time: f64,             // Simulated seconds since the start of the print
+    finished: bool,
+    state: PositionData,   // Current move: feed rate, tool, feature, extruding, line...
+}
+
+#[wasm_bindgen]
+impl PlaybackFrame {
+    #[wasm_bindgen(getter)]
+    pub fn x(&self) -> f64 { self.x }
+    
+    #[wasm_bindgen(getter)]
+    pub fn y(&self) -> f64 { self.y }
+    
+    #[wasm_bindgen(getter)]
+    pub fn z(&self) -> f64 { self.z }
+    
+    #[wasm_bindgen(getter)]
+    pub fn segment_index(&self) -> u32 { self.segment_index }
+    
+    #[wasm_bindgen(getter)]
+    pub fn segment_progress(&self) -> f64 { self.segment_progress }
+    
+    #[wasm_bindgen(getter)]
+    pub fn time(&self) -> f64 { self.time }
+    
+    #[wasm_bindgen(getter)]
+    pub fn finished(&self) -> bool { self.finished }
+    
+    #[wasm_bindgen(getter)]
+    pub fn line_number(&self) -> u32 { self.state.line_number }
+    
+    #[wasm_bindgen(getter)]
+    pub fn file_position(&self) -> u32 { self.state.file_position }
+    
+    #[wasm_bindgen(getter)]
+    pub fn state(&self) -> PositionData { self.state.clone() }
+}
+
 // Main G-code processor class
 #[wasm_bindgen]
 pub struct GCodeProcessor {
     processor: FileProcessor,
-    position_tracker: HashMap<u32, PositionData>,
-    sorted_positions: Vec<u32>,
+    segments: SegmentStore, // Parsed moves in file order
+    input_info: Option<ArchiveInfo>, // Container details when the last file came through `process_input`
+    spatial_index: Option<SpatialIndex>, // Built on request by `build_spatial_index`
+    playback: Option<Playback>, // Built on request by `build_playback`
+    source: String, // Processed text for state replay, resume and lint; only kept with `retain_source`
+    retain_source: bool, // Set from JS; kept across files
+    machine_profile: Option<MachineProfile>, // Set from JS; kept across files
 }
 
 #[wasm_bindgen]
@@ -306,8 +578,13 @@ impl GCodeProcessor {
         
         GCodeProcessor {
             processor: FileProcessor::new(),
-            position_tracker: HashMap::new(),
-            sorted_positions: Vec::new(),
+            segments: SegmentStore::new(),
+            input_info: None,
+            spatial_index: None,
+            playback: None,
+            source: String::new(),
+            retain_source: false,
+            machine_profile: None,
         }
     }
     
@@ -321,107 +598,604 @@ impl GCodeProcessor {
         console_log!("Starting to process file with {} bytes", file_content.len());
         
         // Clear previous data
-        self.position_tracker.clear();
-        self.sorted_positions.clear();
+        self.segments.clear();
+        self.input_info = None;
+        self.spatial_index = None;
+        self.playback = None;
+        self.source = String::new();
         
         // Process the file
         match self.processor.process_file_content(file_content, progress_callback) {
-            Ok((gcode_lines, positions)) => {
-                // Store position data
-                self.position_tracker = positions.into_iter()
-                    .map(|(pos, data)| (pos, data))
-                    .collect();
-                
-                // Sort positions for animation
-                self.sorted_positions = self.position_tracker.keys().cloned().collect();
-                self.sorted_positions.sort();
+            Ok(segments) => {
+                // Segments arrive in file order, no sorting needed
+                self.segments = segments;
+                if self.retain_source {
+                    self.source = file_content.to_string();
+                }
                 
                 let processing_time = js_sys::Date::now() - start_time;
                 
-                console_log!("File processing completed: {} lines, {} positions, {:.2}ms", 
-                           gcode_lines.len(), self.position_tracker.len(), processing_time);
+                // Count every line in the file, including skipped thumbnail blocks
+                let line_count = self.processor.get_statistics().line_count as usize;
+                
+                console_log!("File processing completed: {} lines, {} segments ({} KB), {:.2}ms", 
+                           line_count, self.segments.len(), self.segments.memory_bytes() / 1024, processing_time);
                 
                 ProcessingResult {
                     success: true,
                     error_message: String::new(),
-                    line_count: gcode_lines.len(),
-                    move_count: self.position_tracker.len(),
+                    error: None,
+                    line_count,
+                    move_count: self.segments.len(),
                     processing_time_ms: processing_time,
                 }
             }
             Err(error) => {
                 console_log!("File processing failed: {}", error);
-                
-                ProcessingResult {
-                    success: false,
-                    error_message: error,
-                    line_count: 0,
-                    move_count: 0,
-                    processing_time_ms: js_sys::Date::now() - start_time,
-                }
+                ProcessingResult::failed(error, js_sys::Date::now() - start_time)
+            }
+        }
+    }
+    
+    /// Process raw file bytes: plain text, gzip, zip/3MF, binary G-code or MeatPack
+    /// `plate` selects the 3MF plate (first plate when omitted); file positions refer to `unpack_input_text` output
+    #[wasm_bindgen]
+    pub fn process_input(&mut self, 
+                        data: &[u8], 
+                        plate: Option<u32>, 
+                        progress_callback: Option<ProgressCallback>) -> ProcessingResult {
+        let start_time = js_sys::Date::now();
+        
+        let unpacked = match unpack_input(data, plate) {
+            Ok(unpacked) => unpacked,
+            Err(error) => {
+                console_log!("Input unpacking failed: {}", error);
+                return ProcessingResult::failed(error, js_sys::Date::now() - start_time);
+            }
+        };
+        
+        console_log!("Unpacked {:?} input to {} bytes of G-code", unpacked.info.format, unpacked.text.len());
+        
+        let mut result = self.process_file(&unpacked.text, progress_callback);
+        result.processing_time_ms = js_sys::Date::now() - start_time;
+        
+        if result.success {
+            // Images stored beside the G-code come after any embedded in the text
+            if !unpacked.thumbnails.is_empty() {
+                let mut thumbnails = self.processor.thumbnails().to_vec();
+                thumbnails.extend(unpacked.thumbnails);
+                self.processor.set_thumbnails(thumbnails);
+            }
+            self.input_info = Some(unpacked.info);
+        }
+        result
+    }
+    
+    /// Process a PrusaSlicer binary G-code (.bgcode) file
+    /// The decoded text is processed like an ASCII file; file positions refer to `decode_bgcode` output
+    #[wasm_bindgen]
+    pub fn process_bgcode(&mut self, 
+                         data: &[u8], 
+                         progress_callback: Option<ProgressCallback>) -> ProcessingResult {
+        if !bgcode::is_bgcode(data) {
+            return ProcessingResult::failed(ProcessingError::invalid_input("Not a binary G-code file (missing GCDE header)"), 0.0);
+        }
+        self.process_input(data, None, progress_callback)
+    }
+    
+    /// Container details of the last `process_input` file (format, plates, plate JSON, archive entries), or undefined
+    #[wasm_bindgen]
+    pub fn get_input_info(&self) -> Result<JsValue, JsValue> {
+        match &self.input_info {
+            Some(info) => serde_wasm_bindgen::to_value(info).map_err(|e| JsValue::from_str(&e.to_string())),
+            None => Ok(JsValue::UNDEFINED),
+        }
+    }
+    
+    /// File/printer/print/slicer metadata blocks of the last binary G-code file, or undefined
+    #[wasm_bindgen]
+    pub fn get_bgcode_metadata(&self) -> Result<JsValue, JsValue> {
+        match self.input_info.as_ref().and_then(|info| info.bgcode.as_ref()) {
+            Some(metadata) => serde_wasm_bindgen::to_value(metadata).map_err(|e| JsValue::from_str(&e.to_string())),
+            None => Ok(JsValue::UNDEFINED),
+        }
+    }
+    
+    /// Register a user-defined slicer profile (plain JS object matching `SlicerProfile`)
+    /// Registered profiles are checked before the built-in slicers on the next `process_file`
+    #[wasm_bindgen]
+    pub fn register_slicer_profile(&mut self, profile: JsValue) -> Result<(), JsValue> {
+        let profile: SlicerProfile = serde_wasm_bindgen::from_value(profile)
+            .map_err(|e| JsValue::from_str(&format!("Invalid slicer profile: {}", e)))?;
+        self.processor.register_slicer_profile(profile)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+    
+    /// Remove all user-defined slicer profiles
+    #[wasm_bindgen]
+    pub fn clear_slicer_profiles(&mut self) {
+        self.processor.clear_slicer_profiles();
+    }
+    
+    /// Names of the registered slicer profiles in detection order
+    #[wasm_bindgen]
+    pub fn get_slicer_profile_names(&self) -> Vec<String> {
+        self.processor.slicer_profile_names()
+    }
+    
+    /// Apply one of the built-in color themes (see `get_builtin_color_themes`)
+    /// Already processed moves are recolored; call `recolor_render_buffers` to update existing buffers
+    #[wasm_bindgen]
+    pub fn set_builtin_color_theme(&mut self, name: &str) -> Result<(), JsValue> {
+        let theme = ColorTheme::builtin(name)
+            .ok_or_else(|| JsValue::from_str(&format!("Unknown color theme: {}", name)))?;
+        self.processor.set_color_theme(Some(theme));
+        self.apply_color_theme();
+        Ok(())
+    }
+    
+    /// Apply a custom color theme (plain JS object matching `ColorTheme`)
+    #[wasm_bindgen]
+    pub fn set_color_theme(&mut self, theme: JsValue) -> Result<(), JsValue> {
+        let theme: ColorTheme = serde_wasm_bindgen::from_value(theme)
+            .map_err(|e| JsValue::from_str(&format!("Invalid color theme: {}", e)))?;
+        self.processor.set_color_theme(Some(theme));
+        self.apply_color_theme();
+        Ok(())
+    }
+    
+    /// Return to the detected slicer's own colors
+    #[wasm_bindgen]
+    pub fn clear_color_theme(&mut self) {
+        self.processor.set_color_theme(None);
+        self.apply_color_theme();
+    }
+    
+    /// Name of the active color theme, or empty when slicer colors are used
+    #[wasm_bindgen]
+    pub fn get_color_theme_name(&self) -> String {
+        self.processor.color_theme().map(|t| t.name.clone()).unwrap_or_default()
+    }
+    
+    /// Set the color of printed moves for `get_progress_color_update`; `enabled = false`
+    /// makes updates restore feature colors
+    #[wasm_bindgen]
+    pub fn set_progress_color(&mut self, r: f64, g: f64, b: f64, a: f64, enabled: bool) {
+        self.processor.set_progress_color(Color4::new(r, g, b, a), enabled);
+    }
+    
+    /// Segments whose printed state changed between two print positions (file bytes) and their
+    /// new colors; upload `color_data` at `color_offset` of the full (unfiltered, non-LOD) buffers
+    #[wasm_bindgen]
+    pub fn get_progress_color_update(&self, previous_position: u32, file_position: u32) -> ProgressColorUpdate {
+        let progress_color = self.processor.progress_color()
+            .map(|color| color.to_array().map(|channel| channel as f32));
+        ProgressColorUpdate::between(&self.segments, previous_position, file_position, progress_color)
+    }
+    
+    /// Copy a progress update into buffers produced by `generate_render_buffers`
+    #[wasm_bindgen]
+    pub fn apply_progress_color_update(&self, buffers: &mut RenderBuffers, update: &ProgressColorUpdate) {
+        update.apply_to(&mut buffers.color_data);
+    }
+    
+    /// Rewrite the color channel of buffers produced by `generate_render_buffers` in place
+    #[wasm_bindgen]
+    pub fn recolor_render_buffers(&self, buffers: &mut RenderBuffers) {
+        for (index, &packed) in self.segments.colors().iter().enumerate() {
+            let offset = index * 4;
+            if offset + 4 > buffers.color_data.len() {
+                break;
             }
+            buffers.color_data[offset..offset + 4].copy_from_slice(&segment_store::unpack_color(packed));
+        }
+    }
+    
+    /// Machine state before the line containing `file_position`: position and E, feed, modes,
+    /// units, workplace, tool, temperature targets, fan and feature (plain JS object matching `MachineState`)
+    #[wasm_bindgen]
+    pub fn get_state_at(&self, file_position: u32) -> Result<JsValue, JsValue> {
+        if self.source.is_empty() {
+            return Err(JsValue::from_str(NO_SOURCE_ERROR));
+        }
+        match self.processor.state_at(&self.source, file_position) {
+            Some(state) => serde_wasm_bindgen::to_value(&state).map_err(|e| JsValue::from_str(&e.to_string())),
+            None => Ok(JsValue::UNDEFINED),
         }
     }
     
+    /// G-code to restart the print at `layer` (0-based, as in `get_layer_count`): a preamble that
+    /// heats to the recorded targets, sets Z to the saved height, lifts `z_lift` mm, homes XY, travels
+    /// back and lowers, then restores fan, modes and E, followed by the original file from the layer start
+    #[wasm_bindgen]
+    pub fn generate_resume_gcode(&self, layer: u32, z_lift: f64) -> Result<String, JsValue> {
+        if self.source.is_empty() {
+            return Err(JsValue::from_str(NO_SOURCE_ERROR));
+        }
+        let start = resume::layer_start_position(&self.source, &self.segments, layer)
+            .ok_or_else(|| JsValue::from_str(&format!("Layer {} not found ({} layers)", layer, self.segments.layer_count())))?;
+        let state = self.processor.state_at(&self.source, start)
+            .ok_or_else(|| JsValue::from_str("No machine state recorded for this file"))?;
+        
+        let settings = resume::ResumeSettings { z_lift, ..Default::default() };
+        let preamble = resume::resume_preamble(&state, layer, &settings);
+        console_log!("Resuming at layer {} from line {} (byte {})", layer, state.line_number, start);
+        resume::resume_gcode(&self.source, start, &preamble)
+            .ok_or_else(|| JsValue::from_str("Layer start is not on a character boundary"))
+    }
+    
+    /// Lint the processed file: parse errors, malformed numbers, unknown commands, moves before
+    /// homing, cold extrusion, moves outside `build_volume`, negative feed rates and tools above
+    /// `tool_count`. `settings` is an optional plain JS object matching `LintSettings`; without a build
+    /// volume, moves are checked against the machine profile (circular beds included), and the tool
+    /// count defaults to the slicer's extruder count. Unknown and extended commands are reported once,
+    /// with `occurrences`. Returns an array of `Diagnostic` objects.
+    #[wasm_bindgen]
+    pub fn get_diagnostics(&self, settings: JsValue) -> Result<JsValue, JsValue> {
+        if self.source.is_empty() {
+            return Err(JsValue::from_str(NO_SOURCE_ERROR));
+        }
+        let mut settings: LintSettings = if settings.is_undefined() || settings.is_null() {
+            LintSettings::default()
+        } else {
+            serde_wasm_bindgen::from_value(settings)
+                .map_err(|e| JsValue::from_str(&format!("Invalid lint settings: {}", e)))?
+        };
+        let extruders = self.processor.slicer_config().nozzle_diameters.len() as u32;
+        if settings.tool_count.is_none() && extruders > 0 {
+            settings.tool_count = Some(extruders);
+        }
+        
+        let start_time = js_sys::Date::now();
+        let profile = self.effective_machine_profile();
+        let diagnostics = diagnostics::lint(&self.source, &settings, profile.as_ref());
+        console_log!("Lint found {} diagnostics in {:.2}ms", diagnostics.len(), js_sys::Date::now() - start_time);
+        serde_wasm_bindgen::to_value(&diagnostics).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+    
+    /// Set the machine profile (plain JS object matching `MachineProfile`: rectangular or circular
+    /// bed, origin and optional origin offset, max Z, excluded zones); used instead of the slicer's bed settings
+    #[wasm_bindgen]
+    pub fn set_machine_profile(&mut self, profile: JsValue) -> Result<(), JsValue> {
+        let profile: MachineProfile = serde_wasm_bindgen::from_value(profile)
+            .map_err(|e| JsValue::from_str(&format!("Invalid machine profile: {}", e)))?;
+        self.machine_profile = Some(profile);
+        Ok(())
+    }
+    
+    /// Return to the bed described by the file's slicer config
+    #[wasm_bindgen]
+    pub fn clear_machine_profile(&mut self) {
+        self.machine_profile = None;
+    }
+    
+    /// Active machine profile (set from JS, else derived from the slicer config), or undefined
+    #[wasm_bindgen]
+    pub fn get_machine_profile(&self) -> Result<JsValue, JsValue> {
+        match self.effective_machine_profile() {
+            Some(profile) => serde_wasm_bindgen::to_value(&profile).map_err(|e| JsValue::from_str(&e.to_string())),
+            None => Ok(JsValue::UNDEFINED),
+        }
+    }
+    
+    /// Bounding box of the extrusions per tool (array of `ToolBounds`, G-code axes)
+    #[wasm_bindgen]
+    pub fn get_tool_bounds(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(&machine_profile::tool_bounds(&self.segments))
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+    
+    /// Indices of segments leaving the printable volume (empty without a machine profile)
+    #[wasm_bindgen]
+    pub fn get_out_of_bounds_segments(&self) -> Vec<u32> {
+        let Some(profile) = self.effective_machine_profile() else {
+            return Vec::new();
+        };
+        let indices = machine_profile::out_of_bounds_segments(&self.segments, &profile);
+        console_log!("{} of {} segments leave the printable volume", indices.len(), self.segments.len());
+        indices
+    }
+    
+    /// Keep a copy of the processed text for `get_state_at`, `generate_resume_gcode` and
+    /// `get_diagnostics`. Off by default; applies from the next processed file.
+    #[wasm_bindgen]
+    pub fn set_retain_source(&mut self, retain: bool) {
+        self.retain_source = retain;
+        if !retain {
+            self.source = String::new();
+        }
+    }
+    
+    /// Drop the retained text; `get_state_at`, `generate_resume_gcode` and `get_diagnostics` are unavailable afterwards
+    #[wasm_bindgen]
+    pub fn release_source(&mut self) {
+        self.source = String::new();
+    }
+    
+    /// Typed slicer configuration (nozzle/filament sizes, colors, bed shape...) as a JS object
+    #[wasm_bindgen]
+    pub fn get_slicer_config(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self.processor.slicer_config())
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+    
+    /// Slicer estimates (print time, filament used, layer count, objects, max Z) as a JS object
+    #[wasm_bindgen]
+    pub fn get_job_metadata(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self.processor.job_metadata())
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+    
+    /// Thumbnails embedded in the processed file (collected during `process_file`)
+    #[wasm_bindgen]
+    pub fn get_thumbnails(&self) -> Vec<Thumbnail> {
+        self.processor.thumbnails().to_vec()
+    }
+    
+    /// Number of embedded thumbnails
+    #[wasm_bindgen]
+    pub fn get_thumbnail_count(&self) -> usize {
+        self.processor.thumbnails().len()
+    }
+    
     /// Get position data for a specific file position
     #[wasm_bindgen]
     pub fn get_position_data(&self, file_position: u32) -> Option<PositionData> {
-        self.position_tracker.get(&file_position).cloned()
+        self.segments.index_of(file_position)
+            .map(|index| PositionData::from_segment(&self.segments.get(index)))
     }
     
     /// Get all sorted positions (for animation)
+    /// One entry per G-code line: arc sub-segments share their line's position
     #[wasm_bindgen]
     pub fn get_sorted_positions(&self) -> Vec<u32> {
-        self.sorted_positions.clone()
+        self.segments.unique_file_positions()
     }
     
     /// Get position count
     #[wasm_bindgen]
     pub fn get_position_count(&self) -> usize {
-        self.position_tracker.len()
+        self.segments.len()
     }
     
     /// Find closest position to a target file position
     #[wasm_bindgen]
     pub fn find_closest_position(&self, target_position: u32) -> Option<u32> {
-        if self.sorted_positions.is_empty() {
-            return None;
+        self.segments.closest_file_position(target_position)
+    }
+    
+    /// Bytes used by the segment table
+    #[wasm_bindgen]
+    pub fn get_segment_memory_bytes(&self) -> usize {
+        self.segments.memory_bytes()
+    }
+    
+    /// Number of layers detected while storing segments
+    #[wasm_bindgen]
+    pub fn get_layer_count(&self) -> u32 {
+        self.segments.layer_count()
+    }
+
+    /// Position data for a segment by index (as returned by the spatial queries)
+    #[wasm_bindgen]
+    pub fn get_segment_position_data(&self, segment_index: u32) -> Option<PositionData> {
+        ((segment_index as usize) < self.segments.len())
+            .then(|| PositionData::from_segment(&self.segments.get(segment_index as usize)))
+    }
+    
+    /// Build the BVH used by `pick_ray`, `find_nearest_segment` and `query_box`.
+    /// `radius` is the pick tolerance around each segment (about half the extrusion width).
+    #[wasm_bindgen]
+    pub fn build_spatial_index(&mut self, radius: f32, include_travel: bool) -> usize {
+        let start_time = js_sys::Date::now();
+        let index = SpatialIndex::build(&self.segments, radius, include_travel);
+        let count = index.len();
+        console_log!("Spatial index over {} segments built in {:.2}ms", count, js_sys::Date::now() - start_time);
+        self.spatial_index = Some(index);
+        count
+    }
+    
+    /// First segment hit by a ray (direction need not be normalized); requires `build_spatial_index`
+    #[wasm_bindgen]
+    pub fn pick_ray(&self, origin_x: f32, origin_y: f32, origin_z: f32, dir_x: f32, dir_y: f32, dir_z: f32) -> Option<SegmentHit> {
+        self.spatial_index.as_ref()?.pick_ray([origin_x, origin_y, origin_z], [dir_x, dir_y, dir_z])
+    }
+    
+    /// Segment closest to a point within `max_distance`; requires `build_spatial_index`
+    #[wasm_bindgen]
+    pub fn find_nearest_segment(&self, x: f32, y: f32, z: f32, max_distance: f32) -> Option<SegmentHit> {
+        self.spatial_index.as_ref()?.nearest([x, y, z], max_distance)
+    }
+    
+    /// Indices of segments passing through an axis-aligned box; requires `build_spatial_index`
+    #[wasm_bindgen]
+    pub fn query_box(&self, min_x: f32, min_y: f32, min_z: f32, max_x: f32, max_y: f32, max_z: f32) -> Vec<u32> {
+        self.spatial_index.as_ref()
+            .map(|index| index.query_box([min_x, min_y, min_z], [max_x, max_y, max_z]))
+            .unwrap_or_default()
+    }
+
+    /// Time segments from their feed rates for `get_playback_frame`; returns the total seconds
+    #[wasm_bindgen]
+    pub fn build_playback(&mut self) -> f64 {
+        let playback = Playback::from_feed_rates(&self.segments);
+        let duration = playback.duration();
+        console_log!("Playback over {} segments: {:.1}s", playback.len(), duration);
+        self.playback = Some(playback);
+        duration
+    }
+    
+    /// Use per-segment durations (seconds) from a motion planner instead of feed rates
+    #[wasm_bindgen]
+    pub fn set_playback_durations(&mut self, durations: Vec<f32>) -> Result<f64, JsValue> {
+        if durations.len() != self.segments.len() {
+            return Err(JsValue::from_str(&format!("Expected {} durations, got {}", self.segments.len(), durations.len())));
         }
-        
-        // Binary search for closest position
-        match self.sorted_positions.binary_search(&target_position) {
-            Ok(index) => Some(self.sorted_positions[index]),
-            Err(index) => {
-                if index == 0 {
-                    Some(self.sorted_positions[0])
-                } else if index >= self.sorted_positions.len() {
-                    Some(self.sorted_positions[self.sorted_positions.len() - 1])
-                } else {
-                    // Find closest between index-1 and index
-                    let left = self.sorted_positions[index - 1];
-                    let right = self.sorted_positions[index];
-                    
-                    if target_position - left <= right - target_position {
-                        Some(left)
-                    } else {
-                        Some(right)
-                    }
-                }
-            }
+        let playback = Playback::from_durations(durations);
+        let duration = playback.duration();
+        self.playback = Some(playback);
+        Ok(duration)
+    }
+    
+    /// Total playback time in seconds (0 before `build_playback`)
+    #[wasm_bindgen]
+    pub fn get_playback_duration(&self) -> f64 {
+        self.playback.as_ref().map_or(0.0, |playback| playback.duration())
+    }
+    
+    /// Nozzle state after `elapsed_seconds` of wall time played at `speed` times real time
+    #[wasm_bindgen]
+    pub fn get_playback_frame(&self, elapsed_seconds: f64, speed: f64) -> Option<PlaybackFrame> {
+        let sample = self.playback.as_ref()?.sample(&self.segments, elapsed_seconds * speed)?;
+        let [x, y, z] = sample.position.map(|v| v as f64);
+        Some(PlaybackFrame {
+            x, y, z,
+            segment_index: sample.segment_index as u32,
+            segment_progress: sample.fraction as f64,
+            time: sample.time,
+            finished: sample.finished,
+            state: PositionData::from_segment(&self.segments.get(sample.segment_index)),
+        })
+    }
+    
+    /// Full machine state for the frame at `elapsed_seconds`, replayed up to the current move's line
+    /// (see `get_state_at`: E, feed, modes, units, workplace, tool, temperature targets, fan and
+    /// feature; the position is the move's start). Needs `set_retain_source(true)`
+    #[wasm_bindgen]
+    pub fn get_playback_machine_state(&self, elapsed_seconds: f64, speed: f64) -> Result<JsValue, JsValue> {
+        match self.get_playback_frame(elapsed_seconds, speed) {
+            Some(frame) => self.get_state_at(frame.file_position()),
+            None => Ok(JsValue::UNDEFINED),
         }
     }
+    
+    /// Simulated time at which the move at or after a file position starts, for seeking
+    #[wasm_bindgen]
+    pub fn get_playback_time_at(&self, file_position: u32) -> Option<f64> {
+        let playback = self.playback.as_ref()?;
+        let index = self.segments.file_positions().partition_point(|&p| p < file_position);
+        Some(playback.start_time(index))
+    }
+    
+    fn effective_machine_profile(&self) -> Option<MachineProfile> {
+        self.machine_profile.clone()
+            .or_else(|| MachineProfile::from_slicer_config(self.processor.slicer_config()))
+    }
+    
+    // Recolor stored moves from their feature type; travel moves (no feature) keep their color
+    fn apply_color_theme(&mut self) {
+        let processor = &self.processor;
+        self.segments.recolor(|feature| {
+            (*feature != FeatureType::Unknown).then(|| processor.feature_color(feature))
+        });
+    }
 
     /// Generate render buffers for fast mesh creation in JavaScript
     #[wasm_bindgen]
     pub fn generate_render_buffers(&self, nozzle_size: f32, padding: f32, progress_callback: Option<ProgressCallback>) -> RenderBuffers {
+        self.build_render_buffers(self.indexed_segments(), self.segments.len(), false, nozzle_size, padding, progress_callback)
+    }
+
+    /// Generate buffers for a level of detail (0 = full, 1 = medium, 2 = low): colinear moves
+    /// are merged, sub-pixel segments dropped and sparse infill thinned. `source_first_data` /
+    /// `source_last_data` map each segment back to the original segment indices for picking.
+    #[wasm_bindgen]
+    pub fn generate_lod_render_buffers(&self, level: u8, compact: bool, nozzle_size: f32, padding: f32, progress_callback: Option<ProgressCallback>) -> RenderBuffers {
+        let lod = lod::build_lod(&self.segments, &lod::LodSettings::for_level(level));
+        console_log!("LOD {}: {} of {} segments", level.min(lod::LOD_LEVEL_COUNT - 1), lod.len(), self.segments.len());
+        
+        // Pick IDs refer to the first original segment of each merged run
+        let indexed = lod.source_first.iter().copied().zip(lod.segments.iter().copied());
+        let mut buffers = self.build_render_buffers(indexed, lod.len(), compact, nozzle_size, padding, progress_callback);
+        buffers.source_first_data = lod.source_first;
+        buffers.source_last_data = lod.source_last;
+        buffers
+    }
+
+    /// Generate buffers containing only the segments matching a filter (plain JS object matching
+    /// `SegmentFilter`: layer range, file position range, features, tools, travel/extrusion).
+    /// `source_first_data` holds each segment's index in the full buffers; pick IDs use it too.
+    #[wasm_bindgen]
+    pub fn generate_filtered_render_buffers(&self, filter: JsValue, compact: bool, nozzle_size: f32, padding: f32, progress_callback: Option<ProgressCallback>) -> Result<RenderBuffers, JsValue> {
+        let indices = self.get_filtered_segment_indices(filter)?;
+        console_log!("Filter kept {} of {} segments", indices.len(), self.segments.len());
+
+        let indexed = indices.iter().map(|&index| (index, self.segments.get(index as usize)));
+        let mut buffers = self.build_render_buffers(indexed, indices.len(), compact, nozzle_size, padding, progress_callback);
+        buffers.source_last_data = indices.clone();
+        buffers.source_first_data = indices;
+        Ok(buffers)
+    }
+
+    /// Indices of the segments matching a filter (see `generate_filtered_render_buffers`)
+    #[wasm_bindgen]
+    pub fn get_filtered_segment_indices(&self, filter: JsValue) -> Result<Vec<u32>, JsValue> {
+        let filter: SegmentFilter = serde_wasm_bindgen::from_value(filter)
+            .map_err(|e| JsValue::from_str(&format!("Invalid segment filter: {}", e)))?;
+        Ok(filter.select(&self.segments))
+    }
+
+    /// Generate buffers in the compact layout (8 floats per segment instead of a 4x4 matrix);
+    /// orient the boxes in the vertex shader, see `get_compact_vertex_shader`
+    #[wasm_bindgen]
+    pub fn generate_compact_render_buffers(&self, nozzle_size: f32, padding: f32, progress_callback: Option<ProgressCallback>) -> RenderBuffers {
+        self.build_render_buffers(self.indexed_segments(), self.segments.len(), true, nozzle_size, padding, progress_callback)
+    }
+
+    /// Generate quantized buffers (u16 positions, u8 colors, packed flags, u32 IDs) for clients
+    /// with a tight memory budget; positions use the compact start/end layout
+    #[wasm_bindgen]
+    pub fn generate_quantized_render_buffers(&self, nozzle_size: f32, padding: f32) -> QuantizedRenderBuffers {
+        let start_time = js_sys::Date::now();
+        let buffers = QuantizedRenderBuffers::from_segments(&self.segments, nozzle_size, padding);
+        console_log!("Generated {} quantized segments ({} KB) in {:.2}ms",
+                    buffers.segment_count(), buffers.memory_bytes() / 1024, js_sys::Date::now() - start_time);
+        buffers
+    }
+
+    /// Generate continuous tube meshes along extrusion paths (shared vertices, smooth normals),
+    /// one indexed mesh per `layers_per_chunk` layers. Travel moves are not included.
+    #[wasm_bindgen]
+    pub fn generate_tube_meshes(&self, cross_section: TubeCrossSection, joint: TubeJoint, width: f32, sides: u32, layers_per_chunk: u32) -> Vec<TubeMesh> {
+        let start_time = js_sys::Date::now();
+        let settings = tube_mesh::TubeMeshSettings {
+            cross_section,
+            joint,
+            width,
+            sides,
+            layers_per_chunk,
+            ..Default::default()
+        };
+        let meshes = tube_mesh::build_tube_meshes(&self.segments, &settings);
+        console_log!("Generated {} tube mesh chunks in {:.2}ms", meshes.len(), js_sys::Date::now() - start_time);
+        meshes
+    }
+
+    // Segments paired with their index in the segment store
+    fn indexed_segments(&self) -> impl Iterator<Item = (u32, Segment)> + '_ {
+        self.segments.iter().enumerate().map(|(index, segment)| (index as u32, segment))
+    }
+
+    fn build_render_buffers(
+        &self,
+        segments: impl Iterator<Item = (u32, Segment)>,
+        capacity: usize,
+        compact: bool,
+        nozzle_size: f32,
+        padding: f32,
+        progress_callback: Option<ProgressCallback>,
+    ) -> RenderBuffers {
         let start_time = js_sys::Date::now();
-        console_log!("Generating render buffers for {} positions", self.position_tracker.len());
+        console_log!("Generating {} render buffers for {} segments",
+                    if compact { "compact" } else { "matrix" }, capacity);
 
         // Pre-allocate vectors with estimated capacity
-        let capacity = self.position_tracker.len();
-        let mut matrix_data = Vec::with_capacity(capacity * 16); // 4x4 matrix = 16 floats
+        let mut matrix_data = Vec::with_capacity(if compact { 0 } else { capacity * 16 }); // 4x4 matrix = 16 floats
+        let mut segment_data = Vec::with_capacity(if compact { capacity * COMPACT_FLOATS_PER_SEGMENT } else { 0 });
         let mut color_data = Vec::with_capacity(capacity * 4);   // RGBA = 4 floats
         let mut pick_data = Vec::with_capacity(capacity * 3);   // RGB = 3 floats per segment
+        let mut pick_id_data = Vec::with_capacity(capacity);
+        let mut pick_rgba_data = Vec::with_capacity(capacity * 4);
         let mut file_position_data = Vec::with_capacity(capacity);
         let mut file_end_position_data = Vec::with_capacity(capacity);
         let mut tool_data = Vec::with_capacity(capacity);
@@ -429,35 +1203,41 @@ impl GCodeProcessor {
         let mut is_perimeter_data = Vec::with_capacity(capacity);
 
         let mut segment_count = 0u32;
-        let total_positions = self.sorted_positions.len();
+        let total_positions = capacity;
         let mut processed_positions = 0usize;
         let mut last_progress_report = 0f64;
 
-        // Process positions in sorted order for consistency
-        for &position in &self.sorted_positions {
-            if let Some(pos_data) = self.position_tracker.get(&position) {
-                // Include both extruding and travel moves
-                    // Calculate matrix components (equivalent to TypeScript renderLine())
-                    let (matrix, color) = self.calculate_render_matrix(pos_data, nozzle_size, padding);
-                    
-                    // Add matrix data (16 floats for 4x4 matrix in column-major order)
-                    matrix_data.extend_from_slice(&matrix);
-                    
-                    // Add color data (RGBA)
-                    color_data.extend_from_slice(&color);
-                    
-                    // Add other buffer data
-                    let color_id = Self::num_to_color(pos_data.line_number);
-                    pick_data.extend_from_slice(&color_id); // RGB color for picking (matches TypeScript colorId/255)
-                    file_position_data.push(position as f32);
-                    file_end_position_data.push(pos_data.file_end_position as f32);
-                    tool_data.push(pos_data.tool as f32);
-                    feed_rate_data.push(pos_data.feed_rate as f32);
-                    is_perimeter_data.push(if pos_data.is_perimeter { 1.0 } else { 0.0 });
-
-                    segment_count += 1;
+        // Segments are stored in file order (both extruding and travel moves)
+        for (segment_index, segment) in segments {
+            if compact {
+                // Endpoints and size only; orientation is computed on the GPU
+                segment_data.extend_from_slice(&compact_segment(&segment, nozzle_size, padding));
+                color_data.extend_from_slice(&segment.color_rgba());
+            } else {
+                // Calculate matrix components (equivalent to TypeScript renderLine())
+                let (matrix, color) = Self::calculate_render_matrix(&segment, nozzle_size, padding);
+                
+                // Add matrix data (16 floats for 4x4 matrix in column-major order)
+                matrix_data.extend_from_slice(&matrix);
+                
+                // Add color data (RGBA)
+                color_data.extend_from_slice(&color);
             }
             
+            // Add other buffer data
+            let color_id = Self::num_to_color(segment.line_number);
+            pick_data.extend_from_slice(&color_id); // RGB color for picking (matches TypeScript colorId/255)
+            let id = pick_id::pick_id(segment_index);
+            pick_id_data.push(id);
+            pick_rgba_data.extend_from_slice(&pick_id::encode_pick_color(id));
+            file_position_data.push(segment.file_position as f32);
+            file_end_position_data.push(segment.file_end_position as f32);
+            tool_data.push(segment.tool as f32);
+            feed_rate_data.push(segment.feed_rate);
+            is_perimeter_data.push(if segment.is_perimeter() { 1.0 } else { 0.0 });
+
+            segment_count += 1;
+            
             processed_positions += 1;
             
             // Report progress every 5% or every 10000 positions to avoid callback overhead
@@ -484,33 +1264,41 @@ impl GCodeProcessor {
 
         RenderBuffers {
             matrix_data,
+            segment_data,
             color_data,
             pick_data,
+            pick_id_data,
+            pick_rgba_data,
             file_position_data,
             file_end_position_data,
             tool_data,
             feed_rate_data,
             is_perimeter_data,
+            source_first_data: Vec::new(),
+            source_last_data: Vec::new(),
             segment_count,
         }
     }
 
     // Helper function to calculate render matrix (equivalent to Move.renderLine())
-    fn calculate_render_matrix(&self, pos_data: &PositionData, nozzle_size: f32, padding: f32) -> ([f32; 16], [f32; 4]) {
+    fn calculate_render_matrix(segment: &Segment, nozzle_size: f32, padding: f32) -> ([f32; 16], [f32; 4]) {
         // Replicate TypeScript Move.renderLine() logic exactly
+        let [start_x, start_y, start_z] = segment.start.map(|v| v as f64);
+        let [end_x, end_y, end_z] = segment.end.map(|v| v as f64);
+        let color = segment.color_rgba();
         
         // Calculate length with padding (matches TypeScript: const length = this.length + padding * 0.1)
-        let length = pos_data.length as f32 + padding * 0.1;
+        let length = segment.length() + padding * 0.1;
         
         // Calculate midpoint (matches TypeScript: Move.divide(Move.add(this.start, this.end), VECDIV2))
-        let mid_x = (pos_data.start_x + pos_data.x) / 2.0;
-        let mid_y = (pos_data.start_y + pos_data.y) / 2.0; 
-        let mid_z = (pos_data.start_z + pos_data.z) / 2.0;
+        let mid_x = (start_x + end_x) / 2.0;
+        let mid_y = (start_y + end_y) / 2.0; 
+        let mid_z = (start_z + end_z) / 2.0;
         
         // Calculate direction vector (matches TypeScript: Move.subtract(this.end, this.start))
-        let v_x = pos_data.x - pos_data.start_x;
-        let v_y = pos_data.y - pos_data.start_y;
-        let v_z = pos_data.z - pos_data.start_z;
+        let v_x = end_x - start_x;
+        let v_y = end_y - start_y;
+        let v_z = end_z - start_z;
         
         // Calculate magnitude r (matches TypeScript: Math.sqrt(Math.pow(v[0], 2) + Math.pow(v[1], 2) + Math.pow(v[2], 2)))
         let r = (v_x * v_x + v_y * v_y + v_z * v_z).sqrt();
@@ -524,12 +1312,6 @@ impl GCodeProcessor {
                 0.0, 0.0, 1.0, 0.0,
                 mid_x as f32, mid_y as f32, mid_z as f32, 1.0,
             ];
-            let color = [
-                pos_data.color.r as f32,
-                pos_data.color.g as f32,
-                pos_data.color.b as f32,
-                pos_data.color.a as f32,
-            ];
             return (matrix, color);
         }
         
@@ -582,7 +1364,7 @@ impl GCodeProcessor {
         
         // Scale factors (matches TypeScript: new Vector3(length, this.layerHeight, nozzleSize))
         let scale_x = length as f64;
-        let scale_y = pos_data.layer_height;
+        let scale_y = segment.layer_height as f64;
         let scale_z = nozzle_size as f64;
         
         // Compose transformation matrix exactly like Babylon.js Matrix.Compose(scale, rotation, translation)
@@ -612,13 +1394,6 @@ impl GCodeProcessor {
         ];
         
         // Use the actual move color (matches TypeScript: p.Color = this.color)
-        let color = [
-            pos_data.color.r as f32,
-            pos_data.color.g as f32,
-            pos_data.color.b as f32,
-            pos_data.color.a as f32,
-        ];
-        
         (matrix, color)
     }
     
@@ -648,6 +1423,105 @@ pub fn benchmark_parsing(file_content: &str, iterations: usize) -> f64 {
     total_time / iterations as f64
 }
 
+// Parsing throughput in MB/s averaged over `iterations` runs
+#[wasm_bindgen]
+pub fn benchmark_parsing_throughput(file_content: &str, iterations: usize) -> f64 {
+    let average_ms = benchmark_parsing(file_content, iterations.max(1));
+    if average_ms <= 0.0 {
+        return 0.0;
+    }
+    (file_content.len() as f64 / (1024.0 * 1024.0)) / (average_ms / 1000.0)
+}
+
+// Segment storage footprint for a file: [segment count, previous HashMap layout bytes, columnar bytes]
+// Both layouts are built for the file and measured from their allocated capacities
+#[wasm_bindgen]
+pub fn benchmark_segment_memory(file_content: &str) -> Vec<f64> {
+    let mut processor = FileProcessor::new();
+    match processor.process_file_content(file_content, None) {
+        Ok(segments) => vec![
+            segments.len() as f64,
+            segments.legacy_layout_bytes() as f64,
+            segments.memory_bytes() as f64,
+        ],
+        Err(_) => vec![0.0, 0.0, 0.0],
+    }
+}
+
+// Pick ID from an RGBA8 pick readback (0 = background)
+#[wasm_bindgen]
+pub fn decode_pick_id(r: u8, g: u8, b: u8, a: u8) -> u32 {
+    pick_id::decode_pick_id([r, g, b, a])
+}
+
+// Segment index from an RGBA8 pick readback, for `get_segment_position_data`
+#[wasm_bindgen]
+pub fn decode_pick_segment(r: u8, g: u8, b: u8, a: u8) -> Option<u32> {
+    pick_id::segment_from_pick_id(pick_id::decode_pick_id([r, g, b, a]))
+}
+
+// RGBA8 pick color for a segment index
+#[wasm_bindgen]
+pub fn encode_pick_segment(segment_index: u32) -> Vec<u8> {
+    pick_id::encode_pick_id(pick_id::pick_id(segment_index)).to_vec()
+}
+
+// GLSL ES 3.0 vertex shader for buffers from `generate_compact_render_buffers`
+#[wasm_bindgen]
+pub fn get_compact_vertex_shader() -> String {
+    compact_buffers::COMPACT_VERTEX_SHADER.to_string()
+}
+
+// Names accepted by `GCodeProcessor::set_builtin_color_theme`
+#[wasm_bindgen]
+pub fn get_builtin_color_themes() -> Vec<String> {
+    BUILTIN_THEMES.iter().map(|name| name.to_string()).collect()
+}
+
+// Read embedded thumbnails without processing the G-code (for file browsers)
+#[wasm_bindgen]
+pub fn extract_thumbnails(file_content: &str) -> Vec<Thumbnail> {
+    thumbnails::extract_thumbnails(file_content)
+}
+
+// Decode a binary G-code (.bgcode) file to the ASCII text used by `process_bgcode`
+#[wasm_bindgen]
+pub fn decode_bgcode(data: &[u8]) -> Result<String, JsValue> {
+    BgcodeFile::decode(data)
+        .map(|file| file.to_gcode_text())
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+// Decode a raw MeatPack-packed stream (e.g. captured from a host plugin) to G-code text
+#[wasm_bindgen]
+pub fn decode_meatpack(data: &[u8]) -> String {
+    meatpack::decode_meatpack(data)
+}
+
+// True if the bytes enable MeatPack packing near the start of the stream
+#[wasm_bindgen]
+pub fn is_meatpack(data: &[u8]) -> bool {
+    meatpack::is_meatpack(data)
+}
+
+// Unpack raw file bytes (gzip, zip/3MF, bgcode, MeatPack or text) to the G-code text used by `process_input`
+#[wasm_bindgen]
+pub fn unpack_input_text(data: &[u8], plate: Option<u32>) -> Result<String, ProcessingError> {
+    unpack_input(data, plate).map(|unpacked| unpacked.text)
+}
+
+// Detected input container: "Text", "Gzip", "Zip3mf", "Bgcode" or "MeatPack"
+#[wasm_bindgen]
+pub fn sniff_input_format(data: &[u8]) -> String {
+    format!("{:?}", input_format::sniff_format(data))
+}
+
+// True if the bytes start with the binary G-code header
+#[wasm_bindgen]
+pub fn is_bgcode(data: &[u8]) -> bool {
+    bgcode::is_bgcode(data)
+}
+
 // Export version information
 #[wasm_bindgen]
 pub fn get_version() -> String {
@@ -667,8 +1541,23 @@ pub fn test_matrix_calculation() -> String {
         true              // is_perimeter
     );
     
-    let processor = GCodeProcessor::new();
-    let (matrix, _color) = processor.calculate_render_matrix(&test_pos, 0.4, 0.0);
+    let test_segment = Segment {
+        start: [0.0, 0.0, 0.0],
+        end: [10.0, 0.0, 0.0],
+        feed_rate: 1500.0,
+        layer_height: 0.2,
+        width: 0.4,
+        color: segment_store::pack_color(&test_pos.color),
+        flags: segment_store::FLAG_EXTRUDING | segment_store::FLAG_PERIMETER,
+        tool: 0,
+        feature: segment_store::feature_index(&FeatureType::Unknown),
+        layer: 0,
+        line_number: 0,
+        file_position: 0,
+        file_end_position: 0,
+    };
+    
+    let (matrix, _color) = GCodeProcessor::calculate_render_matrix(&test_segment, 0.4, 0.0);
     
     // Format results for inspection - show key elements of transformation matrix
     format!(
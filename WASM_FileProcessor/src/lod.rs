@@ -0,0 +1,239 @@
+// Level-of-detail segment generation
+// Levels match the viewer's LODLevel (0 = HIGH, 1 = MEDIUM, 2 = LOW). Each reduced segment keeps
+// the range of original segment indices it covers so picks can be mapped back.
+
+use crate::segment_store::{Segment, SegmentStore};
+use crate::slicers::FeatureType;
+
+pub const LOD_LEVEL_COUNT: u8 = 3;
+
+// Upper bound on segments merged into one, keeping the colinearity check linear
+const MAX_MERGED_SEGMENTS: usize = 256;
+
+// Endpoints closer than this are treated as connected
+const CONTINUITY_EPSILON: f32 = 1e-4;
+
+/// Reduction settings for one level
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LodSettings {
+    pub merge_tolerance: f32,    // Max distance of merged interior points from the merged line (mm)
+    pub min_segment_length: f32, // Shorter segments (after merging) are dropped (mm)
+    pub infill_stride: u32,      // Keep every Nth sparse infill segment per layer
+    pub include_travel: bool,
+}
+
+impl LodSettings {
+    /// Preset for a level; levels above the last use the last preset
+    pub fn for_level(level: u8) -> Self {
+        match level {
+            0 => LodSettings { merge_tolerance: 0.0, min_segment_length: 0.0, infill_stride: 1, include_travel: true },
+            1 => LodSettings { merge_tolerance: 0.02, min_segment_length: 0.2, infill_stride: 1, include_travel: true },
+            _ => LodSettings { merge_tolerance: 0.08, min_segment_length: 0.8, infill_stride: 3, include_travel: false },
+        }
+    }
+}
+
+/// Reduced segments plus, per segment, the inclusive range of original indices it replaces
+pub struct LodLevel {
+    pub segments: Vec<Segment>,
+    pub source_first: Vec<u32>,
+    pub source_last: Vec<u32>,
+}
+
+impl LodLevel {
+    pub fn len(&self) -> usize {
+        self.segments.len()
+    }
+
+    fn push(&mut self, segment: Segment, first: usize, last: usize) {
+        self.segments.push(segment);
+        self.source_first.push(first as u32);
+        self.source_last.push(last as u32);
+    }
+}
+
+// Run of consecutive original segments being merged
+struct Run {
+    merged: Segment,
+    first: usize,
+    last: usize,
+    points: Vec<[f32; 3]>, // Interior joints, checked against the merged line
+}
+
+/// Build a reduced level from the stored segments
+pub fn build_lod(segments: &SegmentStore, settings: &LodSettings) -> LodLevel {
+    let mut level = LodLevel {
+        segments: Vec::with_capacity(segments.len()),
+        source_first: Vec::with_capacity(segments.len()),
+        source_last: Vec::with_capacity(segments.len()),
+    };
+    let mut run: Option<Run> = None;
+    let mut infill_counter = 0u32;
+    let mut current_layer = 0u32;
+
+    for (index, segment) in segments.iter().enumerate() {
+        if !settings.include_travel && !segment.extruding() {
+            continue;
+        }
+
+        if segment.layer != current_layer {
+            current_layer = segment.layer;
+            infill_counter = 0;
+        }
+        if settings.infill_stride > 1 && segment.feature() == FeatureType::Infill {
+            infill_counter += 1;
+            if infill_counter % settings.infill_stride != 1 {
+                continue;
+            }
+        }
+
+        if let Some(current) = run.as_mut() {
+            if settings.merge_tolerance > 0.0 && can_extend(current, &segment, settings.merge_tolerance) {
+                current.points.push(current.merged.end);
+                current.merged.end = segment.end;
+                current.merged.file_end_position = segment.file_end_position;
+                current.last = index;
+                continue;
+            }
+        }
+
+        if let Some(finished) = run.take() {
+            emit(&mut level, finished, settings);
+        }
+        run = Some(Run { merged: segment, first: index, last: index, points: Vec::new() });
+    }
+
+    if let Some(finished) = run.take() {
+        emit(&mut level, finished, settings);
+    }
+    level
+}
+
+/// Index of the reduced segment covering an original segment index, if it was kept
+pub fn lod_index_of(source_first: &[u32], source_last: &[u32], source_index: u32) -> Option<usize> {
+    let index = source_last.partition_point(|&last| last < source_index);
+    (index < source_last.len() && source_first[index] <= source_index).then_some(index)
+}
+
+fn emit(level: &mut LodLevel, run: Run, settings: &LodSettings) {
+    if run.merged.length() >= settings.min_segment_length {
+        level.push(run.merged, run.first, run.last);
+    }
+}
+
+// True if `next` continues the run along the same line within tolerance
+fn can_extend(run: &Run, next: &Segment, tolerance: f32) -> bool {
+    let merged = &run.merged;
+    if run.points.len() + 1 >= MAX_MERGED_SEGMENTS
+        || next.flags != merged.flags
+        || next.tool != merged.tool
+        || next.feature != merged.feature
+        || next.color != merged.color
+        || next.layer != merged.layer
+        || distance(next.start, merged.end) > CONTINUITY_EPSILON
+    {
+        return false;
+    }
+
+    // Reject reversals: the new end must continue forwards
+    let previous = sub(merged.end, merged.start);
+    let added = sub(next.end, next.start);
+    if dot(previous, added) <= 0.0 {
+        return false;
+    }
+
+    let start = merged.start;
+    let end = next.end;
+    run.points.iter().chain(std::iter::once(&merged.end))
+        .all(|&point| point_line_distance(point, start, end) <= tolerance)
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    let d = sub(a, b);
+    dot(d, d).sqrt()
+}
+
+// Distance from `point` to the segment start..end
+fn point_line_distance(point: [f32; 3], start: [f32; 3], end: [f32; 3]) -> f32 {
+    let line = sub(end, start);
+    let length_sq = dot(line, line);
+    if length_sq <= f32::EPSILON {
+        return distance(point, start);
+    }
+    let t = (dot(sub(point, start), line) / length_sq).clamp(0.0, 1.0);
+    let closest = [start[0] + line[0] * t, start[1] + line[1] * t, start[2] + line[2] * t];
+    distance(point, closest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segment_store::{feature_index, FLAG_EXTRUDING};
+
+    fn segment(start: [f32; 2], end: [f32; 2], feature: FeatureType, file_position: u32) -> Segment {
+        Segment {
+            start: [start[0], 0.2, start[1]],
+            end: [end[0], 0.2, end[1]],
+            feed_rate: 1800.0,
+            layer_height: 0.2,
+            width: 0.4,
+            color: 0xFF00FFFF,
+            flags: FLAG_EXTRUDING,
+            tool: 0,
+            feature: feature_index(&feature),
+            layer: 0,
+            line_number: file_position / 10,
+            file_position,
+            file_end_position: file_position + 9,
+        }
+    }
+
+    #[test]
+    fn test_merges_colinear_and_maps_back() {
+        let mut store = SegmentStore::new();
+        // Four nearly colinear pieces, then a corner
+        store.push(segment([0.0, 0.0], [1.0, 0.0], FeatureType::Perimeter, 0));
+        store.push(segment([1.0, 0.0], [2.0, 0.01], FeatureType::Perimeter, 10));
+        store.push(segment([2.0, 0.01], [3.0, 0.0], FeatureType::Perimeter, 20));
+        store.push(segment([3.0, 0.0], [4.0, 0.0], FeatureType::Perimeter, 30));
+        store.push(segment([4.0, 0.0], [4.0, 4.0], FeatureType::Perimeter, 40));
+
+        let full = build_lod(&store, &LodSettings::for_level(0));
+        assert_eq!(full.len(), 5);
+
+        let medium = build_lod(&store, &LodSettings::for_level(1));
+        assert_eq!(medium.len(), 2);
+        assert_eq!((medium.source_first[0], medium.source_last[0]), (0, 3));
+        assert_eq!(medium.segments[0].end, [4.0, 0.2, 0.0]);
+        assert_eq!(medium.segments[0].file_end_position, 39);
+        assert_eq!(lod_index_of(&medium.source_first, &medium.source_last, 2), Some(0));
+        assert_eq!(lod_index_of(&medium.source_first, &medium.source_last, 4), Some(1));
+    }
+
+    #[test]
+    fn test_drops_short_segments_and_thins_infill() {
+        let mut store = SegmentStore::new();
+        store.push(segment([0.0, 0.0], [0.1, 0.0], FeatureType::Perimeter, 0)); // Sub-pixel at LOW
+        for i in 0..6 {
+            // Zig-zag infill: alternating directions never merge
+            let y = i as f32;
+            let (from, to) = if i % 2 == 0 { (0.0, 10.0) } else { (10.0, 0.0) };
+            store.push(segment([from, y], [to, y], FeatureType::Infill, 10 + i * 10));
+        }
+
+        let low = build_lod(&store, &LodSettings::for_level(2));
+        // Short perimeter dropped, infill keeps every third line
+        assert_eq!(low.len(), 2);
+        assert_eq!(low.source_first, vec![1, 4]);
+        assert_eq!(lod_index_of(&low.source_first, &low.source_last, 0), None);
+        assert_eq!(lod_index_of(&low.source_first, &low.source_last, 2), None);
+    }
+}
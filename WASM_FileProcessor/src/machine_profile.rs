@@ -0,0 +1,272 @@
+// Machine profile: printable volume and out-of-bounds detection
+// Profiles use G-code axes (Z up); stored segments have Y and Z swapped and are converted here.
+// The bed is convex, so a segment is inside when both endpoints are. Excluded zones (clips,
+// probes) only restrict extrusion; travel may pass over them.
+
+use serde::{Deserialize, Serialize};
+use crate::segment_store::{Segment, SegmentStore};
+use crate::slicers::slicer_config::SlicerConfig;
+
+const EPSILON: f64 = 1e-3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum BedShape {
+    Rectangular { width: f64, depth: f64 },
+    Circular { diameter: f64 },
+}
+
+/// Where G-code X0 Y0 lies on the bed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum BedOrigin {
+    #[default]
+    FrontLeft,
+    Center,
+}
+
+/// Rectangle in G-code XY where nothing may be printed
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExcludedZone {
+    pub min: [f64; 2],
+    pub max: [f64; 2],
+    #[serde(default)]
+    pub name: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MachineProfile {
+    pub bed: BedShape,
+    #[serde(default)]
+    pub origin: BedOrigin,
+    #[serde(default)]
+    pub origin_offset: [f64; 2], // Added to the origin's bed extents, for outlines not starting at 0,0
+    pub max_z: f64,
+    #[serde(default)]
+    pub excluded_zones: Vec<ExcludedZone>,
+}
+
+/// Extents of the extrusions of one tool, in G-code axes
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ToolBounds {
+    pub tool: u8,
+    pub min: [f64; 3],
+    pub max: [f64; 3],
+    pub segment_count: u32,
+}
+
+// Segment endpoints in G-code axes
+fn gcode_endpoints(segment: &Segment) -> [[f64; 3]; 2] {
+    [segment.start, segment.end].map(|p| [p[0] as f64, p[2] as f64, p[1] as f64])
+}
+
+impl MachineProfile {
+    /// Profile from the slicer's bed outline and max print height; outlines with more than four
+    /// points and a square extent are taken as circular beds. A bed minimum other than 0,0 (and not
+    /// centered) is kept as the origin offset.
+    pub fn from_slicer_config(config: &SlicerConfig) -> Option<Self> {
+        let max_z = config.max_print_height?;
+        let first = config.bed_shape.first()?;
+        let (min, max) = config.bed_shape.iter().fold((*first, *first), |(min, max), p| {
+            ([min[0].min(p[0]), min[1].min(p[1])], [max[0].max(p[0]), max[1].max(p[1])])
+        });
+        let (width, depth) = (max[0] - min[0], max[1] - min[1]);
+        let centered = (min[0] + max[0]).abs() < EPSILON && (min[1] + max[1]).abs() < EPSILON;
+
+        let bed = if config.bed_shape.len() > 4 && (width - depth).abs() < EPSILON {
+            BedShape::Circular { diameter: width }
+        } else {
+            BedShape::Rectangular { width, depth }
+        };
+        let (origin, origin_offset) = if centered {
+            (BedOrigin::Center, [0.0, 0.0])
+        } else {
+            (BedOrigin::FrontLeft, min)
+        };
+        Some(MachineProfile {
+            bed,
+            origin,
+            origin_offset,
+            max_z,
+            excluded_zones: Vec::new(),
+        })
+    }
+
+    /// Bed extents in G-code XY
+    pub fn bed_bounds(&self) -> ([f64; 2], [f64; 2]) {
+        let (width, depth) = match self.bed {
+            BedShape::Rectangular { width, depth } => (width, depth),
+            BedShape::Circular { diameter } => (diameter, diameter),
+        };
+        let (min, max) = match self.origin {
+            BedOrigin::FrontLeft => ([0.0, 0.0], [width, depth]),
+            BedOrigin::Center => ([-width / 2.0, -depth / 2.0], [width / 2.0, depth / 2.0]),
+        };
+        let [dx, dy] = self.origin_offset;
+        ([min[0] + dx, min[1] + dy], [max[0] + dx, max[1] + dy])
+    }
+
+    /// True if a point (G-code axes) lies within the printable volume, ignoring excluded zones
+    pub fn contains(&self, point: [f64; 3]) -> bool {
+        if point[2] < -EPSILON || point[2] > self.max_z + EPSILON {
+            return false;
+        }
+        let (min, max) = self.bed_bounds();
+        match self.bed {
+            BedShape::Rectangular { .. } => (0..2).all(|axis| point[axis] >= min[axis] - EPSILON && point[axis] <= max[axis] + EPSILON),
+            BedShape::Circular { diameter } => {
+                let center = [(min[0] + max[0]) / 2.0, (min[1] + max[1]) / 2.0];
+                let (dx, dy) = (point[0] - center[0], point[1] - center[1]);
+                (dx * dx + dy * dy).sqrt() <= diameter / 2.0 + EPSILON
+            }
+        }
+    }
+
+    /// True if the segment stays in the printable volume (and out of excluded zones when extruding)
+    pub fn segment_inside(&self, segment: &Segment) -> bool {
+        let [start, end] = gcode_endpoints(segment);
+        self.contains(start) && self.contains(end)
+            && !(segment.extruding() && self.excluded_zones.iter().any(|zone| crosses_zone(start, end, zone)))
+    }
+}
+
+// Liang-Barsky clip of the XY projection against the zone rectangle
+fn crosses_zone(start: [f64; 3], end: [f64; 3], zone: &ExcludedZone) -> bool {
+    let (mut t0, mut t1) = (0.0f64, 1.0f64);
+    for axis in 0..2 {
+        let delta = end[axis] - start[axis];
+        for (p, q) in [(-delta, start[axis] - zone.min[axis]), (delta, zone.max[axis] - start[axis])] {
+            if p == 0.0 {
+                if q < 0.0 {
+                    return false;
+                }
+            } else {
+                let t = q / p;
+                if p < 0.0 { t0 = t0.max(t) } else { t1 = t1.min(t) }
+            }
+        }
+    }
+    t0 <= t1
+}
+
+/// Indices of segments leaving the printable volume
+pub fn out_of_bounds_segments(segments: &SegmentStore, profile: &MachineProfile) -> Vec<u32> {
+    segments.iter().enumerate()
+        .filter(|(_, segment)| !profile.segment_inside(segment))
+        .map(|(index, _)| index as u32)
+        .collect()
+}
+
+/// Bounding box of the extrusions of each tool, ordered by tool number
+pub fn tool_bounds(segments: &SegmentStore) -> Vec<ToolBounds> {
+    let mut bounds: Vec<ToolBounds> = Vec::new();
+    for segment in segments.iter().filter(|segment| segment.extruding()) {
+        let index = match bounds.binary_search_by_key(&segment.tool, |b| b.tool) {
+            Ok(index) => index,
+            Err(index) => {
+                bounds.insert(index, ToolBounds {
+                    tool: segment.tool,
+                    min: [f64::INFINITY; 3],
+                    max: [f64::NEG_INFINITY; 3],
+                    segment_count: 0,
+                });
+                index
+            }
+        };
+        let entry = &mut bounds[index];
+        for point in gcode_endpoints(&segment) {
+            for ((min, max), value) in entry.min.iter_mut().zip(entry.max.iter_mut()).zip(point) {
+                *min = min.min(value);
+                *max = max.max(value);
+            }
+        }
+        entry.segment_count += 1;
+    }
+    bounds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segment_store::FLAG_EXTRUDING;
+
+    // Endpoints given in G-code axes
+    fn segment(start: [f32; 3], end: [f32; 3], extruding: bool, tool: u8) -> Segment {
+        Segment {
+            start: [start[0], start[2], start[1]],
+            end: [end[0], end[2], end[1]],
+            feed_rate: 1800.0,
+            layer_height: 0.2,
+            width: 0.4,
+            color: 0,
+            flags: if extruding { FLAG_EXTRUDING } else { 0 },
+            tool,
+            feature: 0,
+            layer: 0,
+            line_number: 1,
+            file_position: 0,
+            file_end_position: 9,
+        }
+    }
+
+    #[test]
+    fn test_rectangular_bed_with_excluded_zone() {
+        let profile = MachineProfile {
+            bed: BedShape::Rectangular { width: 250.0, depth: 210.0 },
+            origin: BedOrigin::FrontLeft,
+            origin_offset: [0.0, 0.0],
+            max_z: 220.0,
+            excluded_zones: vec![ExcludedZone { min: [100.0, 0.0], max: [150.0, 10.0], name: "Clip".to_string() }],
+        };
+        let mut store = SegmentStore::new();
+        store.push(segment([10.0, 5.0, 0.2], [200.0, 5.0, 0.2], true, 0)); // Crosses the clip
+        store.push(segment([10.0, 5.0, 0.2], [200.0, 5.0, 0.2], false, 255)); // Travel over it is fine
+        store.push(segment([10.0, 20.0, 0.2], [260.0, 20.0, 0.2], true, 1)); // Past the right edge
+        store.push(segment([10.0, 20.0, 0.2], [20.0, 20.0, 0.2], true, 1));
+        store.push(segment([10.0, 20.0, 221.0], [20.0, 20.0, 221.0], false, 255)); // Above max Z
+
+        assert_eq!(out_of_bounds_segments(&store, &profile), vec![0, 2, 4]);
+
+        let tools = tool_bounds(&store);
+        assert_eq!(tools.len(), 2);
+        assert_eq!((tools[1].tool, tools[1].segment_count), (1, 2));
+        assert_eq!(tools[1].min, [10.0, 20.0, 0.2f32 as f64]);
+        assert_eq!(tools[1].max[0], 260.0);
+    }
+
+    #[test]
+    fn test_circular_bed_from_slicer_config() {
+        let config = SlicerConfig {
+            max_print_height: Some(300.0),
+            bed_shape: (0..16)
+                .map(|i| {
+                    let angle = i as f64 * std::f64::consts::PI / 8.0;
+                    [100.0 * angle.cos(), 100.0 * angle.sin()]
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        let profile = MachineProfile::from_slicer_config(&config).unwrap();
+        assert_eq!(profile.bed, BedShape::Circular { diameter: 200.0 });
+        assert_eq!(profile.origin, BedOrigin::Center);
+        assert!(profile.contains([70.0, 70.0, 10.0]));
+        assert!(!profile.contains([80.0, 80.0, 10.0])); // Inside the square, outside the circle
+        assert!(!profile.contains([0.0, 0.0, 301.0]));
+    }
+
+    #[test]
+    fn test_offset_bed_from_slicer_config() {
+        let config = SlicerConfig {
+            max_print_height: Some(210.0),
+            bed_shape: vec![[-2.0, -3.0], [248.0, -3.0], [248.0, 207.0], [-2.0, 207.0]],
+            ..Default::default()
+        };
+
+        let profile = MachineProfile::from_slicer_config(&config).unwrap();
+        assert_eq!(profile.bed, BedShape::Rectangular { width: 250.0, depth: 210.0 });
+        assert_eq!((profile.origin, profile.origin_offset), (BedOrigin::FrontLeft, [-2.0, -3.0]));
+        assert_eq!(profile.bed_bounds(), ([-2.0, -3.0], [248.0, 207.0]));
+        assert!(profile.contains([-1.0, -2.0, 0.2]));
+        assert!(!profile.contains([249.0, 100.0, 0.2]));
+    }
+}
@@ -0,0 +1,155 @@
+// Machine-state snapshots at arbitrary file positions
+// Processing stores a copy of the processor state every CHECKPOINT_INTERVAL lines; the state at a
+// byte is rebuilt by replaying the lines between the nearest earlier checkpoint and that byte.
+
+use serde::{Deserialize, Serialize};
+use crate::GCodeCommands::ProcessLine::process_line;
+use crate::processor_properties::{ProcessorProperties, Units};
+use crate::slicers::FeatureType;
+use crate::utils::lines_with_length;
+
+pub const CHECKPOINT_INTERVAL: u32 = 5000; // Lines between checkpoints
+
+/// Processor state before the line starting at `file_position`
+#[derive(Clone)]
+pub struct StateCheckpoint {
+    pub file_position: u32,
+    pub line_number: u32,
+    pub properties: ProcessorProperties,
+}
+
+/// Modal and machine state at a file position, in G-code axes (Z is the vertical axis)
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MachineState {
+    pub file_position: u32,
+    pub line_number: u32,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub e: f64,
+    pub feed_rate: f64,
+    pub absolute_positioning: bool,
+    pub absolute_extrusion: bool,
+    pub units: Units,
+    pub workplace: String,
+    pub tool: u8,
+    pub hotend_temperature: f64, // Targets, as set by M104/M109 and M140/M190
+    pub bed_temperature: f64,
+    pub fan_speed: f64,
+    pub feature: FeatureType,
+}
+
+impl MachineState {
+    pub fn capture(properties: &ProcessorProperties, file_position: u32, line_number: u32) -> Self {
+        // Stored positions have Y and Z swapped for rendering
+        let position = &properties.current_position;
+        MachineState {
+            file_position,
+            line_number,
+            x: position.x,
+            y: position.z,
+            z: position.y,
+            e: properties.current_e,
+            feed_rate: properties.current_feed_rate,
+            absolute_positioning: properties.absolute_positioning,
+            absolute_extrusion: properties.absolute_extrusion,
+            units: properties.units.clone(),
+            workplace: properties.workplace_offsets
+                .get(properties.current_workplace_idx as usize)
+                .map(|workplace| workplace.name.clone())
+                .unwrap_or_default(),
+            tool: properties.current_tool.tool_number,
+            hotend_temperature: properties.target_hotend_temp,
+            bed_temperature: properties.target_bed_temp,
+            fan_speed: properties.fan_speed,
+            feature: properties.current_feature.clone(),
+        }
+    }
+}
+
+/// Last checkpoint at or before `file_position`
+pub fn nearest_checkpoint(checkpoints: &[StateCheckpoint], file_position: u32) -> Option<&StateCheckpoint> {
+    let index = checkpoints.partition_point(|checkpoint| checkpoint.file_position <= file_position);
+    index.checked_sub(1).map(|index| &checkpoints[index])
+}
+
+/// State before the line containing `file_position`, replayed from the nearest checkpoint.
+/// `on_line` sees every trimmed line before it is parsed, to apply slicer comments the way processing did.
+pub fn replay_to(
+    checkpoints: &[StateCheckpoint],
+    source: &str,
+    file_position: u32,
+    mut on_line: impl FnMut(&mut ProcessorProperties, &str),
+) -> Option<MachineState> {
+    let checkpoint = nearest_checkpoint(checkpoints, file_position)?;
+    let mut properties = checkpoint.properties.clone();
+    let mut position = checkpoint.file_position;
+    let mut line_number = checkpoint.line_number;
+
+    for (line, length) in lines_with_length(source.get(position as usize..)?) {
+        let next = position + length;
+        if next > file_position {
+            break;
+        }
+        on_line(&mut properties, line.trim());
+        // Parse errors leave the state unchanged, as during processing
+        let _ = process_line(&mut properties, line, position, line_number);
+        position = next;
+        line_number += 1;
+    }
+
+    Some(MachineState::capture(&properties, position, line_number))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "G21\nM104 S215\nG92 E0\nM106 S128\nT1\nM83\nG1 X10 Y20 Z0.3 F1200 E0.5\nG1 X12 E0.25\nG91\nM107\n";
+
+    fn start() -> Vec<StateCheckpoint> {
+        vec![StateCheckpoint { file_position: 0, line_number: 1, properties: ProcessorProperties::new() }]
+    }
+
+    fn position_of(line: &str) -> u32 {
+        SOURCE.find(line).unwrap() as u32
+    }
+
+    #[test]
+    fn test_replays_modal_state() {
+        let checkpoints = start();
+        let state = replay_to(&checkpoints, SOURCE, position_of("G91"), |_, _| {}).unwrap();
+        assert_eq!(state.line_number, 9);
+        assert_eq!(state.file_position, position_of("G91"));
+        assert_eq!((state.x, state.y, state.z), (12.0, 20.0, 0.3));
+        assert!((state.e - 0.75).abs() < 1e-9);
+        assert_eq!(state.feed_rate, 1200.0);
+        assert_eq!((state.tool, state.hotend_temperature, state.fan_speed), (1, 215.0, 128.0));
+        assert!(state.absolute_positioning && !state.absolute_extrusion);
+        assert_eq!(state.workplace, "G54");
+
+        // A position inside a line gives the state before that line
+        let inside = replay_to(&checkpoints, SOURCE, position_of("G91") + 1, |_, _| {}).unwrap();
+        assert_eq!(inside, state);
+
+        let end = replay_to(&checkpoints, SOURCE, SOURCE.len() as u32, |_, _| {}).unwrap();
+        assert!(!end.absolute_positioning);
+        assert_eq!(end.fan_speed, 0.0);
+    }
+
+    #[test]
+    fn test_resumes_from_nearest_checkpoint() {
+        let mut properties = ProcessorProperties::new();
+        properties.target_bed_temp = 60.0;
+        let mut checkpoints = start();
+        checkpoints.push(StateCheckpoint { file_position: position_of("T1"), line_number: 5, properties });
+
+        assert_eq!(nearest_checkpoint(&checkpoints, position_of("T1") - 1).unwrap().line_number, 1);
+        let state = replay_to(&checkpoints, SOURCE, position_of("M83"), |_, _| {}).unwrap();
+        assert_eq!((state.bed_temperature, state.tool, state.line_number), (60.0, 1, 6));
+
+        let mut comments = Vec::new();
+        replay_to(&start(), " ;TYPE:Support\nG1 X1\n", 21, |_, line| comments.push(line.to_string())).unwrap();
+        assert_eq!(comments, vec![";TYPE:Support", "G1 X1"]);
+    }
+}
@@ -0,0 +1,285 @@
+// MeatPack stream decoding
+// Packs the 15 most common G-code characters into 4-bit nibbles, two per byte.
+// A nibble of 0b1111 means the character did not fit and follows as a full byte.
+// Control sequences are introduced by two 0xFF signal bytes followed by a command byte.
+
+const SIGNAL_BYTE: u8 = 0xFF;
+const FULL_CHAR_NIBBLE: u8 = 0x0F;
+
+// Command bytes following the double signal byte
+const CMD_ENABLE_PACKING: u8 = 0xFB;
+const CMD_DISABLE_PACKING: u8 = 0xFA;
+const CMD_RESET_ALL: u8 = 0xF9;
+const CMD_ENABLE_NO_SPACES: u8 = 0xF7;
+const CMD_DISABLE_NO_SPACES: u8 = 0xF6;
+
+// Nibble to character lookup; index 11 becomes 'E' in no-spaces mode
+const PACKED_CHARS: [u8; 15] = *b"0123456789. \nGX";
+
+/// Incremental MeatPack decoder; feed bytes in any chunking and collect the text
+#[derive(Default)]
+pub struct MeatPackDecoder {
+    packing: bool,
+    no_spaces: bool,
+    signal_count: u8,
+    command_pending: bool,
+    full_char_queue: u8,
+    buffered_char: Option<u8>, // Second character of a pair whose first character is a full byte
+    in_g_line: bool,
+    output: Vec<u8>,
+}
+
+impl MeatPackDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decode a chunk of packed bytes
+    pub fn feed(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.feed_byte(byte);
+        }
+    }
+
+    fn feed_byte(&mut self, byte: u8) {
+        if byte == SIGNAL_BYTE {
+            if self.signal_count > 0 {
+                self.command_pending = true;
+                self.signal_count = 0;
+            } else {
+                self.signal_count += 1;
+            }
+            return;
+        }
+
+        if self.command_pending {
+            self.handle_command(byte);
+            self.command_pending = false;
+            return;
+        }
+
+        // A single 0xFF was data after all
+        if self.signal_count > 0 {
+            self.signal_count = 0;
+            self.handle_data(SIGNAL_BYTE);
+        }
+        self.handle_data(byte);
+    }
+
+    fn handle_command(&mut self, command: u8) {
+        match command {
+            CMD_ENABLE_PACKING => self.packing = true,
+            CMD_DISABLE_PACKING => self.packing = false,
+            CMD_ENABLE_NO_SPACES => self.no_spaces = true,
+            CMD_DISABLE_NO_SPACES => self.no_spaces = false,
+            CMD_RESET_ALL => {
+                self.packing = false;
+                self.no_spaces = false;
+            }
+            _ => {} // Query/config commands carry no text
+        }
+    }
+
+    fn handle_data(&mut self, byte: u8) {
+        if !self.packing {
+            self.emit(byte);
+            return;
+        }
+
+        if self.full_char_queue > 0 {
+            self.emit(byte);
+            if let Some(buffered) = self.buffered_char.take() {
+                self.emit(buffered);
+            }
+            self.full_char_queue -= 1;
+            return;
+        }
+
+        let low = byte & 0x0F;
+        let high = byte >> 4;
+
+        if low == FULL_CHAR_NIBBLE {
+            self.full_char_queue += 1;
+            if high == FULL_CHAR_NIBBLE {
+                self.full_char_queue += 1;
+            } else {
+                self.buffered_char = Some(self.unpack(high));
+            }
+            return;
+        }
+
+        let first = self.unpack(low);
+        self.emit(first);
+
+        // A newline in the low nibble ends the pair; the high nibble is padding
+        if first != b'\n' {
+            if high == FULL_CHAR_NIBBLE {
+                self.full_char_queue += 1;
+            } else {
+                let second = self.unpack(high);
+                self.emit(second);
+            }
+        }
+    }
+
+    fn unpack(&self, nibble: u8) -> u8 {
+        if self.no_spaces && nibble == 11 {
+            b'E'
+        } else {
+            PACKED_CHARS[nibble as usize]
+        }
+    }
+
+    // Write a decoded character, restoring the parameter spaces dropped in no-spaces mode
+    fn emit(&mut self, c: u8) {
+        let line_start = self.output.last().is_none_or(|&last| last == b'\n');
+
+        if c == b'\n' {
+            self.in_g_line = false;
+        } else if c == b';' {
+            self.in_g_line = false; // Leave comments untouched
+        } else if c == b'G' && line_start {
+            self.in_g_line = true;
+        } else if self.in_g_line && is_g_parameter(c) && self.output.last() != Some(&b' ') {
+            self.output.push(b' ');
+        }
+
+        self.output.push(c);
+    }
+
+    /// Decoded text; invalid UTF-8 is replaced rather than rejected
+    pub fn finish(self) -> String {
+        match String::from_utf8(self.output) {
+            Ok(text) => text,
+            Err(err) => String::from_utf8_lossy(err.as_bytes()).into_owned(),
+        }
+    }
+}
+
+// Parameter letters of G0/G1, G2/G3 and G29
+fn is_g_parameter(c: u8) -> bool {
+    matches!(c, b'X' | b'Y' | b'Z' | b'E' | b'F' | b'I' | b'J' | b'R' | b'P' | b'W' | b'H' | b'C' | b'A')
+}
+
+/// Decode a complete MeatPack stream to text
+pub fn decode_meatpack(data: &[u8]) -> String {
+    let mut decoder = MeatPackDecoder::new();
+    decoder.feed(data);
+    decoder.finish()
+}
+
+/// True if the data enables MeatPack packing near its start (raw captures from hosts)
+pub fn is_meatpack(data: &[u8]) -> bool {
+    let head = &data[..data.len().min(256)];
+    head.windows(3).any(|w| w == [SIGNAL_BYTE, SIGNAL_BYTE, CMD_ENABLE_PACKING])
+}
+
+// Reference encoder used to round-trip test the decoder
+#[cfg(test)]
+pub fn encode_meatpack(text: &str, no_spaces: bool) -> Vec<u8> {
+    let mut output = vec![SIGNAL_BYTE, SIGNAL_BYTE, CMD_ENABLE_PACKING];
+    if no_spaces {
+        output.extend_from_slice(&[SIGNAL_BYTE, SIGNAL_BYTE, CMD_ENABLE_NO_SPACES]);
+    }
+
+    let pack = |c: u8| -> Option<u8> {
+        if no_spaces && c == b'E' {
+            return Some(11);
+        }
+        if no_spaces && c == b' ' {
+            return None;
+        }
+        PACKED_CHARS.iter().position(|&p| p == c).map(|i| i as u8)
+    };
+
+    for line in text.lines() {
+        // No-spaces mode drops spaces outside comments; the decoder restores them on G lines
+        let (code, comment) = line.split_at(line.find(';').unwrap_or(line.len()));
+        let mut chars: Vec<u8> = code.bytes().filter(|&c| !(no_spaces && c == b' ')).collect();
+        chars.extend_from_slice(comment.as_bytes());
+        chars.push(b'\n');
+
+        for pair in chars.chunks(2) {
+            let first = pair[0];
+            let Some(second) = pair.get(1).copied() else {
+                // Lone trailing newline, high nibble is padding
+                output.push(pack(first).unwrap_or(FULL_CHAR_NIBBLE));
+                if pack(first).is_none() {
+                    output.push(first);
+                }
+                break;
+            };
+
+            let low = pack(first);
+            let high = pack(second);
+            output.push(low.unwrap_or(FULL_CHAR_NIBBLE) | (high.unwrap_or(FULL_CHAR_NIBBLE) << 4));
+            if low.is_none() {
+                output.push(first);
+            }
+            if high.is_none() {
+                output.push(second);
+            }
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_packed_pairs() {
+        // Enable packing, then "G1 X10\n": 'G'(13) '1'(1), ' '(11) 'X'(14), '1'(1) '0'(0), '\n'(12) + padding
+        let data = [
+            0xFF, 0xFF, CMD_ENABLE_PACKING,
+            0x1D, 0xEB, 0x01, 0x0C,
+        ];
+        assert_eq!(decode_meatpack(&data), "G1 X10\n");
+    }
+
+    #[test]
+    fn test_decode_full_chars_and_no_spaces() {
+        // "M1\n" with 'M' as a full byte: low nibble 0xF, high nibble '1'
+        let data = [
+            0xFF, 0xFF, CMD_ENABLE_PACKING,
+            0x1F, b'M', 0x0C,
+        ];
+        assert_eq!(decode_meatpack(&data), "M1\n");
+
+        // No-spaces mode: "G1X1E2" gets its spaces restored
+        let data = [
+            0xFF, 0xFF, CMD_ENABLE_PACKING,
+            0xFF, 0xFF, CMD_ENABLE_NO_SPACES,
+            0x1D, 0x1E, 0x2B, 0x0C,
+        ];
+        assert_eq!(decode_meatpack(&data), "G1 X1 E2\n");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let text = "M104 S215 ; set temperature\nG28\nG1 X10.5 Y-20 Z0.3 F1200\nG1 X12.25 Y22 E0.0415\nT1\n";
+        assert_eq!(decode_meatpack(&encode_meatpack(text, false)), text);
+
+        let moves = "G1 X10.5 Y20 E1.25\nG0 Z0.6 F9000\nG92 E0\n";
+        let packed = encode_meatpack(moves, true);
+        assert!(packed.len() < moves.len() + 6);
+        assert_eq!(decode_meatpack(&packed), moves);
+    }
+
+    #[test]
+    fn test_streaming_and_detection() {
+        let text = "G1 X1 Y2\nM106 S255\n";
+        let packed = encode_meatpack(text, false);
+        assert!(is_meatpack(&packed));
+        assert!(!is_meatpack(text.as_bytes()));
+
+        // Chunk boundaries may split signal sequences and full-char pairs
+        let mut decoder = MeatPackDecoder::new();
+        for chunk in packed.chunks(1) {
+            decoder.feed(chunk);
+        }
+        assert_eq!(decoder.finish(), text);
+    }
+}
@@ -0,0 +1,48 @@
+// 32-bit pick IDs keyed on the segment index
+// ID 0 is reserved for "no segment" (a cleared pick target), so a segment's ID is its index + 1.
+// Colors use all four RGBA8 channels, little-endian: R holds the lowest byte, A the highest.
+// Render pick targets with blending disabled: the alpha channel is 0 for every ID below 2^24,
+// so alpha blending would keep the cleared color and those segments would read back as the background.
+
+/// Pick ID for a segment index
+pub fn pick_id(segment_index: u32) -> u32 {
+    segment_index.wrapping_add(1)
+}
+
+/// Segment index for a pick ID, None for the background ID
+pub fn segment_from_pick_id(id: u32) -> Option<u32> {
+    id.checked_sub(1)
+}
+
+pub fn encode_pick_id(id: u32) -> [u8; 4] {
+    id.to_le_bytes()
+}
+
+pub fn decode_pick_id(rgba: [u8; 4]) -> u32 {
+    u32::from_le_bytes(rgba)
+}
+
+/// Normalized RGBA floats for a float color attribute; exact after an RGBA8 readback
+pub fn encode_pick_color(id: u32) -> [f32; 4] {
+    encode_pick_id(id).map(|channel| channel as f32 / 255.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_above_24_bits() {
+        for index in [0u32, 255, 16_777_215, 16_777_216, 123_456_789, u32::MAX - 1] {
+            let id = pick_id(index);
+            assert_eq!(segment_from_pick_id(decode_pick_id(encode_pick_id(id))), Some(index));
+
+            // Float colors survive quantization to 8 bits per channel
+            let readback = encode_pick_color(id).map(|c| (c * 255.0).round() as u8);
+            assert_eq!(decode_pick_id(readback), id);
+        }
+        // Line 16,777,216 no longer aliases onto line 0
+        assert_ne!(encode_pick_id(pick_id(16_777_216)), encode_pick_id(pick_id(0)));
+        assert_eq!(segment_from_pick_id(decode_pick_id([0, 0, 0, 0])), None);
+    }
+}
@@ -0,0 +1,167 @@
+// Time-based toolpath playback
+// Segment durations come from length / feed rate (constant speed, no acceleration) unless a motion
+// planner supplies them. Cumulative end times make each frame a binary search.
+
+use crate::segment_store::SegmentStore;
+
+// Feed rate (mm/min) for moves issued before any F word
+const DEFAULT_FEED_RATE: f32 = 1800.0;
+
+/// Nozzle state at a point in time
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlaybackSample {
+    pub segment_index: usize,
+    pub fraction: f32, // Progress through the segment, 0..=1
+    pub position: [f32; 3],
+    pub time: f64,
+    pub finished: bool,
+}
+
+pub struct Playback {
+    end_times: Vec<f64>, // Cumulative seconds at the end of each segment
+}
+
+impl Playback {
+    /// Durations from each segment's length and feed rate
+    pub fn from_feed_rates(segments: &SegmentStore) -> Self {
+        let mut feed_rate = DEFAULT_FEED_RATE;
+        let durations = segments.iter().map(|segment| {
+            if segment.feed_rate > 0.0 {
+                feed_rate = segment.feed_rate;
+            }
+            segment.length() / (feed_rate / 60.0)
+        });
+        Self::from_durations(durations)
+    }
+
+    /// Durations in seconds from an external planner, one per segment
+    pub fn from_durations(durations: impl IntoIterator<Item = f32>) -> Self {
+        let mut total = 0.0f64;
+        let end_times = durations.into_iter()
+            .map(|duration| {
+                total += duration.max(0.0) as f64;
+                total
+            })
+            .collect();
+        Playback { end_times }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end_times.len()
+    }
+
+    /// Total playback time in seconds
+    pub fn duration(&self) -> f64 {
+        self.end_times.last().copied().unwrap_or(0.0)
+    }
+
+    /// Time at which a segment starts, for seeking
+    pub fn start_time(&self, segment_index: usize) -> f64 {
+        match segment_index {
+            0 => 0.0,
+            index => self.end_times.get(index - 1).copied().unwrap_or_else(|| self.duration()),
+        }
+    }
+
+    /// Interpolated nozzle position at `time` seconds; clamps to the start and end of the path
+    pub fn sample(&self, segments: &SegmentStore, time: f64) -> Option<PlaybackSample> {
+        if self.end_times.is_empty() || self.end_times.len() != segments.len() {
+            return None;
+        }
+        let time = time.clamp(0.0, self.duration());
+
+        // First segment still running at `time`; zero-length moves are passed over
+        let index = self.end_times.partition_point(|&end| end <= time).min(self.end_times.len() - 1);
+        let start = self.start_time(index);
+        let span = self.end_times[index] - start;
+        let fraction = if span > 0.0 { ((time - start) / span).clamp(0.0, 1.0) as f32 } else { 1.0 };
+
+        let segment = segments.get(index);
+        let position = [0, 1, 2].map(|axis| segment.start[axis] + (segment.end[axis] - segment.start[axis]) * fraction);
+        Some(PlaybackSample {
+            segment_index: index,
+            fraction,
+            position,
+            time,
+            finished: time >= self.duration(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::FileProcessor;
+    use crate::segment_store::{Segment, FLAG_EXTRUDING};
+
+    fn segment(start: [f32; 3], end: [f32; 3], feed_rate: f32, file_position: u32) -> Segment {
+        Segment {
+            start,
+            end,
+            feed_rate,
+            layer_height: 0.2,
+            width: 0.4,
+            color: 0,
+            flags: FLAG_EXTRUDING,
+            tool: 0,
+            feature: 0,
+            layer: 0,
+            line_number: file_position / 10,
+            file_position,
+            file_end_position: file_position + 9,
+        }
+    }
+
+    #[test]
+    fn test_interpolates_by_feed_rate() {
+        let mut store = SegmentStore::new();
+        store.push(segment([0.0, 0.2, 0.0], [10.0, 0.2, 0.0], 600.0, 0)); // 10 mm at 10 mm/s: 1 s
+        store.push(segment([10.0, 0.2, 0.0], [10.0, 0.2, 0.0], 600.0, 10)); // Zero length
+        store.push(segment([10.0, 0.2, 0.0], [10.0, 0.2, 60.0], 3600.0, 20)); // 60 mm at 60 mm/s: 1 s
+
+        let playback = Playback::from_feed_rates(&store);
+        assert!((playback.duration() - 2.0).abs() < 1e-6);
+
+        let sample = playback.sample(&store, 0.5).unwrap();
+        assert_eq!(sample.segment_index, 0);
+        assert_eq!(sample.position, [5.0, 0.2, 0.0]);
+
+        // The zero-length move is skipped over
+        let sample = playback.sample(&store, 1.25).unwrap();
+        assert_eq!(sample.segment_index, 2);
+        assert!((sample.position[2] - 15.0).abs() < 1e-4);
+        assert!(!sample.finished);
+
+        let end = playback.sample(&store, 10.0).unwrap();
+        assert_eq!((end.segment_index, end.position, end.finished), (2, [10.0, 0.2, 60.0], true));
+        assert!((playback.start_time(2) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_fast_travel_uses_its_own_feed_rate() {
+        // Header comments keep the file above the processor's minimum progress chunk
+        let gcode = format!("{}G90\nM83\nG0 X50 F9000\nG1 F1200\nG1 X60 E2\n", "; travel feed rate\n".repeat(40));
+        let store = FileProcessor::new().process_file_content(&gcode, None).unwrap();
+        let playback = Playback::from_feed_rates(&store);
+
+        // 50 mm travel at 150 mm/s, then 10 mm at the modal 20 mm/s
+        let travel = store.iter().position(|s| s.end[0] == 50.0).unwrap();
+        assert!((playback.start_time(travel + 1) - playback.start_time(travel) - 1.0 / 3.0).abs() < 1e-4);
+        assert!((playback.duration() - (1.0 / 3.0 + 0.5)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_planner_durations() {
+        let mut store = SegmentStore::new();
+        store.push(segment([0.0, 0.2, 0.0], [1.0, 0.2, 0.0], 0.0, 0));
+        store.push(segment([1.0, 0.2, 0.0], [2.0, 0.2, 0.0], 0.0, 10));
+
+        // Missing feed rate falls back to the default
+        let default = Playback::from_feed_rates(&store);
+        assert!((default.duration() - 2.0 / 30.0).abs() < 1e-6);
+
+        let planned = Playback::from_durations([3.0, 1.0]);
+        assert_eq!(planned.sample(&store, 3.5).unwrap().position, [1.5, 0.2, 0.0]);
+        assert!(Playback::from_durations([1.0]).sample(&store, 0.0).is_none());
+    }
+}
@@ -1,18 +1,36 @@
 use crate::gcode_line::{GCodeLine, GCodeLineBase};
 use crate::processor_properties::ProcessorProperties;
 use crate::GCodeCommands::ProcessLine::process_line;
-use crate::slicers::detect_slicer;
-use crate::{PositionData, ProgressCallback};
+use crate::slicers::detect_slicer_with_profiles;
+use crate::slicers::ProfileSlicer::SlicerProfile;
+use crate::slicers::slicer_base::{FeatureType, SlicerBase};
+use crate::color_theme::{ColorTheme, ALL_FEATURES};
+use crate::slicers::slicer_config::SlicerConfig;
+use crate::slicers::job_metadata::JobMetadata;
+use crate::thumbnails::{Thumbnail, ThumbnailCollector};
+use crate::machine_state::{replay_to, MachineState, StateCheckpoint, CHECKPOINT_INTERVAL};
+use crate::gcode_line::Color4;
+use crate::segment_store::{feature_index, pack_color, Segment, SegmentStore, FLAG_EXTRUDING, FLAG_PERIMETER, FLAG_SUPPORT};
+use crate::gcode_line::MoveData;
+use crate::error::{ErrorKind, ProcessingError, MAX_FILE_SIZE};
+use crate::utils::lines_with_length;
+use crate::ProgressCallback;
 use std::collections::HashMap;
+#[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
 // Console logging for WASM
+#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 extern "C" {
     #[wasm_bindgen(js_namespace = console)]
     fn log(s: &str);
 }
 
+// Native builds (tests and benchmarks) have no console
+#[cfg(not(target_arch = "wasm32"))]
+fn log(_s: &str) {}
+
 macro_rules! console_log {
     ($($t:tt)*) => (log(&format_args!($($t)*).to_string()))
 }
@@ -20,32 +38,147 @@ macro_rules! console_log {
 /// High-performance file processor optimized for WASM
 pub struct FileProcessor {
     properties: ProcessorProperties,
+    slicer_profiles: Vec<SlicerProfile>,
+    color_theme: Option<ColorTheme>,
+    slicer_colors: HashMap<FeatureType, Color4>, // Feature colors of the last detected slicer
+    slicer_config: SlicerConfig,
+    job_metadata: JobMetadata,
+    thumbnails: Vec<Thumbnail>,
+    slicer: Option<Box<dyn SlicerBase>>, // Slicer detected for the last file, for feature replay
+    state_checkpoints: Vec<StateCheckpoint>, // Every CHECKPOINT_INTERVAL lines of the last file
 }
 
 impl FileProcessor {
     pub fn new() -> Self {
         Self {
             properties: ProcessorProperties::new(),
+            slicer_profiles: Vec::new(),
+            color_theme: None,
+            slicer_colors: HashMap::new(),
+            slicer_config: SlicerConfig::default(),
+            job_metadata: JobMetadata::default(),
+            thumbnails: Vec::new(),
+            slicer: None,
+            state_checkpoints: Vec::new(),
         }
     }
     
-    /// Process G-code file content and return parsed lines and position data
-    /// Returns (gcode_lines, position_tracker)
+    /// Register a user-defined slicer profile, replacing any existing profile with the same name
+    pub fn register_slicer_profile(&mut self, profile: SlicerProfile) -> Result<(), String> {
+        profile.validate()?;
+        self.slicer_profiles.retain(|p| p.name != profile.name);
+        self.slicer_profiles.push(profile);
+        Ok(())
+    }
+    
+    /// Remove all user-defined slicer profiles
+    pub fn clear_slicer_profiles(&mut self) {
+        self.slicer_profiles.clear();
+    }
+    
+    /// Names of the registered slicer profiles in detection order
+    pub fn slicer_profile_names(&self) -> Vec<String> {
+        self.slicer_profiles.iter().map(|p| p.name.clone()).collect()
+    }
+    
+    /// Set (or clear with `None`) the color theme used for feature colors
+    pub fn set_color_theme(&mut self, theme: Option<ColorTheme>) {
+        self.color_theme = theme;
+    }
+    
+    /// Currently active color theme, if any
+    pub fn color_theme(&self) -> Option<&ColorTheme> {
+        self.color_theme.as_ref()
+    }
+    
+    /// Color for a feature: theme override first, then the detected slicer's color
+    pub fn feature_color(&self, feature: &FeatureType) -> Color4 {
+        let slicer_color = self.slicer_colors.get(feature).cloned().unwrap_or_else(Color4::white);
+        match &self.color_theme {
+            Some(theme) => theme.resolve(feature, &slicer_color),
+            None => slicer_color,
+        }
+    }
+    
+    /// Color for printed moves in progress updates, None when progress coloring is off
+    pub fn progress_color(&self) -> Option<Color4> {
+        self.properties.progress_animation.then(|| self.properties.progress_color.clone())
+    }
+    
+    /// Set the progress color and whether printed moves are recolored at all
+    pub fn set_progress_color(&mut self, color: Color4, enabled: bool) {
+        self.properties.progress_color = color;
+        self.properties.progress_animation = enabled;
+    }
+    
+    /// Machine state before the line containing `file_position` of the last processed file;
+    /// `file_content` must be the text that was processed
+    pub fn state_at(&self, file_content: &str, file_position: u32) -> Option<MachineState> {
+        replay_to(&self.state_checkpoints, file_content, file_position, |properties, line| {
+            if let Some(slicer) = &self.slicer {
+                self.apply_slicer_comment(properties, slicer.as_ref(), line);
+            }
+        })
+    }
+    
+    /// Embedded slicer configuration read from the last processed file
+    pub fn slicer_config(&self) -> &SlicerConfig {
+        &self.slicer_config
+    }
+    
+    /// Slicer estimates (time, filament, layers, objects) read from the last processed file
+    pub fn job_metadata(&self) -> &JobMetadata {
+        &self.job_metadata
+    }
+    
+    /// Thumbnails embedded in the last processed file
+    pub fn thumbnails(&self) -> &[Thumbnail] {
+        &self.thumbnails
+    }
+    
+    /// Replace the thumbnails (binary G-code stores them outside the text)
+    pub fn set_thumbnails(&mut self, thumbnails: Vec<Thumbnail>) {
+        self.thumbnails = thumbnails;
+    }
+    
+    // Read the embedded config block and push tool settings into the processor state
+    fn load_slicer_config(&mut self, file_content: &str) {
+        self.slicer_config = SlicerConfig::parse(file_content);
+        self.properties.apply_slicer_config(&self.slicer_config);
+    }
+    
+    /// Remember the detected slicer's palette so themes can be swapped without reparsing
+    fn capture_slicer_colors(&mut self, slicer: &dyn SlicerBase) {
+        self.slicer_colors = ALL_FEATURES.iter()
+            .map(|f| (f.clone(), slicer.get_feature_color(f)))
+            .collect();
+    }
+    
+    /// Process G-code file content and return its segments in file order
+    /// Lines are parsed as spans into `file_content` and dropped once their state is applied;
+    /// use `parse_lines` to materialize line objects when they are needed
     pub fn process_file_content(
         &mut self,
         file_content: &str,
         progress_callback: Option<ProgressCallback>,
-    ) -> Result<(Vec<GCodeLine>, HashMap<u32, PositionData>), String> {
+    ) -> Result<SegmentStore, ProcessingError> {
+        
+        Self::validate_file_content(file_content)?;
         
         // Reset processor state for new file
         self.properties.reset();
+        self.state_checkpoints.clear();
         
         // Detect slicer type and initialize colors
-        let slicer = detect_slicer(file_content);
+        let slicer = detect_slicer_with_profiles(file_content, &self.slicer_profiles);
         self.properties.slicer_name = slicer.get_name().to_string();
+        self.capture_slicer_colors(slicer.as_ref());
+        self.load_slicer_config(file_content);
+        self.job_metadata = slicer.get_job_metadata(file_content);
         
         // Initialize default feature color from slicer
-        self.properties.current_feature_color = slicer.get_feature_color(&crate::slicers::slicer_base::FeatureType::Perimeter);
+        self.properties.current_feature = FeatureType::Perimeter;
+        self.properties.current_feature_color = self.feature_color(&FeatureType::Perimeter);
         
         // Estimate processing parameters
         let file_length = file_content.len();
@@ -55,28 +188,35 @@ impl FileProcessor {
         console_log!("Processing {} bytes, estimated {} lines in chunks of {}", 
                     file_length, estimated_lines, chunk_size);
         
-        // Pre-allocate result vectors with estimated capacity
-        let mut gcode_lines = Vec::with_capacity(estimated_lines + estimated_lines / 5); // +20% buffer
-        let mut position_tracker = HashMap::with_capacity(estimated_lines * 7 / 10); // ~70% moves
+        // Pre-allocate the segment columns with estimated capacity
+        let mut comment_count = 0usize;
+        let mut segments = SegmentStore::with_capacity(estimated_lines * 7 / 10); // ~70% moves
         
         // Stream through file line by line for optimal memory usage
         let mut file_position = 0u32;
         let mut line_number = 1u32;
         let mut lines_processed = 0usize;
         let mut last_progress_report = 0f64;
+        let mut thumbnails = ThumbnailCollector::new();
         
         // Process lines in chunks to avoid blocking
-        for line in file_content.lines() {
+        for (line, length) in lines_with_length(file_content) {
             // Update position tracking
             self.properties.file_position = file_position;
             self.properties.line_number = line_number;
+            self.capture_checkpoint(file_position, line_number);
             
-            // Process slicer comments for feature detection (before G-code processing)
-            if line.trim().starts_with(";TYPE:") {
-                // Pass trimmed comment to slicer to ensure consistent matching
-                self.process_feature_comment(&slicer, line.trim());
+            // Embedded thumbnails are collected here and skip G-code parsing entirely
+            if thumbnails.feed_line(line) {
+                file_position += length;
+                line_number += 1;
+                lines_processed += 1;
+                continue;
             }
             
+            // Process slicer comments for feature detection (before G-code processing)
+            self.process_slicer_comment(slicer.as_ref(), line);
+            
             // Process the line2
             match process_line(&mut self.properties, line, file_position, line_number) {
                 Ok(gcode_line) => {
@@ -86,22 +226,7 @@ impl FileProcessor {
                            move_data.end.y.is_finite() && move_data.end.z.is_finite() &&
                            move_data.start.x.is_finite() && move_data.start.y.is_finite() && move_data.start.z.is_finite() {
                             
-                            let pos_data = PositionData::new_with_color(
-                                move_data.start.x, move_data.start.y, move_data.start.z,
-                                move_data.end.x, move_data.end.y, move_data.end.z,
-                                move_data.feed_rate,
-                                move_data.extruding,
-                                move_data.layer_height,
-                                move_data.is_perimeter,
-                                move_data.color.clone(),
-                                line_number,
-                                file_position,
-                                (file_position + line.len() as u32),
-                                move_data.tool as u32,
-                                move_data.is_support,
-                            );
-                            
-                            position_tracker.insert(file_position, pos_data);
+                            segments.push(Self::move_segment(move_data, file_position + line.len() as u32));
                         }
                     } else if let Some(arc) = gcode_line.as_arc() {
                         // Tessellate arcs into line segments for rendering when extruding
@@ -127,7 +252,7 @@ impl FileProcessor {
                                 crate::processor_properties::ArcPlane::YZ => crate::utils::ArcPlane::YZ,
                             };
 
-                            if let Ok(arc_result) = crate::utils::tessellate_arc(
+                            match crate::utils::tessellate_arc(
                                 arc.start.clone(),
                                 arc.end.clone(),
                                 i_off,
@@ -141,45 +266,52 @@ impl FileProcessor {
                                 relative_move,
                                 workplace,
                             ) {
-                                // Build segments between points
-                                let mut seg_start = arc.start.clone();
-                                let mut seg_index = 0u32;
-                                for p in arc_result.intermediate_points {
-                                    let pos_key = file_position + seg_index; // keep ordering within line
-                                    let pd = PositionData::new_with_color(
-                                        seg_start.x, seg_start.y, seg_start.z,
-                                        p.x, p.y, p.z,
-                                        arc.feed_rate,
-                                        true,
-                                        0.2,
-                                        self.properties.current_is_perimeter,
-                                        // color from slicer feature
-                                        self.properties.current_feature_color.clone(),
-                                        line_number,
-                                        file_position,
-                                        (file_position + line.len() as u32),
-                                        self.properties.current_tool.tool_number as u32,
-                                        self.properties.current_is_support,
-                                    );
-                                    position_tracker.insert(pos_key, pd);
-                                    seg_start = p;
-                                    seg_index += 1;
+                                Ok(arc_result) => {
+                                    // Build segments between points
+                                    // Sub-segments share the arc's file position and stay in order
+                                    let mut flags = FLAG_EXTRUDING;
+                                    if self.properties.current_is_perimeter { flags |= FLAG_PERIMETER; }
+                                    if self.properties.current_is_support { flags |= FLAG_SUPPORT; }
+                                    let mut seg_start = arc.start.clone();
+                                    for p in arc_result.intermediate_points {
+                                        segments.push(Segment {
+                                            start: [seg_start.x as f32, seg_start.y as f32, seg_start.z as f32],
+                                            end: [p.x as f32, p.y as f32, p.z as f32],
+                                            feed_rate: arc.feed_rate as f32,
+                                            layer_height: self.properties.layer_height as f32,
+                                            width: self.properties.current_tool.diameter as f32,
+                                            // color from slicer feature
+                                            color: pack_color(&self.properties.current_feature_color),
+                                            flags,
+                                            tool: self.properties.current_tool.tool_number,
+                                            feature: feature_index(&self.properties.current_feature),
+                                            layer: 0,
+                                            line_number,
+                                            file_position,
+                                            file_end_position: file_position + line.len() as u32,
+                                        });
+                                        seg_start = p;
+                                    }
+                                }
+                                Err(error) => {
+                                    // Arcs that cannot be tessellated are not drawn; the position still moved
+                                    console_log!("Warning: {}", error.at(file_position, line_number));
                                 }
                             }
                         }
+                    } else if matches!(gcode_line, GCodeLine::Comment(_)) {
+                        comment_count += 1;
                     }
-                    
-                    gcode_lines.push(gcode_line);
                 }
                 Err(error) => {
-                    console_log!("Warning: Failed to parse line {}: {} ({})", line_number, error, line);
-                    // Create a comment for unparseable lines
-                    gcode_lines.push(GCodeLine::new_comment(file_position, line_number, line.to_string()));
+                    console_log!("Warning: {}", error);
+                    // Unparseable lines count as comments
+                    comment_count += 1;
                 }
             }
             
-            // Update position for next line (account for stripped newline)
-            file_position += line.len() as u32 + 1;
+            // Update position for next line (account for the stripped terminator)
+            file_position += length;
             line_number += 1;
             lines_processed += 1;
             
@@ -204,13 +336,51 @@ impl FileProcessor {
         
         // Update final statistics
         self.properties.line_count = line_number - 1;
+        self.thumbnails = thumbnails.finish();
         
         console_log!("Processing complete: {} lines, {} moves, {} comments", 
-                    gcode_lines.len(), 
-                    position_tracker.len(),
-                    gcode_lines.iter().filter(|l| matches!(l, GCodeLine::Comment(_))).count());
+                    self.properties.line_count, 
+                    segments.len(),
+                    comment_count);
+        
+        self.slicer = Some(slicer);
         
-        Ok((gcode_lines, position_tracker))
+        Ok(segments)
+    }
+    
+    // Snapshot the state before a line for `state_at`
+    fn capture_checkpoint(&mut self, file_position: u32, line_number: u32) {
+        if line_number % CHECKPOINT_INTERVAL == 1 {
+            self.state_checkpoints.push(StateCheckpoint {
+                file_position,
+                line_number,
+                properties: self.properties.clone(),
+            });
+        }
+    }
+    
+    // Columnar segment for a parsed move
+    fn move_segment(move_data: &MoveData, file_end_position: u32) -> Segment {
+        let mut flags = 0u8;
+        if move_data.extruding { flags |= FLAG_EXTRUDING; }
+        if move_data.is_perimeter { flags |= FLAG_PERIMETER; }
+        if move_data.is_support { flags |= FLAG_SUPPORT; }
+        
+        Segment {
+            start: [move_data.start.x as f32, move_data.start.y as f32, move_data.start.z as f32],
+            end: [move_data.end.x as f32, move_data.end.y as f32, move_data.end.z as f32],
+            feed_rate: move_data.feed_rate as f32,
+            layer_height: move_data.layer_height as f32,
+            width: move_data.width as f32,
+            color: pack_color(&move_data.color),
+            flags,
+            tool: move_data.tool,
+            feature: feature_index(&move_data.feature),
+            layer: 0,
+            line_number: move_data.line_number,
+            file_position: move_data.file_position,
+            file_end_position,
+        }
     }
     
     /// Get processing statistics
@@ -234,29 +404,42 @@ impl FileProcessor {
         file_content: &str,
         chunk_size: usize,
         progress_callback: Option<ProgressCallback>,
-    ) -> Result<(Vec<GCodeLine>, HashMap<u32, PositionData>), String> {
+    ) -> Result<SegmentStore, ProcessingError> {
         
+        Self::validate_file_content(file_content)?;
         self.properties.reset();
-        let slicer = detect_slicer(file_content);
+        self.state_checkpoints.clear();
+        let slicer = detect_slicer_with_profiles(file_content, &self.slicer_profiles);
         self.properties.slicer_name = slicer.get_name().to_string();
+        self.capture_slicer_colors(slicer.as_ref());
+        self.load_slicer_config(file_content);
+        self.job_metadata = slicer.get_job_metadata(file_content);
         
         let total_length = file_content.len();
-        let mut gcode_lines = Vec::new();
-        let mut position_tracker = HashMap::new();
+        let mut segments = SegmentStore::new();
         
         let mut file_position = 0u32;
         let mut line_number = 1u32;
         let mut processed_bytes = 0usize;
+        let mut thumbnails = ThumbnailCollector::new();
         
         // Process in streaming chunks
-        for line_chunk in file_content.lines().collect::<Vec<_>>().chunks(chunk_size) {
+        let chunk_size = chunk_size.max(1);
+        let mut lines = lines_with_length(file_content).peekable();
+        while lines.peek().is_some() {
             
-            for line in line_chunk {
+            for (line, length) in lines.by_ref().take(chunk_size) {
                 self.properties.file_position = file_position;
                 self.properties.line_number = line_number;
-                if line.trim().starts_with(";TYPE:") {
-                    self.process_feature_comment(&slicer, line.trim());
+                self.capture_checkpoint(file_position, line_number);
+                
+                if thumbnails.feed_line(line) {
+                    file_position += length;
+                    line_number += 1;
+                    processed_bytes += length as usize;
+                    continue;
                 }
+                self.process_slicer_comment(slicer.as_ref(), line);
                 
                 match process_line(&mut self.properties, line, file_position, line_number) {
                     Ok(gcode_line) => {
@@ -266,36 +449,18 @@ impl FileProcessor {
                                move_data.end.y.is_finite() && move_data.end.z.is_finite() &&
                                move_data.start.x.is_finite() && move_data.start.y.is_finite() && move_data.start.z.is_finite() {
                                 
-                                let pos_data = PositionData::new_with_color(
-                                    move_data.start.x, move_data.start.y, move_data.start.z,
-                                    move_data.end.x, move_data.end.y, move_data.end.z,
-                                    move_data.feed_rate,
-                                    move_data.extruding,
-                                    move_data.layer_height,
-                                    move_data.is_perimeter,
-                                    move_data.color.clone(),
-                                    line_number,
-                                    file_position,
-                                    (file_position + line.len() as u32),
-                                    move_data.tool as u32,
-                                    move_data.is_support,
-                                );
-                                
-                                position_tracker.insert(file_position, pos_data);
+                                segments.push(Self::move_segment(move_data, file_position + line.len() as u32));
                             }
                         }
-                        
-                        gcode_lines.push(gcode_line);
                     }
                     Err(_) => {
-                        // Create comment for unparseable lines
-                        gcode_lines.push(GCodeLine::new_comment(file_position, line_number, line.to_string()));
+                        // Unparseable lines are skipped like comments
                     }
                 }
                 
-                file_position += line.len() as u32 + 1;
+                file_position += length;
                 line_number += 1;
-                processed_bytes += line.len() + 1; // +1 for newline
+                processed_bytes += length as usize;
             }
             
             // Report progress after each chunk
@@ -305,17 +470,39 @@ impl FileProcessor {
             }
         }
         
-        Ok((gcode_lines, position_tracker))
+        self.properties.line_count = line_number - 1;
+        self.thumbnails = thumbnails.finish();
+        self.slicer = Some(slicer);
+        
+        Ok(segments)
+    }
+    
+    /// Materialize line objects on demand; text is recovered with `original_line(file_content)`
+    pub fn parse_lines(file_content: &str) -> Vec<GCodeLine> {
+        let mut properties = ProcessorProperties::new();
+        let mut file_position = 0u32;
+        
+        lines_with_length(file_content)
+            .enumerate()
+            .map(|(index, (line, length))| {
+                let line_number = index as u32 + 1;
+                let parsed = process_line(&mut properties, line, file_position, line_number)
+                    .unwrap_or_else(|_| GCodeLine::new_comment(file_position, line_number, line));
+                file_position += length;
+                parsed
+            })
+            .collect()
     }
     
     /// Validate file content before processing
-    pub fn validate_file_content(file_content: &str) -> Result<(), String> {
+    pub fn validate_file_content(file_content: &str) -> Result<(), ProcessingError> {
         if file_content.is_empty() {
-            return Err("File is empty".to_string());
+            return Err(ProcessingError::new(ErrorKind::EmptyFile, "File is empty"));
         }
         
-        if file_content.len() > 500_000_000 { // 500MB limit
-            return Err("File too large (>500MB)".to_string());
+        if file_content.len() > MAX_FILE_SIZE {
+            return Err(ProcessingError::new(ErrorKind::FileTooLarge, "File too large (>500MB)")
+                .with_context(&format!("{} bytes", file_content.len())));
         }
         
         // Check if it looks like G-code
@@ -325,27 +512,67 @@ impl FileProcessor {
         
         for line in &lines {
             let trimmed = line.trim();
-            if trimmed.starts_with(';') || trimmed.is_empty() {
+            // `(...)` comments and `%` tape markers come from CNC-style files
+            if trimmed.is_empty() || trimmed.starts_with([';', '(', '%']) {
                 comment_lines += 1;
-            } else if trimmed.starts_with('G') || trimmed.starts_with('M') || trimmed.starts_with('T') {
+            } else if Self::is_command_line(trimmed) {
                 gcode_lines += 1;
             }
         }
         
         if gcode_lines == 0 && comment_lines < lines.len() / 2 {
-            return Err("File does not appear to contain valid G-code".to_string());
+            let first_line = lines.iter().find(|line| !line.trim().is_empty()).copied().unwrap_or_default();
+            return Err(ProcessingError::new(ErrorKind::NotGCode, "File does not appear to contain valid G-code")
+                .with_context(first_line));
         }
         
         Ok(())
     }
     
+    // G, M or T word with a number, in either case, after an optional N line number
+    fn is_command_line(line: &str) -> bool {
+        let line = match line.strip_prefix(['N', 'n']) {
+            Some(rest) => rest.trim_start_matches(|c: char| c.is_ascii_digit()).trim_start(),
+            None => line,
+        };
+        let mut chars = line.chars();
+        matches!(chars.next(), Some('G' | 'g' | 'M' | 'm' | 'T' | 't')) && chars.next().is_some_and(|c| c.is_ascii_digit())
+    }
+    
+    // Feature and layer marker comments, recognized by the active slicer's own prefixes
+    fn process_slicer_comment(&mut self, slicer: &dyn SlicerBase, line: &str) {
+        // Pass trimmed comment to slicer to ensure consistent matching
+        let line = line.trim();
+        if slicer.is_feature_comment(line) {
+            self.process_feature_comment(slicer, line);
+        } else if let Some(z) = slicer.layer_z_from_comment(line) {
+            self.properties.set_layer_marker_z(z);
+        }
+    }
+    
     /// Process slicer feature comments to update coloring state
-    fn process_feature_comment(&mut self, slicer: &Box<dyn crate::slicers::slicer_base::SlicerBase>, line: &str) {
+    fn process_feature_comment(&mut self, slicer: &dyn SlicerBase, line: &str) {
         if let Some(feature) = slicer.parse_feature_from_comment(line) {
-            // Update current feature color based on detected feature
-            self.properties.current_feature_color = slicer.get_feature_color(&feature);
-            self.properties.current_is_perimeter = slicer.is_perimeter_comment(line);
-            self.properties.current_is_support = slicer.is_support_comment(line);
+            // Update current feature color based on detected feature (theme overrides apply)
+            let color = self.feature_color(&feature);
+            self.properties.set_feature(feature, color, slicer.is_perimeter_comment(line), slicer.is_support_comment(line));
+        }
+    }
+    
+    // Same as `process_slicer_comment`, on replayed state
+    fn apply_slicer_comment(&self, properties: &mut ProcessorProperties, slicer: &dyn SlicerBase, line: &str) {
+        if slicer.is_feature_comment(line) {
+            self.apply_feature_comment(properties, slicer, line);
+        } else if let Some(z) = slicer.layer_z_from_comment(line) {
+            properties.set_layer_marker_z(z);
+        }
+    }
+    
+    // Same as `process_feature_comment`, on replayed state
+    fn apply_feature_comment(&self, properties: &mut ProcessorProperties, slicer: &dyn SlicerBase, line: &str) {
+        if let Some(feature) = slicer.parse_feature_from_comment(line) {
+            let color = self.feature_color(&feature);
+            properties.set_feature(feature, color, slicer.is_perimeter_comment(line), slicer.is_support_comment(line));
         }
     }
 }
@@ -375,6 +602,109 @@ impl Default for FileProcessor {
 mod tests {
     use super::*;
     
+    // Sliced-looking print: per layer a perimeter square, a zig-zag infill, travels and an arc
+    fn sample_print(layers: u32) -> String {
+        let mut out = String::from("; generated by PrusaSlicer 2.7.0\nG28\nG90\nM83\nM104 S215\n");
+        for layer in 0..layers {
+            let z = 0.2 * (layer + 1) as f64;
+            out.push_str(&format!(";LAYER_CHANGE\n;Z:{:.1}\nG1 Z{:.1} F720\n;TYPE:Perimeter\nG1 X10 Y10 F9000\n", z, z));
+            for (x, y) in [(60, 10), (60, 60), (10, 60), (10, 10)] {
+                out.push_str(&format!("G1 X{} Y{} E1.66 F1800\n", x, y));
+            }
+            out.push_str(";TYPE:Internal infill\nG1 X12 Y12 F9000\n");
+            for row in 0..24 {
+                let y = 12.0 + row as f64 * 2.0;
+                let (from, to) = if row % 2 == 0 { (12, 58) } else { (58, 12) };
+                out.push_str(&format!("G1 X{} Y{:.1} E0.07\nG1 X{} Y{:.1} E1.53\n", from, y, to, y));
+            }
+            out.push_str("G2 X20 Y30 I5 J0 E0.5\n");
+        }
+        out
+    }
+    
+    #[test]
+    fn test_profile_features_during_processing() {
+        use crate::slicers::ProfileSlicer::ProfileFeature;
+        use crate::slicers::FeatureType;
+        
+        let mut processor = FileProcessor::new();
+        processor.register_slicer_profile(SlicerProfile {
+            name: "KiriMoto".to_string(),
+            signatures: vec!["; Generated by Kiri:Moto".to_string()],
+            comment_prefix: "; feature ".to_string(),
+            features: vec![
+                ProfileFeature { name: "shells".to_string(), feature: FeatureType::ExternalPerimeter, color: None, perimeter: true, support: false },
+                ProfileFeature { name: "support".to_string(), feature: FeatureType::Support, color: None, perimeter: false, support: true },
+            ],
+            default_color: None,
+            layer_number_prefix: Some(";; --- layer ".to_string()),
+            layer_z_prefix: None,
+            layer_height: 0.25,
+            version_prefix: None,
+        }).unwrap();
+        
+        let mut gcode = String::from("; Generated by Kiri:Moto 4.1.2\n");
+        gcode.push_str(&"; settings\n".repeat(40));
+        gcode.push_str("G28\nM83\n;; --- layer 1\nG1 Z0.25 F600\n; feature shells\nG1 X10 Y0 E0.5 F1200\n; feature support\nG1 X10 Y10 E0.5\n");
+        let segments = processor.process_file_content(&gcode, None).unwrap();
+        assert_eq!(processor.get_statistics().slicer_name, "KiriMoto");
+        
+        let extrusions: Vec<Segment> = segments.iter().filter(|s| s.extruding()).collect();
+        assert_eq!(extrusions.len(), 2);
+        assert_eq!(extrusions[0].feature(), FeatureType::ExternalPerimeter);
+        assert!(extrusions[0].is_perimeter());
+        assert_eq!(extrusions[1].feature(), FeatureType::Support);
+        assert!(extrusions[1].is_support());
+        // The layer marker sets the layer height
+        assert!(extrusions.iter().all(|s| (s.layer_height - 0.25).abs() < 1e-6));
+        
+        // Replayed state applies the same comments
+        let support_line = gcode.find("G1 X10 Y10").unwrap() as u32;
+        assert_eq!(processor.state_at(&gcode, support_line).unwrap().feature, FeatureType::Support);
+    }
+    
+    #[test]
+    fn test_segment_memory_on_print() {
+        let mut processor = FileProcessor::new();
+        let segments = processor.process_file_content(&sample_print(50), None).unwrap();
+        assert!(segments.len() > 2500);
+        // 1.66 mm of 1.75 mm filament per 50 mm perimeter at 0.2 mm layers is a 0.4 mm line
+        let perimeter = segments.iter().find(|s| s.extruding() && s.length() > 49.0).unwrap();
+        assert!((perimeter.width - 0.4).abs() < 0.01);
+        // Both layouts measured from their allocations for the same file
+        assert!(segments.memory_bytes() * 2 <= segments.legacy_layout_bytes());
+    }
+    
+    #[test]
+    fn test_crlf_positions() {
+        let source = "; Layer 1\r\nG1 X10 Y5 E0.4\r\nG0 X10 F-100\r\n";
+        let lines = FileProcessor::parse_lines(source);
+        assert_eq!(lines[1].original_line(source), "G1 X10 Y5 E0.4");
+        assert_eq!(lines[2].original_line(source), "G0 X10 F-100");
+        
+        let print = sample_print(3).replace('\n', "\r\n");
+        let segments = FileProcessor::new().process_file_content(&print, None).unwrap();
+        for segment in segments.iter() {
+            let text = &print[segment.file_position as usize..segment.file_end_position as usize];
+            assert!(text.starts_with('G') && !text.contains('\r'), "{:?}", text);
+        }
+    }
+    
+    // Throughput of the full parse; run with `cargo test --release -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_process_throughput() {
+        let print = sample_print(4000);
+        let iterations = 5;
+        let start = std::time::Instant::now();
+        for _ in 0..iterations {
+            let segments = FileProcessor::new().process_file_content(&print, None).unwrap();
+            assert!(!segments.is_empty());
+        }
+        let seconds = start.elapsed().as_secs_f64() / iterations as f64;
+        println!("{:.1} MB in {:.1} ms: {:.1} MB/s", print.len() as f64 / 1e6, seconds * 1000.0, print.len() as f64 / 1e6 / seconds);
+    }
+    
     #[test]
     fn test_validate_file_content() {
         // Valid G-code
@@ -389,6 +719,20 @@ mod tests {
         assert!(FileProcessor::validate_file_content(not_gcode).is_err());
     }
     
+    #[test]
+    fn test_validate_numbered_and_lowercase_gcode() {
+        // Line numbers, lowercase words and CNC-style comments
+        let numbered = "N10 G21\nN20 G90\nN30 G1 X10 Y10 F500\nN40 M2\n";
+        assert!(FileProcessor::validate_file_content(numbered).is_ok());
+        let lowercase = "(cnc program)\n%\ng1 x10 y10 f500\ng1 x20\nm2\n%\n";
+        assert!(FileProcessor::validate_file_content(lowercase).is_ok());
+        
+        // Words need a number: prose starting with G, M or T is not G-code
+        let prose = "Material list\nGlue\nTape\n";
+        let error = FileProcessor::validate_file_content(prose).unwrap_err();
+        assert_eq!((error.kind(), error.context()), (ErrorKind::NotGCode, "Material list".to_string()));
+    }
+    
     #[test]
     fn test_process_simple_file() {
         let mut processor = FileProcessor::new();
@@ -404,8 +748,34 @@ mod tests {
         let result = processor.process_file_content(simple_gcode, None);
         assert!(result.is_ok());
         
-        let (gcode_lines, position_tracker) = result.unwrap();
-        assert!(gcode_lines.len() >= 4); // At least the lines we specified
-        assert!(!position_tracker.is_empty()); // Should have at least one extruding move
+        let segments = result.unwrap();
+        assert!(processor.get_statistics().line_count >= 4); // At least the lines we specified
+        assert!(!segments.is_empty()); // Should have at least one extruding move
+    }
+    
+    #[test]
+    fn test_parse_lines_spans() {
+        let source = "; Layer 1\nG1 X10 Y5 E0.4\nm104 s200\nG92 E0\nT1\n";
+        let lines = FileProcessor::parse_lines(source);
+        assert_eq!(lines.len(), 5);
+        
+        // Lines keep only their byte range; text is sliced back out of the source
+        assert_eq!(lines[1].original_line(source), "G1 X10 Y5 E0.4");
+        assert_eq!(lines[2].file_position(), 25);
+        assert_eq!(lines[4].original_line(source), "T1");
+        if let GCodeLine::Comment(comment) = &lines[0] {
+            assert_eq!(comment.comment_text(source), "Layer 1");
+        } else {
+            panic!("expected a comment");
+        }
+        
+        // Lowercase commands are matched without allocating an uppercase copy
+        assert!(matches!(&lines[2], GCodeLine::Tool(t) if t.temperature == Some(200.0)));
+        if let GCodeLine::Command(cmd) = &lines[3] {
+            assert_eq!(cmd.command_type, "G92");
+            assert!(cmd.parameters.contains(&('E', 0.0)));
+        } else {
+            panic!("expected a command");
+        }
     }
 }
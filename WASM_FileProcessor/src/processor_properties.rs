@@ -1,4 +1,6 @@
 use crate::gcode_line::{Vector3, Color4};
+use crate::slicers::FeatureType;
+use crate::slicers::slicer_config::SlicerConfig;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -16,7 +18,7 @@ pub enum ArcPlane {
     YZ,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Units {
     Millimeters,
     Inches,
@@ -28,6 +30,7 @@ pub struct Tool {
     pub tool_number: u8,
     pub color: Color4,
     pub diameter: f64,
+    pub filament_diameter: f64,
     pub temperature: f64,
     pub name: String,
 }
@@ -38,6 +41,7 @@ impl Tool {
             tool_number,
             color: Color4::white(),
             diameter: 0.4,
+            filament_diameter: 1.75,
             temperature: 200.0,
             name: format!("Tool {}", tool_number),
         }
@@ -46,6 +50,15 @@ impl Tool {
     pub fn default() -> Self {
         Self::new(0)
     }
+    
+    // Extrusion width from filament volume: E length * filament area = width * layer height * move length
+    pub fn extrusion_width(&self, e_length: f64, move_length: f64, layer_height: f64) -> f64 {
+        if move_length <= 0.0 || layer_height <= 0.0 || e_length <= 0.0 {
+            return self.diameter;
+        }
+        let filament_area = std::f64::consts::PI * (self.filament_diameter / 2.0).powi(2);
+        (e_length * filament_area) / (move_length * layer_height)
+    }
 }
 
 // Workplace offset information
@@ -83,6 +96,8 @@ pub struct ProcessorProperties {
     // Layer tracking
     pub layer_dictionary: HashMap<u32, u32>, // Z-height hash -> line count
     pub previous_z: f64, // Last Z where extrusion occurred
+    pub layer_height: f64, // From the slicer config or layer markers, for extrusion widths
+    pub layer_marker_z: f64, // Z of the last layer marker comment
     
     // Tool management
     pub tools: Vec<Tool>,
@@ -106,6 +121,7 @@ pub struct ProcessorProperties {
     pub current_bed_temp: f64,
     
     // Stepper and hardware state
+    pub fan_speed: f64, // M106 S value, 0-255
     pub steppers_enabled: bool,
     pub steps_per_mm_x: f64,
     pub steps_per_mm_y: f64,
@@ -144,6 +160,7 @@ pub struct ProcessorProperties {
     
     // Current feature coloring state (updated by comment processing)
     pub current_feature_color: Color4,
+    pub current_feature: FeatureType,
     pub current_is_perimeter: bool,
     pub current_is_support: bool,
 }
@@ -168,6 +185,8 @@ impl ProcessorProperties {
             last_gcode_byte: 0,
             layer_dictionary: HashMap::new(),
             previous_z: 0.0,
+            layer_height: 0.2,
+            layer_marker_z: 0.0,
             tools: tools.clone(),
             current_tool: tools[0].clone(),
             current_position: Vector3::zero(),
@@ -181,6 +200,7 @@ impl ProcessorProperties {
             target_bed_temp: 0.0,
             current_hotend_temp: 0.0,
             current_bed_temp: 0.0,
+            fan_speed: 0.0,
             steppers_enabled: true,
             steps_per_mm_x: 80.0,
             steps_per_mm_y: 80.0,
@@ -207,11 +227,20 @@ impl ProcessorProperties {
             
             // Initialize with default feature colors (white)
             current_feature_color: Color4::white(),
+            current_feature: FeatureType::Unknown,
             current_is_perimeter: false,
             current_is_support: false,
         }
     }
     
+    // Switch the active slicer feature (from a `;TYPE:` comment)
+    pub fn set_feature(&mut self, feature: FeatureType, color: Color4, is_perimeter: bool, is_support: bool) {
+        self.current_feature_color = color;
+        self.current_feature = feature;
+        self.current_is_perimeter = is_perimeter;
+        self.current_is_support = is_support;
+    }
+    
     // Get current workplace offset
     pub fn current_workplace(&self) -> &Vector3 {
         &self.workplace_offsets[self.current_workplace_idx as usize].offset
@@ -258,6 +287,14 @@ impl ProcessorProperties {
         }
     }
     
+    // Layer marker from the slicer: the rise since the previous marker is the layer height
+    pub fn set_layer_marker_z(&mut self, z: f64) {
+        if z > self.layer_marker_z + 1e-6 {
+            self.layer_height = z - self.layer_marker_z;
+            self.layer_marker_z = z;
+        }
+    }
+    
     // Update height tracking
     pub fn update_height(&mut self, z: f64) {
         if z > self.max_height {
@@ -295,6 +332,34 @@ impl ProcessorProperties {
         colors
     }
     
+    // Apply nozzle/filament sizes and colors from the embedded slicer config
+    pub fn apply_slicer_config(&mut self, config: &SlicerConfig) {
+        for index in 0..config.extruder_count() {
+            if !self.tools.iter().any(|t| t.tool_number as usize == index) {
+                self.tools.push(Tool::new(index as u8));
+            }
+            let tool = self.tools.iter_mut().find(|t| t.tool_number as usize == index).unwrap();
+            
+            if let Some(color) = config.tool_color(index) {
+                tool.color = color;
+            }
+            if let Some(&Some(nozzle)) = config.nozzle_diameters.get(index) {
+                tool.diameter = nozzle;
+            }
+            if let Some(&Some(filament)) = config.filament_diameters.get(index) {
+                tool.filament_diameter = filament;
+            }
+        }
+        self.tools.sort_by_key(|t| t.tool_number);
+        if let Some(layer_height) = config.layer_height {
+            self.layer_height = layer_height;
+        }
+        
+        if let Some(tool) = self.tools.iter().find(|t| t.tool_number == self.current_tool.tool_number) {
+            self.current_tool = tool.clone();
+        }
+    }
+    
     // Reset for new file processing
     pub fn reset(&mut self) {
         self.line_count = 0;
@@ -307,6 +372,8 @@ impl ProcessorProperties {
         self.current_e = 0.0;
         self.total_extrusion = 0.0;
         self.previous_z = 0.0;
+        self.layer_height = 0.2;
+        self.layer_marker_z = 0.0;
         self.total_rendered_segments = 0;
         self.max_height = 0.0;
         self.min_height = 0.0;
@@ -317,11 +384,11 @@ impl ProcessorProperties {
         self.target_bed_temp = 0.0;
         self.current_hotend_temp = 0.0;
         self.current_bed_temp = 0.0;
+        self.fan_speed = 0.0;
         
-        // Reset tool to default
-        if !self.tools.is_empty() {
-            self.current_tool = self.tools[0].clone();
-        }
+        // Tools come from each file's config and T commands; drop the previous file's
+        self.tools = vec![Tool::default()];
+        self.current_tool = self.tools[0].clone();
         
         // Reset workspace to default
         self.current_workplace_idx = 0;
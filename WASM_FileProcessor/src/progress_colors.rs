@@ -0,0 +1,136 @@
+// Incremental color updates for live print tracking
+// A move counts as printed once the print position passes its line start; printed moves take the
+// progress color, the rest keep their feature color. Only moves between the previous and the new
+// position change, so clients upload just that slice of the GPU color buffer.
+
+use wasm_bindgen::prelude::*;
+use crate::segment_store::{unpack_color, SegmentStore};
+
+/// Dirty segment range plus its replacement colors (RGBA floats, 4 per segment)
+#[wasm_bindgen]
+pub struct ProgressColorUpdate {
+    first_segment: u32,
+    color_data: Vec<f32>,
+}
+
+impl ProgressColorUpdate {
+    /// Colors for the segments whose printed state differs between two file positions.
+    /// With no progress color every segment in the range gets its own color back.
+    pub fn between(segments: &SegmentStore, previous_position: u32, position: u32, progress_color: Option<[f32; 4]>) -> Self {
+        let positions = segments.file_positions();
+        let first = positions.partition_point(|&p| p < previous_position.min(position));
+        let last = positions.partition_point(|&p| p < previous_position.max(position));
+
+        let mut color_data = Vec::with_capacity((last - first) * 4);
+        for (&file_position, &packed) in positions[first..last].iter().zip(&segments.colors()[first..last]) {
+            let color = match progress_color {
+                Some(color) if file_position < position => color,
+                _ => unpack_color(packed),
+            };
+            color_data.extend_from_slice(&color);
+        }
+
+        ProgressColorUpdate { first_segment: first as u32, color_data }
+    }
+
+    /// Write the replacement colors into a full per-segment RGBA buffer
+    pub fn apply_to(&self, color_data: &mut [f32]) {
+        let offset = self.color_offset() as usize;
+        if let Some(target) = color_data.get_mut(offset..offset + self.color_data.len()) {
+            target.copy_from_slice(&self.color_data);
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl ProgressColorUpdate {
+    /// First segment whose color changed
+    #[wasm_bindgen(getter)]
+    pub fn first_segment(&self) -> u32 {
+        self.first_segment
+    }
+
+    /// Number of changed segments (0 if nothing changed)
+    #[wasm_bindgen(getter)]
+    pub fn segment_count(&self) -> u32 {
+        (self.color_data.len() / 4) as u32
+    }
+
+    /// Float offset of the first changed color in `RenderBuffers::color_data`
+    #[wasm_bindgen(getter)]
+    pub fn color_offset(&self) -> u32 {
+        self.first_segment * 4
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn color_data(&self) -> Vec<f32> {
+        self.color_data.clone()
+    }
+
+    /// View of `color_data`; follows the lifetime contract of the RenderBuffers views
+    #[wasm_bindgen]
+    pub fn color_view(&self) -> js_sys::Float32Array {
+        unsafe { js_sys::Float32Array::view(&self.color_data) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segment_store::{Segment, FLAG_EXTRUDING};
+
+    const GREEN: [f32; 4] = [0.0, 1.0, 0.0, 1.0];
+
+    fn store() -> SegmentStore {
+        let mut store = SegmentStore::new();
+        for i in 0..10u32 {
+            store.push(Segment {
+                start: [i as f32, 0.2, 0.0],
+                end: [i as f32 + 1.0, 0.2, 0.0],
+                feed_rate: 1800.0,
+                layer_height: 0.2,
+                width: 0.4,
+                color: 0xFF0000FF, // Opaque red
+                flags: FLAG_EXTRUDING,
+                tool: 0,
+                feature: 0,
+                layer: 0,
+                line_number: i + 1,
+                file_position: i * 20,
+                file_end_position: i * 20 + 19,
+            });
+        }
+        store
+    }
+
+    #[test]
+    fn test_forward_and_backward_updates() {
+        let store = store();
+        let mut colors: Vec<f32> = store.colors().iter().flat_map(|&c| unpack_color(c)).collect();
+
+        // Print reaches the middle of line 4 (segment 3 starts at 60)
+        let forward = ProgressColorUpdate::between(&store, 0, 65, Some(GREEN));
+        assert_eq!((forward.first_segment(), forward.segment_count()), (0, 4));
+        forward.apply_to(&mut colors);
+        assert_eq!(&colors[12..16], &GREEN);
+        assert_eq!(&colors[16..20], &[1.0, 0.0, 0.0, 1.0]);
+
+        // Scrubbing back only touches segments 2 and 3, which return to their own color
+        let backward = ProgressColorUpdate::between(&store, 65, 40, Some(GREEN));
+        assert_eq!((backward.first_segment(), backward.segment_count()), (2, 2));
+        assert_eq!(backward.color_offset(), 8);
+        backward.apply_to(&mut colors);
+        assert_eq!(&colors[4..8], &GREEN);
+        assert_eq!(&colors[8..12], &[1.0, 0.0, 0.0, 1.0]);
+
+        assert_eq!(ProgressColorUpdate::between(&store, 65, 70, Some(GREEN)).segment_count(), 0);
+    }
+
+    #[test]
+    fn test_disabled_progress_restores_feature_colors() {
+        let store = store();
+        let update = ProgressColorUpdate::between(&store, 0, 1000, None);
+        assert_eq!(update.segment_count(), 10);
+        assert!(update.color_data.chunks(4).all(|c| c == [1.0, 0.0, 0.0, 1.0]));
+    }
+}
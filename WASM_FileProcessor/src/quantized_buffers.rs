@@ -0,0 +1,237 @@
+// Quantized render buffers for memory-constrained clients
+// Positions are u16 relative to the model bounding box (compact start/end layout), sizes are
+// u16 micrometres, colors u8 RGBA, flags bit-packed and IDs/file positions u32.
+// Dequantize a position with: min + q * scale (per axis, see `bounds_min` / `position_scale`).
+
+use wasm_bindgen::prelude::*;
+use crate::compact_buffers::compact_segment;
+use crate::segment_store::SegmentStore;
+use crate::pick_id::pick_id;
+
+const POSITION_STEPS: f32 = u16::MAX as f32;
+const SIZE_UNITS_PER_MM: f32 = 1000.0; // Width/height in micrometres
+
+#[wasm_bindgen]
+pub struct QuantizedRenderBuffers {
+    position_data: Vec<u16>, // Start xyz, end xyz per segment
+    size_data: Vec<u16>,     // Width, height per segment (micrometres)
+    color_data: Vec<u8>,     // RGBA per segment
+    flag_data: Vec<u8>,      // Segment store bits: 1 extruding, 2 perimeter, 4 support
+    tool_data: Vec<u8>,
+    feed_rate_data: Vec<u16>, // mm/min, saturating
+    id_data: Vec<u32>,        // Pick ID per segment (segment index + 1, see `pick_id`)
+    file_position_data: Vec<u32>,
+    file_end_position_data: Vec<u32>,
+    bounds_min: [f32; 3],
+    position_scale: [f32; 3],
+    segment_count: u32,
+}
+
+impl QuantizedRenderBuffers {
+    /// Quantize every stored segment (two passes: bounds, then quantization; no float copy is kept)
+    pub fn from_segments(segments: &SegmentStore, nozzle_size: f32, padding: f32) -> Self {
+        let count = segments.len();
+
+        // Bounding box over both endpoints (including padding)
+        let mut min = [f32::INFINITY; 3];
+        let mut max = [f32::NEG_INFINITY; 3];
+        for segment in segments.iter() {
+            let record = compact_segment(&segment, nozzle_size, padding);
+            for point in [&record[0..3], &record[3..6]] {
+                for axis in 0..3 {
+                    min[axis] = min[axis].min(point[axis]);
+                    max[axis] = max[axis].max(point[axis]);
+                }
+            }
+        }
+        if count == 0 {
+            min = [0.0; 3];
+            max = [0.0; 3];
+        }
+        let position_scale = [0, 1, 2].map(|axis| (max[axis] - min[axis]) / POSITION_STEPS);
+
+        let quantize = |value: f32, axis: usize| -> u16 {
+            if position_scale[axis] > 0.0 {
+                ((value - min[axis]) / position_scale[axis]).round().clamp(0.0, POSITION_STEPS) as u16
+            } else {
+                0
+            }
+        };
+        let to_micrometres = |mm: f32| (mm * SIZE_UNITS_PER_MM).round().clamp(0.0, u16::MAX as f32) as u16;
+
+        let mut buffers = QuantizedRenderBuffers {
+            position_data: Vec::with_capacity(count * 6),
+            size_data: Vec::with_capacity(count * 2),
+            color_data: Vec::with_capacity(count * 4),
+            flag_data: Vec::with_capacity(count),
+            tool_data: Vec::with_capacity(count),
+            feed_rate_data: Vec::with_capacity(count),
+            id_data: Vec::with_capacity(count),
+            file_position_data: Vec::with_capacity(count),
+            file_end_position_data: Vec::with_capacity(count),
+            bounds_min: min,
+            position_scale,
+            segment_count: count as u32,
+        };
+
+        for (index, segment) in segments.iter().enumerate() {
+            let record = compact_segment(&segment, nozzle_size, padding);
+            for (component, &value) in record[0..6].iter().enumerate() {
+                buffers.position_data.push(quantize(value, component % 3));
+            }
+            buffers.size_data.push(to_micrometres(record[6]));
+            buffers.size_data.push(to_micrometres(record[7]));
+            buffers.color_data.extend_from_slice(&segment.color.to_le_bytes());
+            buffers.flag_data.push(segment.flags);
+            buffers.tool_data.push(segment.tool);
+            buffers.feed_rate_data.push(segment.feed_rate.round().clamp(0.0, u16::MAX as f32) as u16);
+            buffers.id_data.push(pick_id(index as u32));
+            buffers.file_position_data.push(segment.file_position);
+            buffers.file_end_position_data.push(segment.file_end_position);
+        }
+
+        buffers
+    }
+
+    /// Dequantized start and end of a segment
+    pub fn segment_endpoints(&self, index: usize) -> ([f32; 3], [f32; 3]) {
+        let q = &self.position_data[index * 6..index * 6 + 6];
+        let point = |offset: usize| [0, 1, 2].map(|axis| self.bounds_min[axis] + q[offset + axis] as f32 * self.position_scale[axis]);
+        (point(0), point(3))
+    }
+}
+
+#[wasm_bindgen]
+impl QuantizedRenderBuffers {
+    #[wasm_bindgen(getter)]
+    pub fn segment_count(&self) -> u32 {
+        self.segment_count
+    }
+
+    /// Minimum corner of the bounding box the positions are relative to
+    #[wasm_bindgen(getter)]
+    pub fn bounds_min(&self) -> Vec<f32> {
+        self.bounds_min.to_vec()
+    }
+
+    /// Millimetres per quantization step on each axis
+    #[wasm_bindgen(getter)]
+    pub fn position_scale(&self) -> Vec<f32> {
+        self.position_scale.to_vec()
+    }
+
+    /// Bytes held by the quantized buffers
+    #[wasm_bindgen]
+    pub fn memory_bytes(&self) -> usize {
+        (self.position_data.len() + self.size_data.len() + self.feed_rate_data.len()) * 2
+            + self.color_data.len() + self.flag_data.len() + self.tool_data.len()
+            + (self.id_data.len() + self.file_position_data.len() + self.file_end_position_data.len()) * 4
+    }
+
+    // Views follow the lifetime contract of the RenderBuffers views
+    #[wasm_bindgen]
+    pub fn position_view(&self) -> js_sys::Uint16Array {
+        unsafe { js_sys::Uint16Array::view(&self.position_data) }
+    }
+
+    #[wasm_bindgen]
+    pub fn size_view(&self) -> js_sys::Uint16Array {
+        unsafe { js_sys::Uint16Array::view(&self.size_data) }
+    }
+
+    #[wasm_bindgen]
+    pub fn color_view(&self) -> js_sys::Uint8Array {
+        unsafe { js_sys::Uint8Array::view(&self.color_data) }
+    }
+
+    #[wasm_bindgen]
+    pub fn flag_view(&self) -> js_sys::Uint8Array {
+        unsafe { js_sys::Uint8Array::view(&self.flag_data) }
+    }
+
+    #[wasm_bindgen]
+    pub fn tool_view(&self) -> js_sys::Uint8Array {
+        unsafe { js_sys::Uint8Array::view(&self.tool_data) }
+    }
+
+    #[wasm_bindgen]
+    pub fn feed_rate_view(&self) -> js_sys::Uint16Array {
+        unsafe { js_sys::Uint16Array::view(&self.feed_rate_data) }
+    }
+
+    #[wasm_bindgen]
+    pub fn id_view(&self) -> js_sys::Uint32Array {
+        unsafe { js_sys::Uint32Array::view(&self.id_data) }
+    }
+
+    #[wasm_bindgen]
+    pub fn file_position_view(&self) -> js_sys::Uint32Array {
+        unsafe { js_sys::Uint32Array::view(&self.file_position_data) }
+    }
+
+    #[wasm_bindgen]
+    pub fn file_end_position_view(&self) -> js_sys::Uint32Array {
+        unsafe { js_sys::Uint32Array::view(&self.file_end_position_data) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gcode_line::Color4;
+    use crate::segment_store::{pack_color, Segment, FLAG_EXTRUDING, FLAG_PERIMETER};
+
+    fn store() -> SegmentStore {
+        let mut store = SegmentStore::new();
+        for i in 0..100 {
+            let x = i as f32 * 2.5;
+            store.push(Segment {
+                start: [x, 0.2, -50.0],
+                end: [x + 2.5, 0.2, 180.0],
+                feed_rate: 1800.0,
+                layer_height: 0.2,
+                width: 0.4,
+                color: pack_color(&Color4::new(1.0, 0.5, 0.0, 1.0)),
+                flags: FLAG_EXTRUDING | FLAG_PERIMETER,
+                tool: 1,
+                feature: 0,
+                layer: 0,
+                line_number: i + 10,
+                file_position: i * 20,
+                file_end_position: i * 20 + 19,
+            });
+        }
+        store
+    }
+
+    #[test]
+    fn test_quantization_round_trip() {
+        let store = store();
+        let buffers = QuantizedRenderBuffers::from_segments(&store, 0.4, 0.0);
+        assert_eq!(buffers.segment_count, 100);
+
+        // Error stays within half a step of the bounding box extent
+        for index in [0, 37, 99] {
+            let (start, end) = buffers.segment_endpoints(index);
+            let segment = store.get(index);
+            for axis in 0..3 {
+                let tolerance = buffers.position_scale[axis] * 0.5 + 1e-4;
+                assert!((start[axis] - segment.start[axis]).abs() <= tolerance);
+                assert!((end[axis] - segment.end[axis]).abs() <= tolerance);
+            }
+        }
+
+        assert_eq!(&buffers.size_data[0..2], &[400, 200]);
+        assert_eq!(&buffers.color_data[0..4], &[255, 128, 0, 255]);
+        assert_eq!(buffers.flag_data[0], FLAG_EXTRUDING | FLAG_PERIMETER);
+        assert_eq!(buffers.id_data[5], 6);
+    }
+
+    #[test]
+    fn test_memory_versus_f32_layout() {
+        let buffers = QuantizedRenderBuffers::from_segments(&store(), 0.4, 0.0);
+        // f32 layout: 16 matrix + 4 color + 3 pick + 5 scalar floats per segment
+        let f32_bytes = 100 * (16 + 4 + 3 + 5) * 4;
+        assert!(buffers.memory_bytes() * 3 < f32_bytes);
+    }
+}
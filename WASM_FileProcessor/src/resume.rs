@@ -0,0 +1,182 @@
+// Resume-from-layer G-code (power loss, filament runout)
+// The resumed file is a preamble restoring the machine state recorded at the layer's start byte,
+// followed by the original text from that byte. Z is not homed: the nozzle may sit on the print.
+
+use std::fmt::Write;
+use crate::machine_state::MachineState;
+use crate::processor_properties::Units;
+use crate::segment_store::SegmentStore;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ResumeSettings {
+    pub z_lift: f64,           // Clearance above the resume height while travelling (mm)
+    pub travel_feed_rate: f64, // mm/min
+    pub z_feed_rate: f64,      // mm/min
+}
+
+impl Default for ResumeSettings {
+    fn default() -> Self {
+        ResumeSettings { z_lift: 2.0, travel_feed_rate: 3000.0, z_feed_rate: 600.0 }
+    }
+}
+
+/// Byte where printing resumes for a layer: the line after the previous layer's last extrusion,
+/// so the layer change (retract, Z move, comments) is replayed from the original file
+pub fn layer_start_position(source: &str, segments: &SegmentStore, layer: u32) -> Option<u32> {
+    let first = segments.layers().partition_point(|&l| l < layer);
+    if first >= segments.len() {
+        return None;
+    }
+    let previous_extrusion = (0..first).rev().map(|index| segments.get(index)).find(|segment| segment.extruding());
+    let Some(segment) = previous_extrusion else {
+        return Some(segments.file_positions()[first]);
+    };
+    // Past the line terminator, which is two bytes in CRLF files
+    let end = segment.file_end_position as usize;
+    let newline = source.get(end..)?.find('\n')?;
+    Some((end + newline + 1) as u32)
+}
+
+/// Preamble restoring `state`: heat, set Z to the saved height and lift off the print, home XY,
+/// travel back and lower, then restore fan, modes and E
+pub fn resume_preamble(state: &MachineState, layer: u32, settings: &ResumeSettings) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "; Resumed at layer {} (line {}, byte {})", layer, state.line_number, state.file_position);
+    out.push_str(if state.units == Units::Inches { "G20\n" } else { "G21\n" });
+    let _ = writeln!(out, "T{}", state.tool);
+
+    // Start heating both, then wait on both
+    if state.bed_temperature > 0.0 {
+        let _ = writeln!(out, "M140 S{}", state.bed_temperature);
+    }
+    if state.hotend_temperature > 0.0 {
+        let _ = writeln!(out, "M104 S{}", state.hotend_temperature);
+    }
+    if state.bed_temperature > 0.0 {
+        let _ = writeln!(out, "M190 S{}", state.bed_temperature);
+    }
+    if state.hotend_temperature > 0.0 {
+        let _ = writeln!(out, "M109 S{}", state.hotend_temperature);
+    }
+
+    // Z is unhomed but the nozzle sits at the saved height; lift clear before homing XY
+    let _ = writeln!(out, "G92 Z{:.3}", state.z);
+    out.push_str("G91\n");
+    let _ = writeln!(out, "G1 Z{:.3} F{}", settings.z_lift, settings.z_feed_rate);
+    out.push_str("G90\n");
+    out.push_str("G28 X Y ; Home XY only\n");
+    let _ = writeln!(out, "G1 X{:.3} Y{:.3} F{}", state.x, state.y, settings.travel_feed_rate);
+    let _ = writeln!(out, "G1 Z{:.3} F{}", state.z, settings.z_feed_rate);
+
+    if state.fan_speed > 0.0 {
+        let _ = writeln!(out, "M106 S{}", state.fan_speed);
+    } else {
+        out.push_str("M107\n");
+    }
+    out.push_str(if state.absolute_extrusion { "M82\n" } else { "M83\n" });
+    let _ = writeln!(out, "G92 E{:.5}", if state.absolute_extrusion { state.e } else { 0.0 });
+    if !state.absolute_positioning {
+        out.push_str("G91\n");
+    }
+    let _ = writeln!(out, "G1 F{}", state.feed_rate);
+    out
+}
+
+/// Preamble followed by the original text from `start`
+pub fn resume_gcode(source: &str, start: u32, preamble: &str) -> Option<String> {
+    let rest = source.get(start as usize..)?;
+    let mut out = String::with_capacity(preamble.len() + rest.len());
+    out.push_str(preamble);
+    out.push_str(rest);
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segment_store::{Segment, FLAG_EXTRUDING};
+    use crate::slicers::FeatureType;
+
+    fn segment(z: f32, extruding: bool, file_position: u32) -> Segment {
+        Segment {
+            start: [0.0, z, 0.0],
+            end: [1.0, z, 0.0],
+            feed_rate: 1800.0,
+            layer_height: 0.2,
+            width: 0.4,
+            color: 0,
+            flags: if extruding { FLAG_EXTRUDING } else { 0 },
+            tool: 0,
+            feature: 0,
+            layer: 0,
+            line_number: file_position / 10 + 1,
+            file_position,
+            file_end_position: file_position + 9,
+        }
+    }
+
+    fn state() -> MachineState {
+        MachineState {
+            file_position: 30,
+            line_number: 4,
+            x: 10.0,
+            y: 20.0,
+            z: 0.4,
+            e: 12.5,
+            feed_rate: 1800.0,
+            absolute_positioning: true,
+            absolute_extrusion: true,
+            units: Units::Millimeters,
+            workplace: "G54".to_string(),
+            tool: 0,
+            hotend_temperature: 215.0,
+            bed_temperature: 60.0,
+            fan_speed: 255.0,
+            feature: FeatureType::Perimeter,
+        }
+    }
+
+    #[test]
+    fn test_layer_start_includes_layer_change() {
+        let source = "G1 X1 E01\n".repeat(5);
+        let mut store = SegmentStore::new();
+        store.push(segment(0.2, true, 0));
+        store.push(segment(0.2, true, 10));
+        store.push(segment(0.4, false, 30)); // Travel up to the next layer (still layer 0)
+        store.push(segment(0.4, true, 40));
+
+        assert_eq!(store.get(3).layer, 1);
+        // Line after the last layer-0 extrusion, before the travel
+        assert_eq!(layer_start_position(&source, &store, 1), Some(20));
+        assert_eq!(layer_start_position(&source, &store, 0), Some(0));
+        assert_eq!(layer_start_position(&source, &store, 2), None);
+
+        // CRLF: 11-byte lines, the terminator after the extrusion at 11 takes two bytes
+        let crlf = "G1 X1 E01\r\n".repeat(5);
+        let mut store = SegmentStore::new();
+        for (z, extruding, position) in [(0.2, true, 0), (0.2, true, 11), (0.4, false, 33), (0.4, true, 44)] {
+            store.push(segment(z, extruding, position));
+        }
+        assert_eq!(layer_start_position(&crlf, &store, 1), Some(22));
+    }
+
+    #[test]
+    fn test_preamble_restores_state() {
+        let preamble = resume_preamble(&state(), 1, &ResumeSettings::default());
+        let lines: Vec<&str> = preamble.lines().collect();
+        let index_of = |prefix: &str| lines.iter().position(|line| line.starts_with(prefix)).unwrap();
+
+        assert!(index_of("M190 S60") < index_of("G28 X Y"));
+        assert!(index_of("M109 S215") < index_of("G28 X Y"));
+        assert!(!preamble.contains("G28\n") && !preamble.contains("G28 Z"));
+        // Set Z, lift relative, then home XY, travel and lower to the saved height
+        let order = ["G92 Z0.400", "G91", "G1 Z2.000", "G90", "G28 X Y", "G1 X10.000 Y20.000", "G1 Z0.400"];
+        let indices: Vec<usize> = order.iter().map(|prefix| index_of(prefix)).collect();
+        assert!(indices.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", lines);
+        assert!(lines.contains(&"M106 S255") && lines.contains(&"G92 E12.50000"));
+
+        let resumed = resume_gcode("G1 X0\n;LAYER:1\nG1 X1 E1\n", 6, &preamble).unwrap();
+        assert!(resumed.starts_with("; Resumed at layer 1"));
+        assert!(resumed.ends_with(&format!("{}\n;LAYER:1\nG1 X1 E1\n", lines.last().unwrap())));
+    }
+}
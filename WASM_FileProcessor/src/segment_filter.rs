@@ -0,0 +1,155 @@
+// Segment visibility filters for partial render buffers (layer sliders, "show only support"...)
+// Passed from JS as a plain object; omitted fields do not filter.
+
+use crate::color_theme::ALL_FEATURES;
+use crate::segment_store::{feature_index, Segment, SegmentStore};
+use crate::slicers::FeatureType;
+use serde::{Deserialize, Serialize};
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SegmentFilter {
+    /// Inclusive layer range
+    #[serde(default)]
+    pub min_layer: Option<u32>,
+    #[serde(default)]
+    pub max_layer: Option<u32>,
+    /// Inclusive file byte range; a segment matches if its line starts inside it
+    #[serde(default)]
+    pub start_position: Option<u32>,
+    #[serde(default)]
+    pub end_position: Option<u32>,
+    /// Extrusion features to keep (travel moves are controlled by `show_travel`)
+    #[serde(default)]
+    pub features: Option<Vec<FeatureType>>,
+    #[serde(default)]
+    pub tools: Option<Vec<u8>>,
+    #[serde(default = "default_true")]
+    pub show_travel: bool,
+    #[serde(default = "default_true")]
+    pub show_extrusion: bool,
+}
+
+impl Default for SegmentFilter {
+    fn default() -> Self {
+        SegmentFilter {
+            min_layer: None,
+            max_layer: None,
+            start_position: None,
+            end_position: None,
+            features: None,
+            tools: None,
+            show_travel: true,
+            show_extrusion: true,
+        }
+    }
+}
+
+impl SegmentFilter {
+    // Per-feature lookup, indexed like ALL_FEATURES
+    fn feature_mask(&self) -> Option<[bool; ALL_FEATURES.len()]> {
+        self.features.as_ref().map(|features| {
+            let mut mask = [false; ALL_FEATURES.len()];
+            for feature in features {
+                mask[feature_index(feature) as usize] = true;
+            }
+            mask
+        })
+    }
+
+    fn matches(&self, segment: &Segment, feature_mask: Option<&[bool; ALL_FEATURES.len()]>) -> bool {
+        if segment.extruding() {
+            if !self.show_extrusion
+                || feature_mask.is_some_and(|mask| !mask.get(segment.feature as usize).copied().unwrap_or(false))
+            {
+                return false;
+            }
+        } else if !self.show_travel {
+            return false;
+        }
+
+        self.min_layer.is_none_or(|min| segment.layer >= min)
+            && self.max_layer.is_none_or(|max| segment.layer <= max)
+            && self.start_position.is_none_or(|start| segment.file_position >= start)
+            && self.end_position.is_none_or(|end| segment.file_position <= end)
+            && self.tools.as_ref().is_none_or(|tools| tools.contains(&segment.tool))
+    }
+
+    /// Indices (ascending) of the matching segments
+    pub fn select(&self, segments: &SegmentStore) -> Vec<u32> {
+        let mask = self.feature_mask();
+
+        // Segments are in file order, so a file range bounds the scan
+        let positions = segments.file_positions();
+        let first = self.start_position.map_or(0, |start| positions.partition_point(|&p| p < start));
+        let last = self.end_position.map_or(positions.len(), |end| positions.partition_point(|&p| p <= end));
+
+        (first..last.max(first))
+            .filter(|&index| self.matches(&segments.get(index), mask.as_ref()))
+            .map(|index| index as u32)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segment_store::FLAG_EXTRUDING;
+
+    fn store() -> SegmentStore {
+        let mut store = SegmentStore::new();
+        let features = [FeatureType::Perimeter, FeatureType::Support, FeatureType::Infill];
+        for i in 0..30u32 {
+            let height = 0.2 * (i / 10 + 1) as f32;
+            let travel = i % 5 == 4;
+            store.push(Segment {
+                start: [0.0, height, 0.0],
+                end: [1.0, height, 0.0],
+                feed_rate: 1800.0,
+                layer_height: 0.2,
+                width: 0.4,
+                color: 0,
+                flags: if travel { 0 } else { FLAG_EXTRUDING },
+                tool: (i % 2) as u8,
+                feature: feature_index(&features[(i % 3) as usize]),
+                layer: 0,
+                line_number: i + 1,
+                file_position: i * 10,
+                file_end_position: i * 10 + 9,
+            });
+        }
+        store
+    }
+
+    #[test]
+    fn test_layer_and_file_ranges() {
+        let store = store();
+        assert_eq!(store.layer_count(), 3);
+        assert_eq!(SegmentFilter::default().select(&store).len(), 30);
+
+        let layer = SegmentFilter { min_layer: Some(1), max_layer: Some(1), ..Default::default() };
+        assert_eq!(layer.select(&store), (10..20).collect::<Vec<u32>>());
+
+        let range = SegmentFilter { start_position: Some(55), end_position: Some(90), ..Default::default() };
+        assert_eq!(range.select(&store), (6..=9).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn test_feature_tool_and_travel_filters() {
+        let store = store();
+        let support_only = SegmentFilter {
+            features: Some(vec![FeatureType::Support]),
+            show_travel: false,
+            ..Default::default()
+        };
+        let selected = support_only.select(&store);
+        assert!(!selected.is_empty());
+        assert!(selected.iter().all(|&i| i % 3 == 1 && i % 5 != 4));
+
+        let tool_one = SegmentFilter { tools: Some(vec![1]), show_extrusion: false, ..Default::default() };
+        assert_eq!(tool_one.select(&store), vec![9, 19, 29]);
+    }
+}
@@ -0,0 +1,354 @@
+// Columnar (struct-of-arrays) storage for parsed toolpath segments
+// Segments are appended in file order, so file positions are sorted without a separate index.
+
+use crate::color_theme::ALL_FEATURES;
+use crate::gcode_line::Color4;
+use crate::slicers::FeatureType;
+use crate::PositionData;
+use std::collections::HashMap;
+use std::mem::size_of;
+
+pub const FLAG_EXTRUDING: u8 = 1;
+pub const FLAG_PERIMETER: u8 = 2;
+pub const FLAG_SUPPORT: u8 = 4;
+
+// Minimum height increase of an extruding move that starts a new layer
+const LAYER_EPSILON: f32 = 0.001;
+
+/// One segment, assembled from the columns on demand
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Segment {
+    pub start: [f32; 3],
+    pub end: [f32; 3],
+    pub feed_rate: f32,
+    pub layer_height: f32,
+    pub width: f32, // Extrusion width (mm)
+    pub color: u32, // Packed RGBA8, see `pack_color`
+    pub flags: u8,
+    pub tool: u8,
+    pub feature: u8, // Index into ALL_FEATURES
+    pub layer: u32,  // Assigned by `SegmentStore::push`
+    pub line_number: u32,
+    pub file_position: u32,
+    pub file_end_position: u32,
+}
+
+impl Segment {
+    pub fn extruding(&self) -> bool {
+        self.flags & FLAG_EXTRUDING != 0
+    }
+
+    pub fn is_perimeter(&self) -> bool {
+        self.flags & FLAG_PERIMETER != 0
+    }
+
+    pub fn is_support(&self) -> bool {
+        self.flags & FLAG_SUPPORT != 0
+    }
+
+    pub fn feature(&self) -> FeatureType {
+        feature_from_index(self.feature)
+    }
+
+    pub fn length(&self) -> f32 {
+        let dx = self.end[0] - self.start[0];
+        let dy = self.end[1] - self.start[1];
+        let dz = self.end[2] - self.start[2];
+        (dx * dx + dy * dy + dz * dz).sqrt()
+    }
+
+    /// Color as normalized RGBA floats
+    pub fn color_rgba(&self) -> [f32; 4] {
+        unpack_color(self.color)
+    }
+}
+
+/// Segment table in file order
+#[derive(Default)]
+pub struct SegmentStore {
+    start: Vec<[f32; 3]>,
+    end: Vec<[f32; 3]>,
+    feed_rate: Vec<f32>,
+    layer_height: Vec<f32>,
+    width: Vec<f32>,
+    color: Vec<u32>,
+    flags: Vec<u8>,
+    tool: Vec<u8>,
+    feature: Vec<u8>,
+    layer: Vec<u32>,
+    line_number: Vec<u32>,
+    file_position: Vec<u32>,
+    file_end_position: Vec<u32>,
+
+    // Layer assignment state
+    current_layer: u32,
+    layer_top: Option<f32>,
+}
+
+impl SegmentStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            start: Vec::with_capacity(capacity),
+            end: Vec::with_capacity(capacity),
+            feed_rate: Vec::with_capacity(capacity),
+            layer_height: Vec::with_capacity(capacity),
+            width: Vec::with_capacity(capacity),
+            color: Vec::with_capacity(capacity),
+            flags: Vec::with_capacity(capacity),
+            tool: Vec::with_capacity(capacity),
+            feature: Vec::with_capacity(capacity),
+            layer: Vec::with_capacity(capacity),
+            line_number: Vec::with_capacity(capacity),
+            file_position: Vec::with_capacity(capacity),
+            file_end_position: Vec::with_capacity(capacity),
+            current_layer: 0,
+            layer_top: None,
+        }
+    }
+
+    /// Append a segment; must be called in file order. The layer index is assigned here:
+    /// an extruding move ending above the current layer starts a new one (Y is the vertical axis)
+    pub fn push(&mut self, mut segment: Segment) {
+        if segment.extruding() {
+            let height = segment.end[1];
+            match self.layer_top {
+                None => self.layer_top = Some(height),
+                Some(top) if height > top + LAYER_EPSILON => {
+                    self.current_layer += 1;
+                    self.layer_top = Some(height);
+                }
+                _ => {}
+            }
+        }
+        segment.layer = self.current_layer;
+
+        self.start.push(segment.start);
+        self.end.push(segment.end);
+        self.feed_rate.push(segment.feed_rate);
+        self.layer_height.push(segment.layer_height);
+        self.width.push(segment.width);
+        self.color.push(segment.color);
+        self.flags.push(segment.flags);
+        self.tool.push(segment.tool);
+        self.feature.push(segment.feature);
+        self.layer.push(segment.layer);
+        self.line_number.push(segment.line_number);
+        self.file_position.push(segment.file_position);
+        self.file_end_position.push(segment.file_end_position);
+    }
+
+    pub fn len(&self) -> usize {
+        self.file_position.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.file_position.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Assemble the segment at `index`
+    pub fn get(&self, index: usize) -> Segment {
+        Segment {
+            start: self.start[index],
+            end: self.end[index],
+            feed_rate: self.feed_rate[index],
+            layer_height: self.layer_height[index],
+            width: self.width[index],
+            color: self.color[index],
+            flags: self.flags[index],
+            tool: self.tool[index],
+            feature: self.feature[index],
+            layer: self.layer[index],
+            line_number: self.line_number[index],
+            file_position: self.file_position[index],
+            file_end_position: self.file_end_position[index],
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Segment> + '_ {
+        (0..self.len()).map(move |index| self.get(index))
+    }
+
+    /// File position column (sorted ascending; arc segments share their line's position)
+    pub fn file_positions(&self) -> &[u32] {
+        &self.file_position
+    }
+
+    pub fn colors(&self) -> &[u32] {
+        &self.color
+    }
+
+    /// Layer column (ascending, assigned in file order)
+    pub fn layers(&self) -> &[u32] {
+        &self.layer
+    }
+
+    pub fn layer_count(&self) -> u32 {
+        if self.is_empty() { 0 } else { self.current_layer + 1 }
+    }
+
+    /// Index of the first segment starting at exactly `file_position`
+    pub fn index_of(&self, file_position: u32) -> Option<usize> {
+        let index = self.file_position.partition_point(|&p| p < file_position);
+        (self.file_position.get(index) == Some(&file_position)).then_some(index)
+    }
+
+    /// File position of the segment closest to `target` (ties go to the earlier segment)
+    pub fn closest_file_position(&self, target: u32) -> Option<u32> {
+        let positions = &self.file_position;
+        if positions.is_empty() {
+            return None;
+        }
+        let index = positions.partition_point(|&p| p < target);
+        if index == 0 {
+            return Some(positions[0]);
+        }
+        if index >= positions.len() {
+            return Some(positions[positions.len() - 1]);
+        }
+        let left = positions[index - 1];
+        let right = positions[index];
+        Some(if target - left <= right - target { left } else { right })
+    }
+
+    /// Unique file positions in order (one entry per G-code line with moves)
+    pub fn unique_file_positions(&self) -> Vec<u32> {
+        let mut positions = self.file_position.clone();
+        positions.dedup();
+        positions
+    }
+
+    /// Rewrite colors for every segment whose feature matches `recolor`
+    pub fn recolor<F: Fn(&FeatureType) -> Option<Color4>>(&mut self, recolor: F) {
+        // Resolve each feature once rather than per segment
+        let lookup: Vec<Option<u32>> = ALL_FEATURES.iter().map(|f| recolor(f).map(|c| pack_color(&c))).collect();
+        for (color, &feature) in self.color.iter_mut().zip(self.feature.iter()) {
+            if let Some(Some(packed)) = lookup.get(feature as usize) {
+                *color = *packed;
+            }
+        }
+    }
+
+    /// Bytes held by the columns
+    pub fn memory_bytes(&self) -> usize {
+        self.start.capacity() * size_of::<[f32; 3]>() * 2
+            + (self.feed_rate.capacity() + self.layer_height.capacity() + self.width.capacity()) * size_of::<f32>()
+            + (self.color.capacity() + self.layer.capacity() + self.line_number.capacity()
+                + self.file_position.capacity() + self.file_end_position.capacity()) * size_of::<u32>()
+            + self.flags.capacity() + self.tool.capacity() + self.feature.capacity()
+    }
+
+    /// Bytes per segment of the columnar layout
+    pub const BYTES_PER_SEGMENT: usize = 2 * size_of::<[f32; 3]>() + 3 * size_of::<f32>() + 5 * size_of::<u32>() + 3;
+
+    /// Bytes the previous layout allocates for these segments, measured by building it: a
+    /// HashMap<u32, PositionData> keyed by file position plus its sorted key vector. Counts
+    /// usable map capacity (one control byte per slot), so spare buckets are left out.
+    pub fn legacy_layout_bytes(&self) -> usize {
+        let mut positions: HashMap<u32, PositionData> = HashMap::new();
+        for index in 0..self.len() {
+            positions.insert(self.file_position[index], PositionData::from_segment(&self.get(index)));
+        }
+        let mut sorted_positions: Vec<u32> = positions.keys().copied().collect();
+        sorted_positions.sort_unstable();
+        positions.capacity() * (size_of::<(u32, PositionData)>() + 1) + sorted_positions.capacity() * size_of::<u32>()
+    }
+}
+
+/// Pack normalized RGBA into 8 bits per channel
+pub fn pack_color(color: &Color4) -> u32 {
+    let channel = |v: f64| (v.clamp(0.0, 1.0) * 255.0).round() as u32;
+    channel(color.r) | (channel(color.g) << 8) | (channel(color.b) << 16) | (channel(color.a) << 24)
+}
+
+pub fn unpack_color(packed: u32) -> [f32; 4] {
+    [
+        (packed & 0xFF) as f32 / 255.0,
+        ((packed >> 8) & 0xFF) as f32 / 255.0,
+        ((packed >> 16) & 0xFF) as f32 / 255.0,
+        (packed >> 24) as f32 / 255.0,
+    ]
+}
+
+pub fn feature_index(feature: &FeatureType) -> u8 {
+    ALL_FEATURES.iter().position(|f| f == feature).unwrap_or(ALL_FEATURES.len() - 1) as u8
+}
+
+pub fn feature_from_index(index: u8) -> FeatureType {
+    ALL_FEATURES.get(index as usize).cloned().unwrap_or(FeatureType::Unknown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(file_position: u32, height: f32, extruding: bool) -> Segment {
+        Segment {
+            start: [0.0, height, 0.0],
+            end: [10.0, height, 0.0],
+            feed_rate: 1500.0,
+            layer_height: 0.2,
+            width: 0.4,
+            color: pack_color(&Color4::white()),
+            flags: if extruding { FLAG_EXTRUDING } else { 0 },
+            tool: 0,
+            feature: feature_index(&FeatureType::Infill),
+            layer: 0,
+            line_number: file_position / 10,
+            file_position,
+            file_end_position: file_position + 9,
+        }
+    }
+
+    #[test]
+    fn test_layers_and_lookup() {
+        let mut store = SegmentStore::new();
+        store.push(segment(0, 0.2, true));
+        store.push(segment(10, 0.2, true));
+        store.push(segment(10, 0.2, true)); // Arc sub-segment on the same line
+        store.push(segment(20, 0.6, false)); // Travel up does not start a layer
+        store.push(segment(40, 0.4, true));
+
+        assert_eq!(store.len(), 5);
+        assert_eq!(store.layer_count(), 2);
+        assert_eq!(store.get(4).layer, 1);
+        assert_eq!(store.get(3).layer, 0);
+
+        assert_eq!(store.index_of(10), Some(1));
+        assert_eq!(store.index_of(11), None);
+        assert_eq!(store.closest_file_position(14), Some(10));
+        assert_eq!(store.closest_file_position(31), Some(40));
+        assert_eq!(store.unique_file_positions(), vec![0, 10, 20, 40]);
+        assert_eq!(store.get(0).feature(), FeatureType::Infill);
+    }
+
+    #[test]
+    fn test_recolor_and_color_packing() {
+        let mut store = SegmentStore::new();
+        store.push(segment(0, 0.2, true));
+        store.recolor(|feature| (*feature == FeatureType::Infill).then(|| Color4::new(1.0, 0.0, 0.0, 0.5)));
+
+        let rgba = store.get(0).color_rgba();
+        assert_eq!(rgba[0], 1.0);
+        assert_eq!(rgba[1], 0.0);
+        assert!((rgba[3] - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_memory_reduction() {
+        let mut store = SegmentStore::with_capacity(1000);
+        for i in 0..1000 {
+            store.push(segment(i * 10, 0.2, true));
+        }
+        assert_eq!(store.memory_bytes(), 1000 * SegmentStore::BYTES_PER_SEGMENT);
+        // At most half the memory of the HashMap<u32, PositionData> layout
+        assert!(store.memory_bytes() * 2 <= store.legacy_layout_bytes());
+    }
+}
@@ -1,5 +1,6 @@
 use crate::slicers::{SlicerBase, FeatureType, LayerInfo};
 use crate::gcode_line::Color4;
+use crate::slicers::job_metadata::JobMetadata;
 
 pub struct CuraSlicer {
     name: String,
@@ -109,6 +110,11 @@ impl SlicerBase for CuraSlicer {
         }
         None
     }
+    
+    fn get_job_metadata(&self, file_content: &str) -> JobMetadata {
+        // ";TIME:", ";Filament used:", ";LAYER_COUNT:" header comments
+        JobMetadata::from_cura(file_content)
+    }
 }
 
 impl Default for CuraSlicer {
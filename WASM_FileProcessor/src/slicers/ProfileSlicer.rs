@@ -0,0 +1,263 @@
+use crate::slicers::{SlicerBase, FeatureType, LayerInfo};
+use crate::gcode_line::Color4;
+use serde::{Deserialize, Serialize};
+
+/// A single feature entry in a user-defined profile (mirrors a TypeScript featureList entry)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProfileFeature {
+    /// Feature name as written after the comment prefix (matched case-insensitively)
+    pub name: String,
+    pub feature: FeatureType,
+    #[serde(default)]
+    pub color: Option<Color4>,
+    #[serde(default)]
+    pub perimeter: bool,
+    #[serde(default)]
+    pub support: bool,
+}
+
+/// Data-driven slicer description registered from JavaScript
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SlicerProfile {
+    pub name: String,
+    /// Any of these substrings in the file header selects this profile
+    pub signatures: Vec<String>,
+    #[serde(default = "default_comment_prefix")]
+    pub comment_prefix: String,
+    #[serde(default)]
+    pub features: Vec<ProfileFeature>,
+    #[serde(default)]
+    pub default_color: Option<Color4>,
+    /// Comment prefix followed by the layer number, e.g. ";LAYER:"
+    #[serde(default)]
+    pub layer_number_prefix: Option<String>,
+    /// Comment prefix followed by the layer Z height, e.g. ";Z:"
+    #[serde(default)]
+    pub layer_z_prefix: Option<String>,
+    #[serde(default = "default_layer_height")]
+    pub layer_height: f64,
+    /// Text preceding the version number, e.g. "; generated by MySlicer "
+    #[serde(default)]
+    pub version_prefix: Option<String>,
+}
+
+fn default_comment_prefix() -> String {
+    ";TYPE:".to_string()
+}
+
+fn default_layer_height() -> f64 {
+    0.2
+}
+
+impl SlicerProfile {
+    /// Check a profile is usable before it is registered
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Slicer profile name is empty".to_string());
+        }
+        if self.signatures.iter().all(|s| s.is_empty()) {
+            return Err(format!("Slicer profile '{}' has no detection signatures", self.name));
+        }
+        if self.comment_prefix.is_empty() {
+            return Err(format!("Slicer profile '{}' has an empty comment prefix", self.name));
+        }
+        Ok(())
+    }
+
+    /// Returns true if any signature appears in the header
+    pub fn matches(&self, header: &str) -> bool {
+        self.signatures.iter().any(|s| !s.is_empty() && header.contains(s.as_str()))
+    }
+}
+
+/// Slicer implementation driven entirely by a `SlicerProfile`
+pub struct ProfileSlicer {
+    profile: SlicerProfile,
+}
+
+impl ProfileSlicer {
+    pub fn new(profile: SlicerProfile) -> Self {
+        Self { profile }
+    }
+
+    fn find_feature(&self, comment: &str) -> Option<&ProfileFeature> {
+        let c = comment.trim();
+        let feature_name = c.strip_prefix(self.profile.comment_prefix.as_str())?.trim();
+        self.profile.features.iter().find(|f| f.name.eq_ignore_ascii_case(feature_name))
+    }
+
+    fn default_color(&self) -> Color4 {
+        self.profile.default_color.clone().unwrap_or_else(|| Color4::new(0.5, 0.5, 0.5, 1.0))
+    }
+}
+
+impl SlicerBase for ProfileSlicer {
+    fn get_feature_color(&self, feature: &FeatureType) -> Color4 {
+        // First entry mapped to this feature with an explicit color wins
+        self.profile.features.iter()
+            .filter(|f| &f.feature == feature)
+            .find_map(|f| f.color.clone())
+            .unwrap_or_else(|| self.default_color())
+    }
+
+    fn parse_feature_from_comment(&self, comment: &str) -> Option<FeatureType> {
+        self.find_feature(comment).map(|f| f.feature.clone())
+    }
+
+    fn parse_layer_info(&self, comment: &str) -> Option<LayerInfo> {
+        let c = comment.trim();
+
+        if let Some(prefix) = &self.profile.layer_number_prefix {
+            if let Some(rest) = c.strip_prefix(prefix.as_str()) {
+                if let Ok(layer_num) = rest.trim().parse::<u32>() {
+                    return Some(LayerInfo {
+                        layer_number: layer_num,
+                        layer_height: self.profile.layer_height,
+                        z_position: layer_num as f64 * self.profile.layer_height, // Estimate
+                    });
+                }
+            }
+        }
+
+        if let Some(prefix) = &self.profile.layer_z_prefix {
+            if let Some(rest) = c.strip_prefix(prefix.as_str()) {
+                if let Ok(z_pos) = rest.trim().parse::<f64>() {
+                    return Some(LayerInfo {
+                        layer_number: 0,
+                        layer_height: self.profile.layer_height,
+                        z_position: z_pos,
+                    });
+                }
+            }
+        }
+
+        None
+    }
+
+    fn is_perimeter_comment(&self, comment: &str) -> bool {
+        self.find_feature(comment).map(|f| f.perimeter).unwrap_or(false)
+    }
+
+    fn is_support_comment(&self, comment: &str) -> bool {
+        self.find_feature(comment).map(|f| f.support).unwrap_or(false)
+    }
+
+    fn get_temperature_from_comment(&self, _comment: &str) -> Option<f64> {
+        None
+    }
+
+    fn detect_slicer(_file_content: &str) -> bool where Self: Sized {
+        // Profiles are matched per instance through SlicerProfile::matches
+        false
+    }
+
+    fn get_name(&self) -> &str {
+        &self.profile.name
+    }
+
+    fn is_feature_comment(&self, comment: &str) -> bool {
+        comment.starts_with(self.profile.comment_prefix.as_str())
+    }
+
+    // Number markers are converted with the profile's layer height
+    fn layer_z_from_comment(&self, comment: &str) -> Option<f64> {
+        self.parse_layer_info(comment).map(|layer| layer.z_position)
+    }
+
+    fn get_version_info(&self, file_content: &str) -> Option<String> {
+        let prefix = self.profile.version_prefix.as_deref()?;
+        let start = file_content.find(prefix)?;
+        let version_line = &file_content[start + prefix.len()..];
+        let end = version_line.find(&[' ', '\n', '\r'][..]).unwrap_or(version_line.len());
+        let version = version_line[..end].trim();
+        if version.is_empty() {
+            None
+        } else {
+            Some(version.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::slicers::detect_slicer_with_profiles;
+
+    fn test_profile() -> SlicerProfile {
+        SlicerProfile {
+            name: "KiriMoto".to_string(),
+            signatures: vec!["; Generated by Kiri:Moto".to_string()],
+            comment_prefix: "; feature ".to_string(),
+            features: vec![
+                ProfileFeature {
+                    name: "shells".to_string(),
+                    feature: FeatureType::ExternalPerimeter,
+                    color: Some(Color4::new(1.0, 0.0, 0.0, 1.0)),
+                    perimeter: true,
+                    support: false,
+                },
+                ProfileFeature {
+                    name: "support".to_string(),
+                    feature: FeatureType::Support,
+                    color: None,
+                    perimeter: false,
+                    support: true,
+                },
+            ],
+            default_color: Some(Color4::new(0.2, 0.2, 0.2, 1.0)),
+            layer_number_prefix: Some(";; --- layer ".to_string()),
+            layer_z_prefix: None,
+            layer_height: 0.25,
+            version_prefix: Some("; Generated by Kiri:Moto ".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_profile_feature_parsing() {
+        let slicer = ProfileSlicer::new(test_profile());
+
+        assert_eq!(slicer.parse_feature_from_comment("; feature SHELLS"), Some(FeatureType::ExternalPerimeter));
+        assert_eq!(slicer.parse_feature_from_comment("; feature unknown"), None);
+        assert!(slicer.is_perimeter_comment("; feature shells"));
+        assert!(slicer.is_support_comment("; feature support"));
+
+        let color = slicer.get_feature_color(&FeatureType::ExternalPerimeter);
+        assert_eq!(color.r, 1.0);
+        let fallback = slicer.get_feature_color(&FeatureType::Support);
+        assert_eq!(fallback.r, 0.2);
+
+        let layer = slicer.parse_layer_info(";; --- layer 4").unwrap();
+        assert_eq!(layer.layer_number, 4);
+        assert!((layer.z_position - 1.0).abs() < 1e-9);
+        assert_eq!(slicer.layer_z_from_comment(";; --- layer 4"), Some(1.0));
+
+        // Processing routes comments by the profile's prefix, not ";TYPE:"
+        assert!(slicer.is_feature_comment("; feature shells"));
+        assert!(!slicer.is_feature_comment(";TYPE:Perimeter"));
+
+        let header = "; Generated by Kiri:Moto 4.1.2\nG28";
+        assert_eq!(slicer.get_version_info(header), Some("4.1.2".to_string()));
+    }
+
+    #[test]
+    fn test_profiles_take_precedence_over_builtins() {
+        let mut profile = test_profile();
+        profile.signatures.push("; generated by PrusaSlicer".to_string());
+
+        let content = "; generated by PrusaSlicer 2.6.0\nG28";
+        let slicer = detect_slicer_with_profiles(content, &[profile]);
+        assert_eq!(slicer.get_name(), "KiriMoto");
+
+        let slicer = detect_slicer_with_profiles(content, &[]);
+        assert_eq!(slicer.get_name(), "PrusaSlicer");
+    }
+
+    #[test]
+    fn test_profile_validation() {
+        let mut profile = test_profile();
+        assert!(profile.validate().is_ok());
+
+        profile.signatures.clear();
+        assert!(profile.validate().is_err());
+    }
+}
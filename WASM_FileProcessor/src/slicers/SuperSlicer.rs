@@ -1,5 +1,6 @@
 use crate::slicers::{SlicerBase, FeatureType, LayerInfo};
 use crate::gcode_line::Color4;
+use crate::slicers::job_metadata::JobMetadata;
 
 pub struct SuperSlicer {
     name: String,
@@ -117,6 +118,11 @@ impl SlicerBase for SuperSlicer {
         }
         None
     }
+    
+    fn get_job_metadata(&self, file_content: &str) -> JobMetadata {
+        // Estimates are written to the footer, objects_info to the header
+        JobMetadata::from_prusa_style(file_content)
+    }
 }
 
 impl Default for SuperSlicer {
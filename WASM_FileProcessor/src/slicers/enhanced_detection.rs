@@ -48,9 +48,9 @@ impl EnhancedSlicerDetector {
         }
     }
     
-    /// Process a comment line and extract slicer-specific information
-    pub fn process_comment(&mut self, comment: &CommentData) -> SlicerInfo {
-        let comment_text = &comment.comment_text;
+    /// Process a comment line (sliced from `source`) and extract slicer-specific information
+    pub fn process_comment(&mut self, comment: &CommentData, source: &str) -> SlicerInfo {
+        let comment_text = comment.comment_text(source);
         
         // Check for layer information
         if let Some(layer_info) = self.slicer.parse_layer_info(comment_text) {
@@ -287,8 +287,9 @@ mod tests {
         let content = "; generated by PrusaSlicer 2.6.0+win64\n; LAYER_CHANGE\n; Z:0.3\n; external perimeter";
         let mut detector = EnhancedSlicerDetector::new(content);
         
-        let comment = CommentData::new(0, 1, "; LAYER_CHANGE".to_string());
-        let info = detector.process_comment(&comment);
+        let line = "; LAYER_CHANGE";
+        let comment = CommentData::new(0, 1, line);
+        let info = detector.process_comment(&comment, line);
         
         assert!(matches!(info.info_type, SlicerInfoType::LayerChange));
         assert!(info.layer_info.is_some());
@@ -299,8 +300,9 @@ mod tests {
         let content = ";Generated with Cura_SteamEngine 5.4.0\n;TYPE:WALL-OUTER";
         let mut detector = EnhancedSlicerDetector::new(content);
         
-        let comment = CommentData::new(0, 1, ";TYPE:WALL-OUTER".to_string());
-        let info = detector.process_comment(&comment);
+        let line = ";TYPE:WALL-OUTER";
+        let comment = CommentData::new(0, 1, line);
+        let info = detector.process_comment(&comment, line);
         
         assert!(matches!(info.info_type, SlicerInfoType::FeatureChange));
         assert!(matches!(info.feature_type, Some(FeatureType::ExternalPerimeter)));
@@ -311,8 +313,9 @@ mod tests {
         let content = "; layer_height = 0.2\n; print_speed = 60";
         let mut detector = EnhancedSlicerDetector::new(content);
         
-        let comment = CommentData::new(0, 1, "; layer_height = 0.2".to_string());
-        let info = detector.process_comment(&comment);
+        let line = "; layer_height = 0.2";
+        let comment = CommentData::new(0, 1, line);
+        let info = detector.process_comment(&comment, line);
         
         assert!(matches!(info.info_type, SlicerInfoType::Setting));
         if let Some(setting) = info.setting {
@@ -0,0 +1,236 @@
+use crate::slicers::slicer_config::{head_slice, tail_slice};
+use serde::{Deserialize, Serialize};
+
+/// Bytes scanned at each end of the file; Prusa-style slicers write estimates in the footer
+pub const METADATA_SCAN_BYTES: usize = 256 * 1024;
+
+/// Print job estimates written by the slicer into header/footer comments
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct JobMetadata {
+    pub estimated_time_seconds: Option<f64>,
+    pub filament_used_mm: Vec<f64>, // Per extruder
+    pub filament_used_g: Vec<f64>,
+    pub filament_used_cm3: Vec<f64>,
+    pub filament_cost: Option<f64>,
+    pub layer_count: Option<u32>,
+    pub max_z: Option<f64>,
+    pub object_names: Vec<String>,
+}
+
+impl JobMetadata {
+    /// Head and tail regions that hold slicer metadata
+    pub fn scan_regions(file_content: &str) -> (&str, &str) {
+        let head = head_slice(file_content, METADATA_SCAN_BYTES);
+        if file_content.len() <= METADATA_SCAN_BYTES {
+            // Small file: the head already covers everything
+            return (head, "");
+        }
+        (head, tail_slice(file_content, METADATA_SCAN_BYTES))
+    }
+
+    /// Metadata in the "; key = value" style used by PrusaSlicer, SuperSlicer and OrcaSlicer
+    pub fn from_prusa_style(file_content: &str) -> JobMetadata {
+        let mut metadata = JobMetadata::default();
+        let (head, tail) = Self::scan_regions(file_content);
+
+        for line in head.lines().chain(tail.lines()) {
+            let line = line.trim();
+            let Some(comment) = line.strip_prefix(';') else {
+                metadata.read_object_marker(line);
+                continue;
+            };
+            let comment = comment.trim();
+
+            // "; printing object Shape-Box id:0 copy 0"
+            if let Some(name) = comment.strip_prefix("printing object ") {
+                metadata.add_object(name.split(" id:").next().unwrap_or(name));
+                continue;
+            }
+
+            // Orca: "; model printing time: 52m 3s; total estimated time: 58m 52s"; the total wins
+            if let Some((_, total)) = comment.split_once("total estimated time:") {
+                metadata.estimated_time_seconds = parse_duration(total.trim());
+                continue;
+            }
+
+            // Orca also writes "; total layer number: 50" and "; max_z_height: 10.2"
+            let (key, value) = match comment.split_once(" = ").or_else(|| comment.split_once(": ")) {
+                Some((k, v)) => (k.trim(), v.trim()),
+                None => continue,
+            };
+
+            match key {
+                "estimated printing time (normal mode)" | "model printing time"
+                    if metadata.estimated_time_seconds.is_none() => {
+                    metadata.estimated_time_seconds = parse_duration(value);
+                }
+                "filament used [mm]" => metadata.filament_used_mm = parse_number_list(value),
+                "filament used [g]" => metadata.filament_used_g = parse_number_list(value),
+                // Orca's total only when no per-extruder figures were written
+                "total filament used [g]" if metadata.filament_used_g.is_empty() => {
+                    metadata.filament_used_g = parse_number_list(value);
+                }
+                "filament used [cm3]" => metadata.filament_used_cm3 = parse_number_list(value),
+                "filament cost" | "total filament cost" => {
+                    let total: f64 = parse_number_list(value).iter().sum();
+                    metadata.filament_cost = Some(total);
+                }
+                "total layers count" | "total layer number" => {
+                    metadata.layer_count = value.parse::<u32>().ok();
+                }
+                "max_layer_z" | "max_z_height" => {
+                    metadata.max_z = value.parse::<f64>().ok();
+                }
+                "objects_info" => metadata.read_objects_info(value),
+                _ => {}
+            }
+        }
+
+        metadata
+    }
+
+    /// Cura header comments (";TIME:", ";Filament used:", ";LAYER_COUNT:", ";MAXZ:", ";MESH:")
+    pub fn from_cura(file_content: &str) -> JobMetadata {
+        let mut metadata = JobMetadata::default();
+        let (head, tail) = Self::scan_regions(file_content);
+
+        for line in head.lines().chain(tail.lines()) {
+            let line = line.trim();
+            if let Some(value) = line.strip_prefix(";TIME:") {
+                metadata.estimated_time_seconds = value.trim().parse::<f64>().ok();
+            } else if let Some(value) = line.strip_prefix(";Filament used:") {
+                // Meters per extruder: "5.12345m, 0m"
+                metadata.filament_used_mm = value.split(',')
+                    .filter_map(|v| v.trim().trim_end_matches('m').parse::<f64>().ok())
+                    .map(|meters| meters * 1000.0)
+                    .collect();
+            } else if let Some(value) = line.strip_prefix(";LAYER_COUNT:") {
+                metadata.layer_count = value.trim().parse::<u32>().ok();
+            } else if let Some(value) = line.strip_prefix(";MAXZ:") {
+                metadata.max_z = value.trim().parse::<f64>().ok();
+            } else if let Some(name) = line.strip_prefix(";MESH:") {
+                if name != "NONMESH" {
+                    metadata.add_object(name);
+                }
+            } else {
+                metadata.read_object_marker(line);
+            }
+        }
+
+        metadata
+    }
+
+    // Klipper object exclusion: "EXCLUDE_OBJECT_DEFINE NAME=part_1 CENTER=..."
+    fn read_object_marker(&mut self, line: &str) {
+        if let Some(rest) = line.strip_prefix("EXCLUDE_OBJECT_DEFINE ") {
+            if let Some(name_start) = rest.find("NAME=") {
+                let name = rest[name_start + 5..].split_whitespace().next().unwrap_or("");
+                self.add_object(name.trim_matches('\''));
+            }
+        }
+    }
+
+    // {"objects":[{"name":"Shape-Box","polygon":[...]}, ...]}
+    fn read_objects_info(&mut self, json: &str) {
+        let mut rest = json;
+        while let Some(start) = rest.find("\"name\":\"") {
+            let after = &rest[start + 8..];
+            let Some(end) = after.find('"') else { break };
+            self.add_object(&after[..end]);
+            rest = &after[end..];
+        }
+    }
+
+    fn add_object(&mut self, name: &str) {
+        let name = name.trim();
+        if !name.is_empty() && !self.object_names.iter().any(|n| n == name) {
+            self.object_names.push(name.to_string());
+        }
+    }
+}
+
+/// Parse slicer durations such as "1d 2h 3m 4s", "2h 5m" or "45s" into seconds
+pub fn parse_duration(value: &str) -> Option<f64> {
+    let mut total = 0.0;
+    let mut found = false;
+
+    for part in value.split_whitespace() {
+        let (number, unit) = part.split_at(part.find(|c: char| c.is_ascii_alphabetic())?);
+        let number = number.parse::<f64>().ok()?;
+        let multiplier = match unit {
+            "d" => 86400.0,
+            "h" => 3600.0,
+            "m" => 60.0,
+            "s" => 1.0,
+            _ => return None,
+        };
+        total += number * multiplier;
+        found = true;
+    }
+
+    if found { Some(total) } else { None }
+}
+
+fn parse_number_list(value: &str) -> Vec<f64> {
+    value.split([',', ';'])
+        .filter_map(|v| v.trim().parse::<f64>().ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("1h 2m 3s"), Some(3723.0));
+        assert_eq!(parse_duration("1d 0h 0m 5s"), Some(86405.0));
+        assert_eq!(parse_duration("45s"), Some(45.0));
+        assert_eq!(parse_duration("soon"), None);
+        assert_eq!(parse_duration(""), None);
+    }
+
+    #[test]
+    fn test_prusa_footer_metadata() {
+        let header = "; generated by PrusaSlicer 2.6.0\n\
+; objects_info = {\"objects\":[{\"name\":\"Shape-Box\",\"polygon\":[[0,0]]},{\"name\":\"Benchy\",\"polygon\":[[1,1]]}]}\n";
+        let body = "G1 X10 Y10 E1\n".repeat(30_000);
+        let footer = "; filament used [mm] = 1234.56, 10.0\n\
+; filament used [cm3] = 2.97\n\
+; filament used [g] = 3.68\n\
+; filament cost = 0.09\n\
+; total layers count = 120\n\
+; estimated printing time (normal mode) = 1h 2m 3s\n\
+; estimated printing time (silent mode) = 1h 10m 0s\n\
+; max_layer_z = 24.2\n";
+        let content = format!("{}{}{}", header, body, footer);
+
+        let metadata = JobMetadata::from_prusa_style(&content);
+        assert_eq!(metadata.estimated_time_seconds, Some(3723.0));
+        assert_eq!(metadata.filament_used_mm, vec![1234.56, 10.0]);
+        assert_eq!(metadata.filament_used_g, vec![3.68]);
+        assert_eq!(metadata.filament_used_cm3, vec![2.97]);
+        assert_eq!(metadata.filament_cost, Some(0.09));
+        assert_eq!(metadata.layer_count, Some(120));
+        assert_eq!(metadata.max_z, Some(24.2));
+        assert_eq!(metadata.object_names, vec!["Shape-Box".to_string(), "Benchy".to_string()]);
+
+        let orca = "; HEADER_BLOCK_START\n; model printing time: 52m 3s; total estimated time: 58m 52s\n; total layer number: 50\n";
+        let metadata = JobMetadata::from_prusa_style(orca);
+        assert_eq!(metadata.estimated_time_seconds, Some(58.0 * 60.0 + 52.0));
+        assert_eq!(metadata.layer_count, Some(50));
+    }
+
+    #[test]
+    fn test_cura_header_metadata() {
+        let content = ";FLAVOR:Marlin\n;TIME:3600\n;Filament used: 5.5m\n;Layer height: 0.2\n;MAXZ:10.2\n;LAYER_COUNT:50\n\
+;LAYER:0\n;MESH:cube.stl\nG1 X1 Y1 E1\n;MESH:NONMESH\n;MESH:cube.stl\n";
+
+        let metadata = JobMetadata::from_cura(content);
+        assert_eq!(metadata.estimated_time_seconds, Some(3600.0));
+        assert_eq!(metadata.filament_used_mm, vec![5500.0]);
+        assert_eq!(metadata.layer_count, Some(50));
+        assert_eq!(metadata.max_z, Some(10.2));
+        assert_eq!(metadata.object_names, vec!["cube.stl".to_string()]);
+    }
+}
@@ -5,6 +5,9 @@ pub mod CuraSlicer;
 pub mod OrcaSlicer;
 pub mod SuperSlicer;
 pub mod GenericSlicer;
+pub mod ProfileSlicer;
+pub mod slicer_config;
+pub mod job_metadata;
 
 pub use slicer_base::*;
 pub use enhanced_detection::*;
\ No newline at end of file
@@ -1,8 +1,11 @@
 use crate::gcode_line::Color4;
+use crate::slicers::ProfileSlicer::{ProfileSlicer, SlicerProfile};
+use crate::slicers::job_metadata::JobMetadata;
+use serde::{Deserialize, Serialize};
 use std::hash::Hash;
 
 /// Feature types that slicers can identify
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum FeatureType {
     Perimeter,
     ExternalPerimeter,
@@ -39,10 +42,31 @@ pub trait SlicerBase {
     fn detect_slicer(file_content: &str) -> bool where Self: Sized;
     fn get_name(&self) -> &str;
     fn get_version_info(&self, file_content: &str) -> Option<String>;
+    
+    /// True for comments that select a feature; processing passes only these to
+    /// `parse_feature_from_comment`. `comment` is trimmed.
+    fn is_feature_comment(&self, comment: &str) -> bool {
+        comment.starts_with(";TYPE:")
+    }
+    
+    /// Z height announced by a layer marker comment, for slicers whose markers carry one
+    fn layer_z_from_comment(&self, _comment: &str) -> Option<f64> {
+        None
+    }
+    
+    /// Slicer estimates (time, filament, layers, objects) from header/footer comments
+    fn get_job_metadata(&self, _file_content: &str) -> JobMetadata {
+        JobMetadata::default()
+    }
 }
 
 /// Detect slicer type from file content
 pub fn detect_slicer(file_content: &str) -> Box<dyn SlicerBase> {
+    detect_slicer_with_profiles(file_content, &[])
+}
+
+/// Detect slicer type, checking user-defined profiles (in registration order) before the built-ins
+pub fn detect_slicer_with_profiles(file_content: &str, profiles: &[SlicerProfile]) -> Box<dyn SlicerBase> {
     use crate::slicers::PrusaSlicer::PrusaSlicer;
     use crate::slicers::CuraSlicer::CuraSlicer;
     use crate::slicers::SuperSlicer::SuperSlicer;
@@ -56,6 +80,11 @@ pub fn detect_slicer(file_content: &str) -> Box<dyn SlicerBase> {
         file_content
     };
     
+    // User-defined profiles
+    if let Some(profile) = profiles.iter().find(|p| p.matches(header)) {
+        return Box::new(ProfileSlicer::new(profile.clone()));
+    }
+    
     // PrusaSlicer detection
     if PrusaSlicer::detect_slicer(header) {
         return Box::new(PrusaSlicer::new());
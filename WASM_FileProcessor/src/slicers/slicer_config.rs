@@ -0,0 +1,379 @@
+use crate::gcode_line::Color4;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Config blocks live at the start (Cura header) or end (Prusa/SuperSlicer/Orca) of the file
+const HEAD_SCAN_BYTES: usize = 64 * 1024;
+const TAIL_SCAN_BYTES: usize = 512 * 1024;
+
+/// Typed view of the slicer configuration embedded in a G-code file
+/// Per-extruder lists keep one slot per extruder; unset or unparseable entries are None
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SlicerConfig {
+    pub printer_model: Option<String>,
+    pub nozzle_diameters: Vec<Option<f64>>,
+    pub filament_diameters: Vec<Option<f64>>,
+    pub filament_densities: Vec<Option<f64>>,
+    pub filament_colors: Vec<Option<Color4>>,
+    pub extruder_colors: Vec<Option<Color4>>,
+    pub layer_height: Option<f64>,
+    pub first_layer_height: Option<f64>,
+    pub bed_shape: Vec<[f64; 2]>, // Bed outline points (X, Y)
+    pub max_print_height: Option<f64>,
+    /// Every key/value pair that was read, including those without a typed field
+    pub raw: HashMap<String, String>,
+}
+
+impl SlicerConfig {
+    /// Parse the embedded configuration from file content
+    /// Handles "; <slicer>_config = begin/end" blocks, Orca's CONFIG_BLOCK_START/END
+    /// and Cura's ";SETTING_3" serialized settings plus its header comments
+    pub fn parse(file_content: &str) -> SlicerConfig {
+        let mut config = SlicerConfig::default();
+
+        let head = head_slice(file_content, HEAD_SCAN_BYTES);
+        let tail = tail_slice(file_content, TAIL_SCAN_BYTES);
+
+        config.read_cura_header(head);
+
+        if let Some(block) = find_config_block(tail) {
+            for line in block.lines() {
+                if let Some((key, value)) = split_setting(line) {
+                    config.raw.insert(key.to_string(), value.to_string());
+                }
+            }
+        }
+
+        config.read_cura_settings(tail);
+        config.apply_raw_values();
+        config
+    }
+
+    /// Returns true when nothing was found
+    pub fn is_empty(&self) -> bool {
+        self.raw.is_empty()
+    }
+
+    /// Number of extruders described by the config
+    pub fn extruder_count(&self) -> usize {
+        self.nozzle_diameters.len()
+            .max(self.filament_diameters.len())
+            .max(self.filament_colors.len())
+            .max(self.extruder_colors.len())
+    }
+
+    /// Display color for an extruder: extruder color first, then filament color
+    pub fn tool_color(&self, index: usize) -> Option<Color4> {
+        self.extruder_colors.get(index).cloned().flatten()
+            .or_else(|| self.filament_colors.get(index).cloned().flatten())
+    }
+
+    // Cura header comments, e.g. ";EXTRUDER_TRAIN.0.NOZZLE.DIAMETER:0.4"
+    fn read_cura_header(&mut self, head: &str) {
+        for line in head.lines() {
+            let line = line.trim();
+            if let Some(name) = line.strip_prefix(";TARGET_MACHINE.NAME:") {
+                self.raw.insert("machine_name".to_string(), name.trim().to_string());
+            } else if let Some(height) = line.strip_prefix(";Layer height:") {
+                self.raw.insert("layer_height".to_string(), height.trim().to_string());
+            } else if let Some(rest) = line.strip_prefix(";EXTRUDER_TRAIN.") {
+                // "<index>.<KEY>:<value>"
+                let Some((path, value)) = rest.split_once(':') else { continue };
+                let Some((index, key)) = path.split_once('.') else { continue };
+                let Ok(index) = index.parse::<usize>() else { continue };
+                let key = match key {
+                    "NOZZLE.DIAMETER" => "machine_nozzle_size",
+                    "MATERIAL.DIAMETER" => "material_diameter",
+                    _ => continue,
+                };
+                let list_key = format!("{}#{}", key, index);
+                self.raw.insert(list_key, value.trim().to_string());
+            }
+        }
+    }
+
+    // Cura ";SETTING_3 " lines hold a JSON document split across lines; the values inside
+    // are INI sections with escaped newlines, so the key/value lines can be read directly
+    fn read_cura_settings(&mut self, tail: &str) {
+        let mut serialized = String::new();
+        for line in tail.lines() {
+            if let Some(fragment) = line.strip_prefix(";SETTING_3 ") {
+                serialized.push_str(fragment);
+            }
+        }
+        if serialized.is_empty() {
+            return;
+        }
+
+        let unescaped = serialized.replace("\\\\n", "\n").replace("\\n", "\n");
+        for line in unescaped.lines() {
+            let line = line.trim_matches(|c: char| c == '"' || c == ',' || c == '[' || c == ']' || c.is_whitespace());
+            if line.starts_with('[') || line.starts_with('{') {
+                continue;
+            }
+            if let Some((key, value)) = line.split_once(" = ") {
+                self.raw.entry(key.trim().to_string()).or_insert_with(|| value.trim().to_string());
+            }
+        }
+    }
+
+    // Map known keys from every supported slicer onto the typed fields
+    fn apply_raw_values(&mut self) {
+        self.printer_model = self.first_string(&["printer_model", "machine_name", "printer_settings_id"]);
+
+        self.nozzle_diameters = self.number_list(&["nozzle_diameter", "machine_nozzle_size"]);
+        self.filament_diameters = self.number_list(&["filament_diameter", "material_diameter"]);
+        self.filament_densities = self.number_list(&["filament_density", "material_density"]);
+        self.filament_colors = self.color_list(&["filament_colour", "material_color"]);
+        self.extruder_colors = self.color_list(&["extruder_colour"]);
+
+        self.layer_height = self.first_number(&["layer_height"]);
+        self.first_layer_height = self.first_number(&["first_layer_height", "initial_layer_print_height", "layer_height_0"]);
+        self.max_print_height = self.first_number(&["max_print_height", "printable_height", "machine_height"]);
+
+        self.bed_shape = self.first_string(&["bed_shape", "printable_area"])
+            .map(|shape| parse_bed_shape(&shape))
+            .unwrap_or_default();
+
+        if self.bed_shape.is_empty() {
+            // Cura only stores the bed size
+            let width = self.first_number(&["machine_width"]);
+            let depth = self.first_number(&["machine_depth"]);
+            if let (Some(w), Some(d)) = (width, depth) {
+                self.bed_shape = vec![[0.0, 0.0], [w, 0.0], [w, d], [0.0, d]];
+            }
+        }
+    }
+
+    fn first_string(&self, keys: &[&str]) -> Option<String> {
+        keys.iter()
+            .filter_map(|k| self.raw.get(*k))
+            .map(|v| v.trim_matches('"').trim().to_string())
+            .find(|v| !v.is_empty())
+    }
+
+    // Values can be a single list ("0.4,0.6") or per-extruder keys ("machine_nozzle_size#1").
+    // Entries keep their extruder index; a key with no usable entry falls through to the next.
+    fn number_list(&self, keys: &[&str]) -> Vec<Option<f64>> {
+        for key in keys {
+            if let Some(value) = self.raw.get(*key) {
+                let values = slots(split_list(value).map(|v| v.parse::<f64>().ok()));
+                if !values.is_empty() {
+                    return values;
+                }
+            }
+
+            let mut indexed = Vec::new();
+            while let Some(value) = self.raw.get(&format!("{}#{}", key, indexed.len())) {
+                indexed.push(value.trim().parse::<f64>().ok());
+            }
+            let indexed = slots(indexed);
+            if !indexed.is_empty() {
+                return indexed;
+            }
+        }
+        Vec::new()
+    }
+
+    fn first_number(&self, keys: &[&str]) -> Option<f64> {
+        self.number_list(keys).first().copied().flatten()
+    }
+
+    fn color_list(&self, keys: &[&str]) -> Vec<Option<Color4>> {
+        for key in keys {
+            if let Some(value) = self.raw.get(*key) {
+                let colors = slots(split_list(value).map(parse_hex_color));
+                if !colors.is_empty() {
+                    return colors;
+                }
+            }
+        }
+        Vec::new()
+    }
+}
+
+/// Parse "#RRGGBB" or "#RRGGBBAA" into a color
+pub fn parse_hex_color(value: &str) -> Option<Color4> {
+    let hex = value.trim().trim_matches('"').strip_prefix('#')?;
+    if hex.len() != 6 && hex.len() != 8 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok().map(|v| v as f64 / 255.0);
+    let alpha = if hex.len() == 8 { channel(6)? } else { 1.0 };
+    Some(Color4::new(channel(0)?, channel(2)?, channel(4)?, alpha))
+}
+
+// "0x0,250x0,250x210,0x210"
+fn parse_bed_shape(value: &str) -> Vec<[f64; 2]> {
+    split_list(value)
+        .filter_map(|point| {
+            let (x, y) = point.split_once('x')?;
+            Some([x.trim().parse::<f64>().ok()?, y.trim().parse::<f64>().ok()?])
+        })
+        .collect()
+}
+
+// Entries in order, blanks included so positions match extruder indices
+fn split_list(value: &str) -> impl Iterator<Item = &str> {
+    value.split([',', ';'])
+        .map(|v| v.trim().trim_matches('"').trim())
+}
+
+// Per-extruder slots without trailing unset entries; empty when nothing is set
+fn slots<T>(values: impl IntoIterator<Item = Option<T>>) -> Vec<Option<T>> {
+    let mut values: Vec<Option<T>> = values.into_iter().collect();
+    while matches!(values.last(), Some(None)) {
+        values.pop();
+    }
+    values
+}
+
+// "; key = value" -> (key, value)
+fn split_setting(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim().strip_prefix(';')?;
+    let (key, value) = line.split_once(" = ")?;
+    let key = key.trim();
+    if key.is_empty() || key.contains(' ') {
+        return None;
+    }
+    Some((key, value.trim()))
+}
+
+// Body of the last config block in the scanned text
+fn find_config_block(text: &str) -> Option<&str> {
+    if let Some(begin) = text.rfind("; CONFIG_BLOCK_START") {
+        let body = &text[begin..];
+        let end = body.find("; CONFIG_BLOCK_END").unwrap_or(body.len());
+        return Some(&body[..end]);
+    }
+
+    // "; prusaslicer_config = begin", "; SuperSlicer_config = begin", ...
+    let begin = text.rfind("_config = begin")?;
+    let body = &text[begin..];
+    let end = body.find("_config = end").unwrap_or(body.len());
+    Some(&body[..end])
+}
+
+pub(crate) fn head_slice(text: &str, max_bytes: usize) -> &str {
+    let mut end = text.len().min(max_bytes);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+pub(crate) fn tail_slice(text: &str, max_bytes: usize) -> &str {
+    let mut start = text.len().saturating_sub(max_bytes);
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    &text[start..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRUSA_FOOTER: &str = "G1 X10 Y10 E1\n\
+; prusaslicer_config = begin\n\
+; bed_shape = 0x0,250x0,250x210,0x210\n\
+; extruder_colour = \"\";\"\"\n\
+; filament_colour = #FF8000;#00FF00\n\
+; filament_density = 1.24,1.27\n\
+; filament_diameter = 1.75,2.85\n\
+; first_layer_height = 0.2\n\
+; layer_height = 0.15\n\
+; max_print_height = 220\n\
+; nozzle_diameter = 0.4,0.6\n\
+; printer_model = MK4\n\
+; prusaslicer_config = end\n";
+
+    #[test]
+    fn test_prusa_config_block() {
+        let config = SlicerConfig::parse(PRUSA_FOOTER);
+
+        assert_eq!(config.printer_model.as_deref(), Some("MK4"));
+        assert_eq!(config.nozzle_diameters, vec![Some(0.4), Some(0.6)]);
+        assert_eq!(config.filament_diameters, vec![Some(1.75), Some(2.85)]);
+        assert_eq!(config.filament_densities, vec![Some(1.24), Some(1.27)]);
+        assert_eq!(config.layer_height, Some(0.15));
+        assert_eq!(config.first_layer_height, Some(0.2));
+        assert_eq!(config.max_print_height, Some(220.0));
+        assert_eq!(config.bed_shape.len(), 4);
+        assert_eq!(config.bed_shape[2], [250.0, 210.0]);
+        assert_eq!(config.extruder_count(), 2);
+
+        // Empty extruder colors fall back to filament colors
+        let tool1 = config.tool_color(1).unwrap();
+        assert_eq!((tool1.r, tool1.g, tool1.b), (0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_unset_entries_keep_extruder_positions() {
+        let content = "; prusaslicer_config = begin\n\
+; extruder_colour = \"\";\"#FF8000\"\n\
+; filament_colour = #00FF00;#0000FF\n\
+; filament_diameter = abc,2.85\n\
+; nozzle_diameter = 0.4,0.6,\n\
+; prusaslicer_config = end\n";
+        let config = SlicerConfig::parse(content);
+
+        // Extruder 0 has no extruder color and falls back to its own filament color
+        let tool0 = config.tool_color(0).unwrap();
+        assert_eq!((tool0.r, tool0.g, tool0.b), (0.0, 1.0, 0.0));
+        let tool1 = config.tool_color(1).unwrap();
+        assert_eq!((tool1.r, tool1.g, tool1.b), (1.0, 128.0 / 255.0, 0.0));
+
+        assert_eq!(config.filament_diameters, vec![None, Some(2.85)]);
+        assert_eq!(config.nozzle_diameters, vec![Some(0.4), Some(0.6)]);
+        assert_eq!(config.extruder_count(), 2);
+
+        let mut properties = crate::processor_properties::ProcessorProperties::new();
+        properties.apply_slicer_config(&config);
+        assert_eq!(properties.tools[0].filament_diameter, 1.75); // Default kept
+        assert_eq!(properties.tools[1].filament_diameter, 2.85);
+    }
+
+    #[test]
+    fn test_tools_replaced_per_file() {
+        let mut properties = crate::processor_properties::ProcessorProperties::new();
+        properties.apply_slicer_config(&SlicerConfig::parse(PRUSA_FOOTER));
+        properties.set_current_tool(3);
+        assert_eq!(properties.tools.len(), 3);
+        assert_eq!(properties.tools[1].filament_diameter, 2.85);
+
+        properties.reset();
+        properties.apply_slicer_config(&SlicerConfig::parse(PRUSA_FOOTER));
+        assert_eq!(properties.tools.iter().map(|t| t.tool_number).collect::<Vec<_>>(), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_cura_settings() {
+        let content = ";FLAVOR:Marlin\n\
+;TARGET_MACHINE.NAME:Creality Ender-3\n\
+;EXTRUDER_TRAIN.0.NOZZLE.DIAMETER:0.4\n\
+;EXTRUDER_TRAIN.0.MATERIAL.DIAMETER:1.75\n\
+;Layer height: 0.2\n\
+G28\n\
+;SETTING_3 {\"global_quality\": \"[general]\\\\nversion = 4\\\\n[values]\\\\nlayer_height_0 = 0.3\\\\nmachine_wid\n\
+;SETTING_3 th = 235\\\\nmachine_depth = 220\\\\n\"}\n";
+
+        let config = SlicerConfig::parse(content);
+
+        assert_eq!(config.printer_model.as_deref(), Some("Creality Ender-3"));
+        assert_eq!(config.nozzle_diameters, vec![Some(0.4)]);
+        assert_eq!(config.filament_diameters, vec![Some(1.75)]);
+        assert_eq!(config.layer_height, Some(0.2));
+        assert_eq!(config.first_layer_height, Some(0.3));
+        assert_eq!(config.bed_shape[2], [235.0, 220.0]);
+    }
+
+    #[test]
+    fn test_parse_hex_color() {
+        let color = parse_hex_color("#FF000080").unwrap();
+        assert_eq!(color.r, 1.0);
+        assert!((color.a - 128.0 / 255.0).abs() < 1e-9);
+        assert!(parse_hex_color("\"\"").is_none());
+        assert!(parse_hex_color("#12").is_none());
+    }
+}
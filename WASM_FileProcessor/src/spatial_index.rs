@@ -0,0 +1,477 @@
+// Bounding volume hierarchy over segments for CPU-side picking
+// Segments are treated as capsules of `radius` around their centerline. Nodes are stored flat;
+// a leaf references a run of `order`, which holds segment indices sorted by the build.
+
+use wasm_bindgen::prelude::*;
+use crate::segment_store::SegmentStore;
+
+const LEAF_SIZE: usize = 4;
+
+#[derive(Clone, Copy, Debug)]
+struct Aabb {
+    min: [f32; 3],
+    max: [f32; 3],
+}
+
+impl Aabb {
+    fn empty() -> Self {
+        Aabb { min: [f32::INFINITY; 3], max: [f32::NEG_INFINITY; 3] }
+    }
+
+    fn grow(&mut self, other: &Aabb) {
+        for axis in 0..3 {
+            self.min[axis] = self.min[axis].min(other.min[axis]);
+            self.max[axis] = self.max[axis].max(other.max[axis]);
+        }
+    }
+
+    fn center(&self, axis: usize) -> f32 {
+        (self.min[axis] + self.max[axis]) * 0.5
+    }
+
+    fn intersects(&self, other: &Aabb) -> bool {
+        (0..3).all(|axis| self.min[axis] <= other.max[axis] && self.max[axis] >= other.min[axis])
+    }
+
+    fn distance_sq(&self, point: [f32; 3]) -> f32 {
+        (0..3)
+            .map(|axis| {
+                let d = (self.min[axis] - point[axis]).max(0.0).max(point[axis] - self.max[axis]);
+                d * d
+            })
+            .sum()
+    }
+
+    // Entry distance of a ray (with precomputed inverse direction), or None if it misses
+    fn ray_entry(&self, origin: [f32; 3], inverse_dir: [f32; 3], max_t: f32) -> Option<f32> {
+        let mut t_min = 0.0f32;
+        let mut t_max = max_t;
+        for axis in 0..3 {
+            let t1 = (self.min[axis] - origin[axis]) * inverse_dir[axis];
+            let t2 = (self.max[axis] - origin[axis]) * inverse_dir[axis];
+            // NaN (origin on a slab plane of a parallel ray) leaves the bounds unchanged
+            t_min = t_min.max(t1.min(t2));
+            t_max = t_max.min(t1.max(t2));
+        }
+        (t_min <= t_max).then_some(t_min)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    bounds: Aabb,
+    first: u32, // Leaf: start in `order`; inner: index of the left child (right is left + 1)
+    count: u32, // Leaf: number of segments; 0 for inner nodes
+}
+
+/// Result of a spatial query
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct SegmentHit {
+    segment_index: u32,
+    file_position: u32,
+    line_number: u32,
+    distance: f32, // Ray: distance along the ray; nearest: distance from the query point
+    point: [f32; 3], // Closest point on the segment centerline
+}
+
+#[wasm_bindgen]
+impl SegmentHit {
+    #[wasm_bindgen(getter)]
+    pub fn segment_index(&self) -> u32 {
+        self.segment_index
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn file_position(&self) -> u32 {
+        self.file_position
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn line_number(&self) -> u32 {
+        self.line_number
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn point(&self) -> Vec<f32> {
+        self.point.to_vec()
+    }
+}
+
+pub struct SpatialIndex {
+    nodes: Vec<Node>,
+    order: Vec<u32>,
+    starts: Vec<[f32; 3]>, // Indexed like the segment store
+    ends: Vec<[f32; 3]>,
+    file_positions: Vec<u32>,
+    line_numbers: Vec<u32>,
+    radius: f32,
+}
+
+impl SpatialIndex {
+    /// Index the segments; travel moves are skipped unless `include_travel` is set
+    pub fn build(segments: &SegmentStore, radius: f32, include_travel: bool) -> Self {
+        let count = segments.len();
+        let mut index = SpatialIndex {
+            nodes: Vec::new(),
+            order: Vec::with_capacity(count),
+            starts: Vec::with_capacity(count),
+            ends: Vec::with_capacity(count),
+            file_positions: Vec::with_capacity(count),
+            line_numbers: Vec::with_capacity(count),
+            radius: radius.max(0.0),
+        };
+
+        let mut bounds = Vec::with_capacity(count);
+        for (i, segment) in segments.iter().enumerate() {
+            index.starts.push(segment.start);
+            index.ends.push(segment.end);
+            index.file_positions.push(segment.file_position);
+            index.line_numbers.push(segment.line_number);
+            bounds.push(index.segment_bounds(i));
+            if include_travel || segment.extruding() {
+                index.order.push(i as u32);
+            }
+        }
+
+        if !index.order.is_empty() {
+            index.nodes.push(Node { bounds: Aabb::empty(), first: 0, count: 0 });
+            index.subdivide(0, 0, index.order.len(), &bounds);
+        }
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    fn segment_bounds(&self, i: usize) -> Aabb {
+        let (start, end) = (self.starts[i], self.ends[i]);
+        let mut bounds = Aabb::empty();
+        for axis in 0..3 {
+            bounds.min[axis] = start[axis].min(end[axis]) - self.radius;
+            bounds.max[axis] = start[axis].max(end[axis]) + self.radius;
+        }
+        bounds
+    }
+
+    // Median split on the longest axis of the centroid bounds
+    fn subdivide(&mut self, node: usize, first: usize, count: usize, bounds: &[Aabb]) {
+        let mut node_bounds = Aabb::empty();
+        let mut centroids = Aabb::empty();
+        for &i in &self.order[first..first + count] {
+            let b = &bounds[i as usize];
+            node_bounds.grow(b);
+            let c = [b.center(0), b.center(1), b.center(2)];
+            centroids.grow(&Aabb { min: c, max: c });
+        }
+        self.nodes[node].bounds = node_bounds;
+
+        let extent = [0, 1, 2].map(|axis| centroids.max[axis] - centroids.min[axis]);
+        let axis = (0..3).max_by(|&a, &b| extent[a].total_cmp(&extent[b])).unwrap_or(0);
+        if count <= LEAF_SIZE || extent[axis] <= 0.0 {
+            self.nodes[node].first = first as u32;
+            self.nodes[node].count = count as u32;
+            return;
+        }
+
+        let half = count / 2;
+        self.order[first..first + count].select_nth_unstable_by(half, |&a, &b| {
+            bounds[a as usize].center(axis).total_cmp(&bounds[b as usize].center(axis))
+        });
+
+        let left = self.nodes.len();
+        self.nodes.push(Node { bounds: Aabb::empty(), first: 0, count: 0 });
+        self.nodes.push(Node { bounds: Aabb::empty(), first: 0, count: 0 });
+        self.nodes[node].first = left as u32;
+        self.subdivide(left, first, half, bounds);
+        self.subdivide(left + 1, first + half, count - half, bounds);
+    }
+
+    fn hit(&self, i: u32, distance: f32, point: [f32; 3]) -> SegmentHit {
+        SegmentHit {
+            segment_index: i,
+            file_position: self.file_positions[i as usize],
+            line_number: self.line_numbers[i as usize],
+            distance,
+            point,
+        }
+    }
+
+    /// First segment whose capsule the ray passes through (closest along the ray)
+    pub fn pick_ray(&self, origin: [f32; 3], direction: [f32; 3]) -> Option<SegmentHit> {
+        let length = dot(direction, direction).sqrt();
+        if self.is_empty() || length <= f32::EPSILON {
+            return None;
+        }
+        let dir = direction.map(|v| v / length);
+        let inverse_dir = dir.map(|v| 1.0 / v);
+
+        let mut best: Option<SegmentHit> = None;
+        let mut stack = vec![0usize];
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            let max_t = best.map_or(f32::INFINITY, |hit| hit.distance);
+            if node.bounds.ray_entry(origin, inverse_dir, max_t).is_none() {
+                continue;
+            }
+            if node.count == 0 {
+                stack.push(node.first as usize);
+                stack.push(node.first as usize + 1);
+                continue;
+            }
+            for &i in &self.order[node.first as usize..(node.first + node.count) as usize] {
+                let (t, point, distance_sq) = ray_segment_closest(origin, dir, self.starts[i as usize], self.ends[i as usize]);
+                if distance_sq <= self.radius * self.radius && best.is_none_or(|hit| t < hit.distance) {
+                    best = Some(self.hit(i, t, point));
+                }
+            }
+        }
+        best
+    }
+
+    /// Segment closest to `point` within `max_distance`
+    pub fn nearest(&self, point: [f32; 3], max_distance: f32) -> Option<SegmentHit> {
+        if self.is_empty() {
+            return None;
+        }
+        let mut best: Option<SegmentHit> = None;
+        let mut best_sq = max_distance * max_distance;
+        let mut stack = vec![0usize];
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            if node.bounds.distance_sq(point) > best_sq {
+                continue;
+            }
+            if node.count == 0 {
+                // Visit the nearer child first
+                let (left, right) = (node.first as usize, node.first as usize + 1);
+                if self.nodes[left].bounds.distance_sq(point) < self.nodes[right].bounds.distance_sq(point) {
+                    stack.push(right);
+                    stack.push(left);
+                } else {
+                    stack.push(left);
+                    stack.push(right);
+                }
+                continue;
+            }
+            for &i in &self.order[node.first as usize..(node.first + node.count) as usize] {
+                let closest = point_segment_closest(point, self.starts[i as usize], self.ends[i as usize]);
+                let d = sub(point, closest);
+                let distance_sq = dot(d, d);
+                if distance_sq <= best_sq {
+                    best_sq = distance_sq;
+                    best = Some(self.hit(i, distance_sq.sqrt(), closest));
+                }
+            }
+        }
+        best
+    }
+
+    /// Indices (ascending) of segments whose centerline passes through the box
+    pub fn query_box(&self, min: [f32; 3], max: [f32; 3]) -> Vec<u32> {
+        let query = Aabb { min, max };
+        let mut result = Vec::new();
+        if self.is_empty() {
+            return result;
+        }
+        let mut stack = vec![0usize];
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            if !node.bounds.intersects(&query) {
+                continue;
+            }
+            if node.count == 0 {
+                stack.push(node.first as usize);
+                stack.push(node.first as usize + 1);
+                continue;
+            }
+            for &i in &self.order[node.first as usize..(node.first + node.count) as usize] {
+                if segment_intersects_box(self.starts[i as usize], self.ends[i as usize], &query) {
+                    result.push(i);
+                }
+            }
+        }
+        result.sort_unstable();
+        result
+    }
+}
+
+// Closest approach of a ray (unit direction) and a segment: (ray t, point on segment, squared distance)
+fn ray_segment_closest(origin: [f32; 3], dir: [f32; 3], start: [f32; 3], end: [f32; 3]) -> (f32, [f32; 3], f32) {
+    let edge = sub(end, start);
+    let offset = sub(origin, start);
+    let a = dot(edge, edge);
+    let b = dot(edge, dir);
+    let c = dot(edge, offset);
+    let e = dot(dir, offset);
+
+    // Parameter s along the segment, t along the ray
+    let denominator = a - b * b;
+    let mut s = if a <= f32::EPSILON {
+        0.0
+    } else if denominator.abs() > 1e-9 {
+        ((c - b * e) / denominator).clamp(0.0, 1.0)
+    } else {
+        0.0 // Parallel: any point works, refined below
+    };
+    let mut t = (b * s - e).max(0.0);
+    if a > f32::EPSILON {
+        // Re-project after clamping t to the ray
+        s = ((t * b + c) / a).clamp(0.0, 1.0);
+        t = (b * s - e).max(0.0);
+    }
+
+    let on_segment = [start[0] + edge[0] * s, start[1] + edge[1] * s, start[2] + edge[2] * s];
+    let on_ray = [origin[0] + dir[0] * t, origin[1] + dir[1] * t, origin[2] + dir[2] * t];
+    let d = sub(on_ray, on_segment);
+    (t, on_segment, dot(d, d))
+}
+
+fn point_segment_closest(point: [f32; 3], start: [f32; 3], end: [f32; 3]) -> [f32; 3] {
+    let edge = sub(end, start);
+    let length_sq = dot(edge, edge);
+    if length_sq <= f32::EPSILON {
+        return start;
+    }
+    let s = (dot(sub(point, start), edge) / length_sq).clamp(0.0, 1.0);
+    [start[0] + edge[0] * s, start[1] + edge[1] * s, start[2] + edge[2] * s]
+}
+
+// Slab clipping of the segment against the box
+fn segment_intersects_box(start: [f32; 3], end: [f32; 3], query: &Aabb) -> bool {
+    let mut t_min = 0.0f32;
+    let mut t_max = 1.0f32;
+    for axis in 0..3 {
+        let d = end[axis] - start[axis];
+        if d.abs() <= f32::EPSILON {
+            if start[axis] < query.min[axis] || start[axis] > query.max[axis] {
+                return false;
+            }
+            continue;
+        }
+        let t1 = (query.min[axis] - start[axis]) / d;
+        let t2 = (query.max[axis] - start[axis]) / d;
+        t_min = t_min.max(t1.min(t2));
+        t_max = t_max.min(t1.max(t2));
+        if t_min > t_max {
+            return false;
+        }
+    }
+    true
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segment_store::{Segment, FLAG_EXTRUDING};
+
+    // 20x20 grid of short extrusions on two layers plus one travel move
+    fn store() -> SegmentStore {
+        let mut store = SegmentStore::new();
+        let mut position = 0;
+        for (layer, height) in [0.2f32, 0.4].iter().enumerate() {
+            for row in 0..20 {
+                for column in 0..20 {
+                    let x = column as f32;
+                    let z = row as f32;
+                    store.push(Segment {
+                        start: [x, *height, z],
+                        end: [x + 0.8, *height, z],
+                        feed_rate: 1800.0,
+                        layer_height: 0.2,
+                        width: 0.4,
+                        color: 0,
+                        flags: FLAG_EXTRUDING,
+                        tool: 0,
+                        feature: 0,
+                        layer: layer as u32,
+                        line_number: position + 1,
+                        file_position: position * 20,
+                        file_end_position: position * 20 + 19,
+                    });
+                    position += 1;
+                }
+            }
+        }
+        store.push(Segment {
+            start: [0.0, 5.0, 0.0],
+            end: [50.0, 5.0, 50.0],
+            feed_rate: 9000.0,
+            layer_height: 0.2,
+            width: 0.4,
+            color: 0,
+            flags: 0,
+            tool: 0,
+            feature: 0,
+            layer: 1,
+            line_number: 9999,
+            file_position: 999_999,
+            file_end_position: 1_000_010,
+        });
+        store
+    }
+
+    #[test]
+    fn test_pick_ray_hits_top_layer_first() {
+        let store = store();
+        let index = SpatialIndex::build(&store, 0.2, false);
+        assert_eq!(index.len(), 800);
+
+        // Straight down onto (5.4, z = 7): the layer at 0.4 is hit before the one at 0.2
+        let hit = index.pick_ray([5.4, 10.0, 7.0], [0.0, -1.0, 0.0]).unwrap();
+        assert_eq!(hit.segment_index, 400 + 7 * 20 + 5);
+        assert!((hit.distance - 9.6).abs() < 0.25);
+
+        // Between segments (gap from x = 5.8 to 6.0 is narrower than the radius) still hits
+        assert!(index.pick_ray([5.9, 10.0, 7.0], [0.0, -1.0, 0.0]).is_some());
+        // Far outside the grid
+        assert!(index.pick_ray([100.0, 10.0, 100.0], [0.0, -1.0, 0.0]).is_none());
+        // Travel moves are excluded unless requested
+        let with_travel = SpatialIndex::build(&store, 0.2, true);
+        assert_eq!(with_travel.pick_ray([30.0, 10.0, 30.0], [0.0, -1.0, 0.0]).unwrap().line_number, 9999);
+    }
+
+    #[test]
+    fn test_nearest_and_box_queries_match_brute_force() {
+        let store = store();
+        let index = SpatialIndex::build(&store, 0.2, false);
+
+        for point in [[3.3, 0.25, 4.1], [19.9, 1.0, -2.0], [10.0, 0.45, 10.5]] {
+            let hit = index.nearest(point, f32::INFINITY).unwrap();
+            let brute = (0..800)
+                .map(|i| {
+                    let s = store.get(i);
+                    let d = sub(point, point_segment_closest(point, s.start, s.end));
+                    dot(d, d).sqrt()
+                })
+                .fold(f32::INFINITY, f32::min);
+            assert!((hit.distance - brute).abs() < 1e-5);
+        }
+        assert!(index.nearest([100.0, 0.0, 100.0], 1.0).is_none());
+
+        // Box around columns 2..=3 of rows 4..=5 on the top layer only
+        let found = index.query_box([2.5, 0.3, 3.9], [3.5, 0.5, 5.1]);
+        let expected: Vec<u32> = [4, 5].iter()
+            .flat_map(|row| [2, 3].map(|column| 400 + row * 20 + column))
+            .collect();
+        assert_eq!(found, expected);
+    }
+}
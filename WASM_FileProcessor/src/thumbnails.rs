@@ -0,0 +1,237 @@
+use crate::utils::decode_base64;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+/// Preview image embedded by the slicer
+#[wasm_bindgen]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Thumbnail {
+    format: String, // "PNG", "JPG" or "QOI"
+    width: u32,
+    height: u32,
+    bytes: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl Thumbnail {
+    #[wasm_bindgen(getter)]
+    pub fn format(&self) -> String { self.format.clone() }
+
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> u32 { self.width }
+
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> u32 { self.height }
+
+    /// Decoded image file bytes (copied into a Uint8Array)
+    #[wasm_bindgen(getter)]
+    pub fn bytes(&self) -> Vec<u8> { self.bytes.clone() }
+
+    /// MIME type for building a Blob / data URL
+    #[wasm_bindgen(getter)]
+    pub fn mime_type(&self) -> String {
+        match self.format.as_str() {
+            "JPG" => "image/jpeg",
+            "QOI" => "image/qoi",
+            _ => "image/png",
+        }.to_string()
+    }
+}
+
+impl Thumbnail {
+    pub fn new(format: &str, width: u32, height: u32, bytes: Vec<u8>) -> Self {
+        Self { format: format.to_string(), width, height, bytes }
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+// Block currently being read
+struct PendingThumbnail {
+    format: &'static str,
+    width: u32,
+    height: u32,
+    length: usize, // Declared base64 length (0 when missing)
+    base64: Vec<u8>,
+}
+
+impl PendingThumbnail {
+    fn complete(&self) -> bool {
+        self.length > 0 && self.base64.len() >= self.length
+    }
+
+    fn decode(self) -> Option<Thumbnail> {
+        decode_base64(&self.base64).ok().map(|decoded| Thumbnail::new(self.format, self.width, self.height, decoded))
+    }
+}
+
+/// Collects "; thumbnail begin WxH len" ... "; thumbnail end" blocks while lines stream past
+/// Also handles the "; thumbnail_JPG begin" / "; thumbnail_QOI begin" variants
+#[derive(Default)]
+pub struct ThumbnailCollector {
+    pending: Option<PendingThumbnail>,
+    thumbnails: Vec<Thumbnail>,
+}
+
+impl ThumbnailCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a raw line; returns true if the line belonged to a thumbnail block
+    /// and needs no further parsing. A block without its end marker stops at the declared
+    /// length, or is dropped at the first line that isn't a base64 comment.
+    pub fn feed_line(&mut self, line: &str) -> bool {
+        let bytes = line.as_bytes();
+
+        // Every thumbnail line is a comment - reject everything else on the first byte
+        if bytes.first() != Some(&b';') {
+            if self.pending.is_some() && !line.trim().is_empty() {
+                self.close_pending();
+            }
+            return false;
+        }
+
+        let comment = line[1..].trim();
+
+        if let Some(pending) = self.pending.as_mut() {
+            if is_marker(comment, "end") {
+                let pending = self.pending.take().unwrap();
+                self.thumbnails.extend(pending.decode());
+                return true;
+            }
+            if !pending.complete() && comment.bytes().all(is_base64_byte) {
+                pending.base64.extend_from_slice(comment.as_bytes());
+                return true;
+            }
+            // Missing end marker: this line is ordinary content again
+            self.close_pending();
+        }
+
+        if !comment.starts_with("thumbnail") || !is_marker(comment, "begin") {
+            return false;
+        }
+
+        // "thumbnail[_FMT] begin WxH length"
+        let mut parts = comment.split_whitespace();
+        let tag = parts.next().unwrap_or("");
+        let format = match tag {
+            "thumbnail_JPG" => "JPG",
+            "thumbnail_QOI" => "QOI",
+            _ => "PNG",
+        };
+        let size = parts.nth(1).unwrap_or("");
+        let (width, height) = size.split_once('x')
+            .and_then(|(w, h)| Some((w.parse::<u32>().ok()?, h.parse::<u32>().ok()?)))
+            .unwrap_or((0, 0));
+        let length = parts.next().and_then(|len| len.parse::<usize>().ok()).unwrap_or(0);
+
+        self.pending = Some(PendingThumbnail {
+            format,
+            width,
+            height,
+            length,
+            base64: Vec::with_capacity(length),
+        });
+        true
+    }
+
+    // Keep an unterminated block that reached its declared length, drop a truncated one
+    fn close_pending(&mut self) {
+        if let Some(pending) = self.pending.take() {
+            if pending.complete() {
+                self.thumbnails.extend(pending.decode());
+            }
+        }
+    }
+
+    /// Finished thumbnails; an unterminated block is discarded
+    pub fn finish(self) -> Vec<Thumbnail> {
+        self.thumbnails
+    }
+}
+
+fn is_base64_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'+' | b'/' | b'=')
+}
+
+// "thumbnail begin", "thumbnail_JPG end", ...
+fn is_marker(comment: &str, marker: &str) -> bool {
+    let mut parts = comment.split_whitespace();
+    matches!(parts.next(), Some(tag) if tag.starts_with("thumbnail")) && parts.next() == Some(marker)
+}
+
+/// Scan file content for thumbnails without running the G-code parser
+pub fn extract_thumbnails(file_content: &str) -> Vec<Thumbnail> {
+    let mut collector = ThumbnailCollector::new();
+    for line in file_content.lines() {
+        collector.feed_line(line);
+    }
+    collector.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT: &str = "; generated by PrusaSlicer 2.6.0\n\
+;\n\
+; thumbnail begin 2x2 12\n\
+; iVBORw0K\n\
+; GgoA\n\
+; thumbnail end\n\
+;\n\
+; thumbnail_JPG begin 16x12 4\n\
+; /9j/\n\
+; thumbnail_JPG end\n\
+G28\n\
+G1 X10 Y10 E1\n";
+
+    #[test]
+    fn test_extract_thumbnails() {
+        let thumbnails = extract_thumbnails(CONTENT);
+        assert_eq!(thumbnails.len(), 2);
+
+        assert_eq!(thumbnails[0].format(), "PNG");
+        assert_eq!((thumbnails[0].width(), thumbnails[0].height()), (2, 2));
+        assert_eq!(&thumbnails[0].data()[..4], &[0x89, b'P', b'N', b'G']);
+
+        assert_eq!(thumbnails[1].format(), "JPG");
+        assert_eq!(thumbnails[1].mime_type(), "image/jpeg");
+        assert_eq!(thumbnails[1].data(), &[0xFF, 0xD8, 0xFF]);
+    }
+
+    #[test]
+    fn test_collector_consumes_only_thumbnail_lines() {
+        let mut collector = ThumbnailCollector::new();
+        assert!(!collector.feed_line("; generated by PrusaSlicer"));
+        assert!(collector.feed_line("; thumbnail begin 2x2 12"));
+        assert!(collector.pending.is_some());
+        assert!(collector.feed_line("; iVBORw0KGgoA"));
+        assert!(collector.feed_line("; thumbnail end"));
+        assert!(collector.pending.is_none());
+        assert!(!collector.feed_line("G1 X10"));
+        assert_eq!(collector.finish().len(), 1);
+    }
+
+    #[test]
+    fn test_unterminated_blocks_release_later_comments() {
+        // Declared length reached without an end marker: the image is kept
+        let mut collector = ThumbnailCollector::new();
+        assert!(collector.feed_line("; thumbnail begin 2x2 12"));
+        assert!(collector.feed_line("; iVBORw0KGgoA"));
+        assert!(!collector.feed_line(";TYPE:External perimeter"));
+        assert_eq!(collector.finish().len(), 1);
+
+        // Truncated block: dropped at the first comment that isn't base64
+        let mut collector = ThumbnailCollector::new();
+        assert!(collector.feed_line("; thumbnail begin 16x16 400"));
+        assert!(collector.feed_line("; iVBORw0K"));
+        assert!(!collector.feed_line(";TYPE:External perimeter"));
+        assert!(collector.pending.is_none());
+        assert!(!collector.feed_line(";LAYER_CHANGE"));
+        assert!(collector.finish().is_empty());
+    }
+}
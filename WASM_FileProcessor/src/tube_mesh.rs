@@ -0,0 +1,525 @@
+// Continuous tube meshes swept along extrusion paths
+// Consecutive connected extrusions of one layer and color form a path. A ring of vertices (the
+// cross-section) is placed at every path point and shared by both adjacent segments, so corners
+// have no gaps or overlaps and normals are smooth. Output is indexed and chunked by layer range.
+//
+// Segment IDs are per vertex. Every triangle's last vertex (the WebGL provoking vertex) carries
+// the pick ID of the segment the triangle belongs to, so read IDs with `flat` interpolation.
+// IDs match `pick_id_data` of the render buffers: segment index + 1, with 0 left for the background.
+
+use wasm_bindgen::prelude::*;
+use crate::pick_id::pick_id;
+use crate::segment_store::{Segment, SegmentStore};
+
+// Endpoints closer than this are treated as connected
+const CONTINUITY_EPSILON: f32 = 1e-4;
+const MIN_SEGMENT_LENGTH: f32 = 1e-5;
+// Joints turning less than this (radians) are always mitred
+const ROUND_JOINT_MIN_ANGLE: f32 = 0.17;
+const ROUND_JOINT_STEP: f32 = std::f32::consts::FRAC_PI_8;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TubeCrossSection {
+    Ellipse = 0,
+    RoundedRectangle = 1, // Flat top and bottom with semicircular sides, like a squashed extrusion
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TubeJoint {
+    Miter = 0,
+    Round = 1,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TubeMeshSettings {
+    pub cross_section: TubeCrossSection,
+    pub joint: TubeJoint,
+    pub width: f32,
+    pub sides: u32,            // Vertices per ring (rounded rectangles round up to 4n + 2)
+    pub layers_per_chunk: u32,
+    pub max_miter_scale: f32,  // Sharper mitres fall back to a rounded elbow
+}
+
+impl Default for TubeMeshSettings {
+    fn default() -> Self {
+        TubeMeshSettings {
+            cross_section: TubeCrossSection::RoundedRectangle,
+            joint: TubeJoint::Miter,
+            width: 0.4,
+            sides: 10,
+            layers_per_chunk: 10,
+            max_miter_scale: 3.0,
+        }
+    }
+}
+
+/// Indexed mesh for a range of layers
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct TubeMesh {
+    positions: Vec<f32>, // xyz per vertex
+    normals: Vec<f32>,   // xyz per vertex
+    colors: Vec<f32>,    // RGBA per vertex
+    ids: Vec<u32>,       // Pick ID per vertex
+    indices: Vec<u32>,
+    first_layer: u32,
+    last_layer: u32,
+}
+
+#[wasm_bindgen]
+impl TubeMesh {
+    #[wasm_bindgen(getter)]
+    pub fn first_layer(&self) -> u32 {
+        self.first_layer
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn last_layer(&self) -> u32 {
+        self.last_layer
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn vertex_count(&self) -> u32 {
+        (self.positions.len() / 3) as u32
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn positions(&self) -> Vec<f32> {
+        self.positions.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn normals(&self) -> Vec<f32> {
+        self.normals.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn colors(&self) -> Vec<f32> {
+        self.colors.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn ids(&self) -> Vec<u32> {
+        self.ids.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn indices(&self) -> Vec<u32> {
+        self.indices.clone()
+    }
+
+    // Views follow the lifetime contract of the RenderBuffers views
+    #[wasm_bindgen]
+    pub fn positions_view(&self) -> js_sys::Float32Array {
+        unsafe { js_sys::Float32Array::view(&self.positions) }
+    }
+
+    #[wasm_bindgen]
+    pub fn normals_view(&self) -> js_sys::Float32Array {
+        unsafe { js_sys::Float32Array::view(&self.normals) }
+    }
+
+    #[wasm_bindgen]
+    pub fn colors_view(&self) -> js_sys::Float32Array {
+        unsafe { js_sys::Float32Array::view(&self.colors) }
+    }
+
+    #[wasm_bindgen]
+    pub fn ids_view(&self) -> js_sys::Uint32Array {
+        unsafe { js_sys::Uint32Array::view(&self.ids) }
+    }
+
+    #[wasm_bindgen]
+    pub fn indices_view(&self) -> js_sys::Uint32Array {
+        unsafe { js_sys::Uint32Array::view(&self.indices) }
+    }
+}
+
+// Cross-section vertex: offset along (side, up) and its 2D normal
+#[derive(Clone, Copy)]
+struct ProfilePoint {
+    offset: [f32; 2],
+    normal: [f32; 2],
+}
+
+fn profile(settings: &TubeMeshSettings, height: f32) -> Vec<ProfilePoint> {
+    let sides = settings.sides.max(4) as usize;
+    let half_width = settings.width * 0.5;
+    let half_height = height * 0.5;
+    let tau = std::f32::consts::TAU;
+
+    match settings.cross_section {
+        TubeCrossSection::Ellipse => (0..sides)
+            .map(|k| {
+                let (sin, cos) = (tau * k as f32 / sides as f32).sin_cos();
+                let normal = normalize2([cos / half_width.max(1e-6), sin / half_height.max(1e-6)]);
+                ProfilePoint { offset: [half_width * cos, half_height * sin], normal }
+            })
+            .collect(),
+        TubeCrossSection::RoundedRectangle => {
+            // Two semicircles of radius half_height joined by the flat top and bottom
+            let radius = half_height.min(half_width);
+            let center = half_width - radius;
+            // Odd per side so the widest point is on the ring
+            let per_side = (sides / 2).max(3) | 1;
+            let mut points = Vec::with_capacity(per_side * 2);
+            for (side_center, start_angle) in [(center, -0.25 * tau), (-center, 0.25 * tau)] {
+                for k in 0..per_side {
+                    let angle = start_angle + 0.5 * tau * k as f32 / (per_side - 1).max(1) as f32;
+                    let (sin, cos) = angle.sin_cos();
+                    points.push(ProfilePoint {
+                        offset: [side_center + radius * cos, radius * sin],
+                        normal: [cos, sin],
+                    });
+                }
+            }
+            points
+        }
+    }
+}
+
+// Orientation at a path point: side (width) axis, up axis, and the mitre scale on the side axis
+#[derive(Clone, Copy)]
+struct Frame {
+    side: [f32; 3],
+    up: [f32; 3],
+    side_scale: f32,
+}
+
+fn frame(direction: [f32; 3]) -> Frame {
+    let horizontal = (direction[0] * direction[0] + direction[2] * direction[2]).sqrt();
+    let side = if horizontal < MIN_SEGMENT_LENGTH {
+        [0.0, 0.0, 1.0]
+    } else {
+        [-direction[2] / horizontal, 0.0, direction[0] / horizontal]
+    };
+    Frame { side, up: cross(side, direction), side_scale: 1.0 }
+}
+
+// Mitred frame between two directions, or None if the mitre would exceed `max_scale`
+fn miter_frame(incoming: [f32; 3], outgoing: [f32; 3], max_scale: f32) -> Option<Frame> {
+    let a = frame(incoming);
+    let b = frame(outgoing);
+    let sum = [a.side[0] + b.side[0], a.side[1] + b.side[1], a.side[2] + b.side[2]];
+    let length = dot(sum, sum).sqrt();
+    if length < 1e-6 {
+        return None; // Full reversal
+    }
+    let side = [sum[0] / length, sum[1] / length, sum[2] / length];
+    let scale = 1.0 / dot(side, a.side).max(1e-6);
+    if scale > max_scale {
+        return None;
+    }
+    let tangent = normalize3([incoming[0] + outgoing[0], incoming[1] + outgoing[1], incoming[2] + outgoing[2]]);
+    Some(Frame { side, up: cross(side, tangent), side_scale: scale })
+}
+
+// Incremental builder for one chunk
+struct ChunkBuilder<'a> {
+    mesh: TubeMesh,
+    settings: &'a TubeMeshSettings,
+}
+
+impl ChunkBuilder<'_> {
+    fn add_ring(&mut self, center: [f32; 3], frame: &Frame, profile: &[ProfilePoint], color: [f32; 4], id: u32) -> u32 {
+        let first = self.mesh.vertex_count();
+        for point in profile {
+            let side = point.offset[0] * frame.side_scale;
+            let up = point.offset[1];
+            let position = [0, 1, 2].map(|axis| center[axis] + frame.side[axis] * side + frame.up[axis] * up);
+            self.mesh.positions.extend_from_slice(&position);
+            let normal = normalize3([0, 1, 2].map(|axis| frame.side[axis] * point.normal[0] + frame.up[axis] * point.normal[1]));
+            self.mesh.normals.extend_from_slice(&normal);
+            self.mesh.colors.extend_from_slice(&color);
+            self.mesh.ids.push(id);
+        }
+        first
+    }
+
+    // Quads between consecutive rings; the last vertex of each triangle is on ring `a`
+    fn connect(&mut self, a: u32, b: u32, ring_size: u32) {
+        for k in 0..ring_size {
+            let next = (k + 1) % ring_size;
+            self.mesh.indices.extend_from_slice(&[b + k, b + next, a + k]);
+            self.mesh.indices.extend_from_slice(&[b + next, a + next, a + k]);
+        }
+    }
+
+    // Flat cap facing `direction`
+    fn add_cap(&mut self, center: [f32; 3], frame: &Frame, profile: &[ProfilePoint], direction: [f32; 3], color: [f32; 4], id: u32) {
+        let ring_size = profile.len() as u32;
+        let center_index = self.mesh.vertex_count();
+        let mut cap = vec![ProfilePoint { offset: [0.0, 0.0], normal: [0.0, 0.0] }];
+        cap.extend_from_slice(profile);
+        self.add_ring(center, frame, &cap, color, id);
+        for vertex in center_index as usize..(center_index + ring_size + 1) as usize {
+            self.mesh.normals[vertex * 3..vertex * 3 + 3].copy_from_slice(&direction);
+        }
+
+        // Wind outwards: counter-clockwise when viewed along `direction`
+        let facing_forward = dot(cross(frame.side, frame.up), direction) > 0.0;
+        for k in 0..ring_size {
+            let a = center_index + 1 + k;
+            let b = center_index + 1 + (k + 1) % ring_size;
+            if facing_forward {
+                self.mesh.indices.extend_from_slice(&[a, b, center_index]);
+            } else {
+                self.mesh.indices.extend_from_slice(&[b, a, center_index]);
+            }
+        }
+    }
+
+    fn add_path(&mut self, path: &[(usize, Segment)]) {
+        let height = path[0].1.layer_height.max(1e-3);
+        let profile = profile(self.settings, height);
+        let ring_size = profile.len() as u32;
+        let color = path[0].1.color_rgba();
+        let directions: Vec<[f32; 3]> = path.iter().map(|(_, s)| normalize3(sub(s.end, s.start))).collect();
+
+        let start_frame = frame(directions[0]);
+        let first_id = pick_id(path[0].0 as u32);
+        self.add_cap(path[0].1.start, &start_frame, &profile, directions[0].map(|v| -v), color, first_id);
+        let mut previous_ring = self.add_ring(path[0].1.start, &start_frame, &profile, color, first_id);
+
+        for i in 1..path.len() {
+            let point = path[i].1.start;
+            let (incoming, outgoing) = (directions[i - 1], directions[i]);
+            let (incoming_id, outgoing_id) = (pick_id(path[i - 1].0 as u32), pick_id(path[i].0 as u32));
+            let turn = dot(incoming, outgoing).clamp(-1.0, 1.0).acos();
+
+            let miter = match self.settings.joint {
+                TubeJoint::Round if turn > ROUND_JOINT_MIN_ANGLE => None,
+                _ => miter_frame(incoming, outgoing, self.settings.max_miter_scale),
+            };
+
+            if let Some(joint_frame) = miter {
+                let ring = self.add_ring(point, &joint_frame, &profile, color, outgoing_id);
+                self.connect(previous_ring, ring, ring_size);
+                previous_ring = ring;
+            } else {
+                // Rounded elbow: rotate the ring about the joint from the incoming to the outgoing frame
+                let end_ring = self.add_ring(point, &frame(incoming), &profile, color, incoming_id);
+                self.connect(previous_ring, end_ring, ring_size);
+                previous_ring = end_ring;
+
+                let steps = (turn / ROUND_JOINT_STEP).ceil().max(1.0) as u32;
+                for step in 1..=steps {
+                    let t = step as f32 / steps as f32;
+                    let direction = slerp_direction(incoming, outgoing, t);
+                    let ring = self.add_ring(point, &frame(direction), &profile, color, outgoing_id);
+                    self.connect(previous_ring, ring, ring_size);
+                    previous_ring = ring;
+                }
+            }
+        }
+
+        let (last_index, last) = path[path.len() - 1];
+        let last_direction = directions[directions.len() - 1];
+        let end_frame = frame(last_direction);
+        let last_id = pick_id(last_index as u32);
+        let end_ring = self.add_ring(last.end, &end_frame, &profile, color, last_id);
+        self.connect(previous_ring, end_ring, ring_size);
+        self.add_cap(last.end, &end_frame, &profile, last_direction, color, last_id);
+    }
+}
+
+/// Sweep the cross-section along every extrusion path, one mesh per `layers_per_chunk` layers
+pub fn build_tube_meshes(segments: &SegmentStore, settings: &TubeMeshSettings) -> Vec<TubeMesh> {
+    let mut meshes = Vec::new();
+    let mut chunk: Option<ChunkBuilder> = None;
+    let mut path: Vec<(usize, Segment)> = Vec::new();
+
+    for (index, segment) in segments.iter().enumerate() {
+        if !segment.extruding() || segment.length() < MIN_SEGMENT_LENGTH {
+            if !segment.extruding() {
+                flush_path(&mut path, &mut chunk, &mut meshes, settings);
+            }
+            continue;
+        }
+
+        let continues = path.last().is_some_and(|(_, previous)| {
+            previous.layer == segment.layer
+                && previous.color == segment.color
+                && previous.tool == segment.tool
+                && distance(previous.end, segment.start) <= CONTINUITY_EPSILON
+        });
+        if !continues {
+            flush_path(&mut path, &mut chunk, &mut meshes, settings);
+        }
+        path.push((index, segment));
+    }
+    flush_path(&mut path, &mut chunk, &mut meshes, settings);
+
+    if let Some(builder) = chunk {
+        meshes.push(builder.mesh);
+    }
+    meshes
+}
+
+// Add a finished path to its layer chunk, starting a new chunk when the layer range changes
+fn flush_path<'a>(
+    path: &mut Vec<(usize, Segment)>,
+    chunk: &mut Option<ChunkBuilder<'a>>,
+    meshes: &mut Vec<TubeMesh>,
+    settings: &'a TubeMeshSettings,
+) {
+    if path.is_empty() {
+        return;
+    }
+    let layers_per_chunk = settings.layers_per_chunk.max(1);
+    let layer = path[0].1.layer;
+    let chunk_first = layer - layer % layers_per_chunk;
+    if let Some(finished) = chunk.take_if(|c| c.mesh.first_layer != chunk_first) {
+        meshes.push(finished.mesh);
+    }
+    let builder = chunk.get_or_insert_with(|| ChunkBuilder {
+        mesh: TubeMesh { first_layer: chunk_first, last_layer: chunk_first + layers_per_chunk - 1, ..Default::default() },
+        settings,
+    });
+    builder.add_path(path);
+    path.clear();
+}
+
+fn slerp_direction(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3] {
+    let angle = dot(a, b).clamp(-1.0, 1.0).acos();
+    let sin = angle.sin();
+    if sin < 1e-5 {
+        // Parallel or reversed: no unique arc, snap to the nearer end
+        return if t < 0.5 { a } else { b };
+    }
+    let wa = ((1.0 - t) * angle).sin() / sin;
+    let wb = (t * angle).sin() / sin;
+    normalize3([a[0] * wa + b[0] * wb, a[1] * wa + b[1] * wb, a[2] * wa + b[2] * wb])
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    let d = sub(a, b);
+    dot(d, d).sqrt()
+}
+
+fn normalize2(v: [f32; 2]) -> [f32; 2] {
+    let length = (v[0] * v[0] + v[1] * v[1]).sqrt().max(1e-12);
+    [v[0] / length, v[1] / length]
+}
+
+fn normalize3(v: [f32; 3]) -> [f32; 3] {
+    let length = dot(v, v).sqrt().max(1e-12);
+    [v[0] / length, v[1] / length, v[2] / length]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gcode_line::Color4;
+    use crate::segment_store::{pack_color, FLAG_EXTRUDING};
+
+    fn segment(start: [f32; 2], end: [f32; 2], height: f32, file_position: u32) -> Segment {
+        Segment {
+            start: [start[0], height, start[1]],
+            end: [end[0], height, end[1]],
+            feed_rate: 1800.0,
+            layer_height: 0.2,
+            width: 0.4,
+            color: pack_color(&Color4::new(1.0, 0.0, 0.0, 1.0)),
+            flags: FLAG_EXTRUDING,
+            tool: 0,
+            feature: 0,
+            layer: 0,
+            line_number: file_position / 10,
+            file_position,
+            file_end_position: file_position + 9,
+        }
+    }
+
+    fn corner_store() -> SegmentStore {
+        let mut store = SegmentStore::new();
+        store.push(segment([0.0, 0.0], [10.0, 0.0], 0.2, 0));
+        store.push(segment([10.0, 0.0], [10.0, 10.0], 0.2, 10));
+        store
+    }
+
+    #[test]
+    fn test_miter_corner_shares_ring() {
+        let settings = TubeMeshSettings { cross_section: TubeCrossSection::Ellipse, sides: 8, ..Default::default() };
+        let meshes = build_tube_meshes(&corner_store(), &settings);
+        assert_eq!(meshes.len(), 1);
+        let mesh = &meshes[0];
+
+        // Start cap (9) + start ring + shared corner ring + end ring + end cap (9)
+        assert_eq!(mesh.vertex_count(), 9 + 8 + 8 + 8 + 9);
+        // Two tube sections of 8 quads plus two 8-triangle caps
+        assert_eq!(mesh.indices.len(), (2 * 8 * 2 + 2 * 8) * 3);
+        assert!(mesh.indices.iter().all(|&i| i < mesh.vertex_count()));
+
+        // Corner ring is mitred: the outer side vertex sits sqrt(2) * half width from the corner
+        let ring = (9 + 8) * 3;
+        let corner = [mesh.positions[ring], mesh.positions[ring + 1], mesh.positions[ring + 2]];
+        let offset = distance(corner, [10.0, 0.2, 0.0]);
+        assert!((offset - 0.2 * 2f32.sqrt()).abs() < 1e-4);
+
+        // Normals are unit length; the last vertex of each tube triangle carries its segment's pick ID
+        for normal in mesh.normals.chunks(3).skip(9) {
+            assert!((dot([normal[0], normal[1], normal[2]], [normal[0], normal[1], normal[2]]) - 1.0).abs() < 1e-4);
+        }
+        let first_section = &mesh.indices[8 * 3..(8 + 16) * 3];
+        assert!(first_section.chunks(3).all(|t| mesh.ids[t[2] as usize] == pick_id(0)));
+        let second_section = &mesh.indices[(8 + 16) * 3..(8 + 32) * 3];
+        assert!(second_section.chunks(3).all(|t| mesh.ids[t[2] as usize] == pick_id(1)));
+    }
+
+    #[test]
+    fn test_round_joint_and_rounded_rectangle() {
+        let settings = TubeMeshSettings { joint: TubeJoint::Round, sides: 10, ..Default::default() };
+        let mesh = &build_tube_meshes(&corner_store(), &settings)[0];
+        // A 90 degree turn adds the incoming ring plus four rotated rings
+        assert_eq!(mesh.vertex_count(), 11 + 10 + 10 + 4 * 10 + 10 + 11);
+
+        // Rounded rectangle spans the full width and layer height
+        let ring = &mesh.positions[11 * 3..(11 + 10) * 3];
+        let zs: Vec<f32> = ring.chunks(3).map(|p| p[2]).collect();
+        let ys: Vec<f32> = ring.chunks(3).map(|p| p[1]).collect();
+        let width = zs.iter().cloned().fold(f32::MIN, f32::max) - zs.iter().cloned().fold(f32::MAX, f32::min);
+        let height = ys.iter().cloned().fold(f32::MIN, f32::max) - ys.iter().cloned().fold(f32::MAX, f32::min);
+        assert!((width - 0.4).abs() < 1e-4);
+        assert!((height - 0.2).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_paths_split_and_chunk_by_layer() {
+        let mut store = SegmentStore::new();
+        store.push(segment([0.0, 0.0], [10.0, 0.0], 0.2, 0));
+        let mut travel = segment([10.0, 0.0], [0.0, 5.0], 0.2, 10);
+        travel.flags = 0;
+        store.push(travel);
+        store.push(segment([0.0, 5.0], [10.0, 5.0], 0.2, 20));
+        store.push(segment([0.0, 0.0], [10.0, 0.0], 0.4, 30)); // Layer 1
+
+        let settings = TubeMeshSettings { layers_per_chunk: 1, sides: 6, ..Default::default() };
+        let meshes = build_tube_meshes(&store, &settings);
+        assert_eq!(meshes.len(), 2);
+        assert_eq!((meshes[1].first_layer, meshes[1].last_layer), (1, 1));
+        // Layer 0 has two separate paths of one segment each
+        assert_eq!(meshes[0].vertex_count(), 2 * (7 + 6 + 6 + 7));
+        // The travel (segment 1) is not part of any path, and no vertex uses the background ID
+        assert!(!meshes[0].ids.contains(&pick_id(1)));
+        assert!(meshes[0].ids.iter().all(|&id| id != 0));
+    }
+}
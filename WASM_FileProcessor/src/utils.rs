@@ -3,6 +3,7 @@
 // by avoiding string allocations and using direct byte manipulation
 
 use crate::gcode_line::Vector3;
+use crate::error::{ErrorKind, ProcessingError};
 
 #[derive(Debug, Clone, Copy)]
 pub struct ParseResult {
@@ -279,6 +280,15 @@ pub fn to_uppercase_ascii(s: &str) -> String {
         .collect()
 }
 
+/// Lines paired with their byte length including the terminator (`\n` or `\r\n`), so file
+/// positions stay exact on CRLF files; the text is stripped like `str::lines`
+pub fn lines_with_length(text: &str) -> impl Iterator<Item = (&str, u32)> {
+    text.split_inclusive('\n').map(|raw| {
+        let line = raw.strip_suffix('\n').map_or(raw, |line| line.strip_suffix('\r').unwrap_or(line));
+        (line, raw.len() as u32)
+    })
+}
+
 /// Skip whitespace characters and return new position
 pub fn skip_whitespace(bytes: &[u8], mut pos: usize) -> usize {
     while pos < bytes.len() && (bytes[pos] == b' ' || bytes[pos] == b'\t') {
@@ -288,13 +298,42 @@ pub fn skip_whitespace(bytes: &[u8], mut pos: usize) -> usize {
 }
 
 /// Parse number from string (wrapper for compatibility)
-pub fn parse_number_from_str(line: &str, start_pos: usize) -> Result<(f64, usize), String> {
+pub fn parse_number_from_str(line: &str, start_pos: usize) -> Result<(f64, usize), ProcessingError> {
     let bytes = line.as_bytes();
     if let Some(result) = parse_number_fast(bytes, start_pos) {
         Ok((result.value, start_pos + result.consumed_bytes))
     } else {
-        Err("Failed to parse number".to_string())
+        Err(ProcessingError::new(ErrorKind::MalformedNumber, "Failed to parse number").with_context(line))
+    }
+}
+
+/// Decode standard (RFC 4648) base64, ignoring whitespace; padding is optional
+pub fn decode_base64(input: &[u8]) -> Result<Vec<u8>, String> {
+    let mut output = Vec::with_capacity(input.len() * 3 / 4);
+    let mut buffer: u32 = 0;
+    let mut bits = 0u32;
+    
+    for &byte in input {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            b' ' | b'\t' | b'\r' | b'\n' => continue,
+            _ => return Err(format!("Invalid base64 character: {}", byte as char)),
+        };
+        
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+        }
     }
+    
+    Ok(output)
 }
 
 /// Arc tessellation result containing intermediate points
@@ -327,7 +366,7 @@ pub fn tessellate_arc(
     fix_radius: bool,
     relative_move: bool,
     workplace_offset: Vector3,
-) -> Result<ArcResult, String> {
+) -> Result<ArcResult, ProcessingError> {
     
     let mut current = current_position;
     let mut target = target_position;
@@ -385,7 +424,8 @@ pub fn tessellate_arc(
                     h_squared = min_r * min_r - d_squared / 4.0;
                     h_div_d = (h_squared / d_squared).sqrt();
                 } else {
-                    return Err("G2/G3: Radius too small".to_string());
+                    return Err(ProcessingError::new(ErrorKind::ArcRadiusTooSmall, "G2/G3: Radius too small")
+                        .with_context(&format!("R{} for a {:.3}mm chord", r, d_squared.sqrt())));
                 }
             }
         }
@@ -550,6 +590,15 @@ mod tests {
         assert!(!is_comment_line("M104 S200"));
     }
     
+    #[test]
+    fn test_decode_base64() {
+        assert_eq!(decode_base64(b"TWFu").unwrap(), b"Man");
+        assert_eq!(decode_base64(b"TWE=").unwrap(), b"Ma");
+        assert_eq!(decode_base64(b"TQ==").unwrap(), b"M");
+        assert_eq!(decode_base64(b"iVBO\nRw0K").unwrap(), vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A]);
+        assert!(decode_base64(b"T*==").is_err());
+    }
+    
     #[test]
     fn test_detect_gcode_command() {
         assert_eq!(detect_gcode_command("G0 X10 Y20"), Some("G0"));
@@ -559,4 +608,17 @@ mod tests {
         assert_eq!(detect_gcode_command("; comment"), None);
         assert_eq!(detect_gcode_command(""), None);
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_arc_radius_too_small() {
+        let origin = Vector3 { x: 0.0, y: 0.0, z: 0.0 };
+        let target = Vector3 { x: 10.0, y: 0.0, z: 0.0 };
+        let arc = |fix_radius| tessellate_arc(origin.clone(), target.clone(), 0.0, 0.0, None, Some(2.0), true,
+                                               ArcPlane::XY, 0.5, fix_radius, false, origin.clone());
+        
+        let error = arc(false).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::ArcRadiusTooSmall);
+        assert_eq!(error.context(), "R2 for a 10.000mm chord");
+        assert!(arc(true).is_ok());
+    }
+}